@@ -1,4 +1,5 @@
 import React, { useState, useRef, useEffect, useCallback } from "react";
+import { decode } from "@msgpack/msgpack";
 
 // Windowインターフェースを拡張して、webkitAudioContextの型定義を追加
 // これにより、(window as any) を使わずに型安全なアクセスが可能になります。
@@ -85,12 +86,152 @@ const styles: { [key: string]: React.CSSProperties } = {
 /**
  * @type AudioInfo
  * @description サーバーから受信するオーディオ情報の型定義
- * @property {number} channel - チャンネル数 (e.g., 1 for mono, 2 for stereo)
+ * @property {number} channels - チャンネル数 (e.g., 1 for mono, 2 for stereo)
  * @property {number} sample_rate - サンプルレート (e.g., 44100)
+ * @property {number} bits_per_sample - 量子化ビット数 (e.g., 16)
+ * @property {string} pcm_format - サンプル形式 ("int" | "float")
  */
 type AudioInfo = {
-  channel: number;
+  channels: number;
   sample_rate: number;
+  bits_per_sample: number;
+  pcm_format: "int" | "float";
+};
+
+// 制御プロトコルのバージョン (protocol/src/models/protocol.rs の PROTOCOL_VERSION と一致させる)
+const PROTOCOL_VERSION = 2;
+// 4000番台のクローズコードはアプリケーション固有 (protocol/src/models/protocol.rs を参照)
+const CLOSE_ERROR_BASE = 4000;
+
+/**
+ * @type ControlMessage
+ * @description テキストフレームで送受信するJSON制御メッセージ ("type" で判別する)
+ */
+type ControlMessage =
+  | { type: "hello"; version: number; upstream?: string; analyzers?: string[] }
+  | { type: "open"; track_id?: string }
+  | ({ type: "audio-info"; seekable?: boolean } & AudioInfo)
+  | { type: "accept" }
+  | { type: "transport"; command: "pause" | "resume" | "stop" }
+  | { type: "end-of-stream"; reason: "finished" | "stopped" }
+  | { type: "error"; status: number; message: string }
+  | { type: "upstream"; status: "reconnecting"; attempt: number; retry_in_ms: number }
+  | { type: "upstream"; status: "reconnected"; resume_frame?: number };
+
+const sendControlMessage = (ws: WebSocket, message: ControlMessage) => {
+  ws.send(JSON.stringify(message));
+};
+
+/**
+ * @type FrameHeader
+ * @description 各PCMチャンクの先頭に付く32バイトのヘッダー (protocol/src/models/frame.rs を参照)
+ */
+type FrameHeader = {
+  stream_id: number;
+  sequence: number;
+  frames: number;
+  first_frame: number;
+  timestamp_us: number;
+  info: AudioInfo;
+};
+
+const FRAME_HEADER_SIZE = 32;
+const FRAME_HEADER_VERSION = 1;
+
+/**
+ * @type MessagePack
+ * @description アナライザーを動かす middle-server が送るウィンドウ (middle-server/src/models/packet.rs を参照)
+ */
+type MessagePack = {
+  pcm: Uint8Array;
+  bpm: number | null;
+  features: Record<string, { bpm?: number }>;
+};
+
+/**
+ * @function unpackWindow
+ * @description 生のウィンドウはそのまま、MessagePack はウィンドウとBPMに分解する
+ */
+const unpackWindow = (
+  data: ArrayBuffer
+): { window: ArrayBuffer; bpm: number | null } => {
+  // 生のウィンドウはヘッダーのバージョンで始まり、MessagePack は fixmap (0x8X) で始まる
+  if (new Uint8Array(data)[0] === FRAME_HEADER_VERSION) {
+    return { window: data, bpm: null };
+  }
+  const message = decode(data) as MessagePack;
+  return { window: message.pcm.slice().buffer, bpm: message.bpm ?? null };
+};
+
+/**
+ * @function decodeFrames
+ * @description ウィンドウ (ヘッダー付きPCMの連結、middle-server からは1つ) を分解してFloat32Arrayへ変換する
+ */
+const decodeFrames = (
+  data: ArrayBuffer,
+  sampleRate: number
+): { header: FrameHeader; samples: Float32Array }[] => {
+  const frames = [];
+  let offset = 0;
+  while (offset + FRAME_HEADER_SIZE <= data.byteLength) {
+    const view = new DataView(data, offset, FRAME_HEADER_SIZE);
+    const formatTag = view.getUint8(1);
+    const info: AudioInfo = {
+      channels: view.getUint16(2, true),
+      sample_rate: sampleRate,
+      bits_per_sample: formatTag & 0x7f,
+      pcm_format: formatTag & 0x80 ? "float" : "int",
+    };
+    const header: FrameHeader = {
+      stream_id: view.getUint32(4, true),
+      sequence: view.getUint32(8, true),
+      frames: view.getUint32(12, true),
+      first_frame: Number(view.getBigUint64(16, true)),
+      timestamp_us: Number(view.getBigUint64(24, true)),
+      info,
+    };
+    const payloadSize =
+      header.frames * info.channels * Math.ceil(info.bits_per_sample / 8);
+    const payload = new DataView(
+      data,
+      offset + FRAME_HEADER_SIZE,
+      payloadSize
+    );
+    frames.push({ header, samples: decodePcm(payload, info) });
+    offset += FRAME_HEADER_SIZE + payloadSize;
+  }
+  return frames;
+};
+
+/**
+ * @function decodePcm
+ * @description リトルエンディアンのPCMバイナリをAudioInfoの形式に従ってFloat32Arrayへ変換する
+ */
+const decodePcm = (view: DataView, info: AudioInfo): Float32Array => {
+  const bytesPerSample = Math.ceil(info.bits_per_sample / 8);
+  const sampleCount = Math.floor(view.byteLength / bytesPerSample);
+  const samples = new Float32Array(sampleCount);
+
+  if (info.pcm_format === "float") {
+    for (let i = 0; i < sampleCount; i++) {
+      samples[i] = view.getFloat32(i * 4, true);
+    }
+    return samples;
+  }
+
+  const scale = 2 ** (bytesPerSample * 8 - 1);
+  for (let i = 0; i < sampleCount; i++) {
+    const offset = i * bytesPerSample;
+    let value = 0;
+    for (let b = 0; b < bytesPerSample; b++) {
+      value |= view.getUint8(offset + b) << (8 * b);
+    }
+    // sign extension
+    const shift = 32 - bytesPerSample * 8;
+    value = (value << shift) >> shift;
+    samples[i] = value / scale;
+  }
+  return samples;
 };
 
 // 再生を開始するために必要なバッファの数を定義
@@ -99,10 +240,13 @@ const PLAYBACK_BUFFER_THRESHOLD = 5;
 const App: React.FC = () => {
   // --- State Hooks ---
   const [url, setUrl] = useState<string>("ws://localhost:7001");
+  const [trackId, setTrackId] = useState<string>("");
+  const [upstream, setUpstream] = useState<string>("");
   const [isConnected, setIsConnected] = useState<boolean>(false);
   const [statusMessage, setStatusMessage] = useState<string>("未接続");
   const [audioInfo, setAudioInfo] = useState<AudioInfo | null>(null);
   const [bufferSize, setBufferSize] = useState<number>(0);
+  const [bpm, setBpm] = useState<number | null>(null);
 
   // --- Ref Hooks ---
   const webSocketRef = useRef<WebSocket | null>(null);
@@ -110,6 +254,7 @@ const App: React.FC = () => {
   const pcmBufferRef = useRef<Float32Array[]>([]);
   const isPlayingRef = useRef<boolean>(false);
   const audioInfoRef = useRef<AudioInfo | null>(null);
+  const lastFrameRef = useRef<FrameHeader | null>(null);
 
   /**
    * @function handleDisconnect
@@ -127,12 +272,27 @@ const App: React.FC = () => {
     setIsConnected(false);
     setAudioInfo(null);
     audioInfoRef.current = null;
+    lastFrameRef.current = null;
     setStatusMessage("切断されました");
     setBufferSize(0);
+    setBpm(null);
     pcmBufferRef.current = [];
     isPlayingRef.current = false;
   }, []);
 
+  /**
+   * @function sendTransportCommand
+   * @description 再生制御コマンド (pause / resume / stop) をサーバーへ送信する
+   */
+  const sendTransportCommand = useCallback(
+    (command: "pause" | "resume" | "stop") => {
+      if (webSocketRef.current?.readyState === WebSocket.OPEN) {
+        sendControlMessage(webSocketRef.current, { type: "transport", command });
+      }
+    },
+    []
+  );
+
   /**
    * @function playNextChunk
    * @description pcmBufferから次のオーディオチャンクを再生する。
@@ -158,20 +318,20 @@ const App: React.FC = () => {
     const pcmData = pcmBufferRef.current.shift()!;
     setBufferSize(pcmBufferRef.current.length);
 
-    const frameCount = pcmData.length / currentAudioInfo.channel;
+    const frameCount = pcmData.length / currentAudioInfo.channels;
     const audioBuffer = audioContext.createBuffer(
-      currentAudioInfo.channel,
+      currentAudioInfo.channels,
       frameCount,
       currentAudioInfo.sample_rate
     );
 
-    if (currentAudioInfo.channel === 1) {
+    if (currentAudioInfo.channels === 1) {
       audioBuffer.copyToChannel(pcmData, 0);
     } else {
-      for (let ch = 0; ch < currentAudioInfo.channel; ch++) {
+      for (let ch = 0; ch < currentAudioInfo.channels; ch++) {
         const channelData = new Float32Array(frameCount);
         for (let i = 0; i < frameCount; i++) {
-          channelData[i] = pcmData[i * currentAudioInfo.channel + ch];
+          channelData[i] = pcmData[i * currentAudioInfo.channels + ch];
         }
         audioBuffer.copyToChannel(channelData, ch);
       }
@@ -236,29 +396,66 @@ const App: React.FC = () => {
 
       ws.onopen = () => {
         setIsConnected(true);
-        setStatusMessage('接続成功。 "open" を送信します...');
-        ws.send("open");
+        setStatusMessage(`接続成功。 "hello" を送信します...`);
+        // 中継サーバーの接続先 (空の場合は middle-server のデフォルト)
+        sendControlMessage(ws, {
+          type: "hello",
+          version: PROTOCOL_VERSION,
+          upstream: upstream.trim() || undefined,
+        });
       };
 
       ws.onmessage = (event: MessageEvent) => {
         if (typeof event.data === "string") {
           setStatusMessage(`サーバーからメッセージ受信: ${event.data}`);
-          const parts = event.data.split(" ");
-          if (parts.length === 2) {
-            const channel = parseInt(parts[0], 10);
-            const sample_rate = parseInt(parts[1], 10);
-
-            if (!isNaN(channel) && !isNaN(sample_rate)) {
-              const newAudioInfo = { channel, sample_rate };
+          let message: ControlMessage;
+          try {
+            message = JSON.parse(event.data) as ControlMessage;
+          } catch {
+            setStatusMessage(`エラー: 不正な制御メッセージです: ${event.data}`);
+            return;
+          }
+          switch (message.type) {
+            case "hello": {
+              // トラックIDが空の場合はサーバーのデフォルトトラックを再生する
+              const track_id = trackId.trim() || undefined;
+              setStatusMessage(`プロトコル v${message.version}。 "open" を送信します...`);
+              sendControlMessage(ws, { type: "open", track_id });
+              break;
+            }
+            case "audio-info": {
+              const newAudioInfo: AudioInfo = {
+                channels: message.channels,
+                sample_rate: message.sample_rate,
+                bits_per_sample: message.bits_per_sample,
+                pcm_format: message.pcm_format,
+              };
               setAudioInfo(newAudioInfo);
               audioInfoRef.current = newAudioInfo;
               setStatusMessage('AudioInfo 受信完了。"accept" を送信します。');
-              ws.send("accept");
-            } else {
-              setStatusMessage(
-                `エラー: 不正なAudioInfo形式です: ${event.data}`
-              );
+              sendControlMessage(ws, { type: "accept" });
+              break;
             }
+            case "end-of-stream":
+              setStatusMessage(`ストリーム終了: ${message.reason}`);
+              break;
+            case "error":
+              setStatusMessage(`エラー (${message.status}): ${message.message}`);
+              break;
+            // 中継サーバーが上流サーバーへ再接続している間も接続は維持される
+            case "upstream":
+              if (message.status === "reconnecting") {
+                setStatusMessage(
+                  `上流サーバーに再接続中... (${message.attempt}回目, ${message.retry_in_ms}ms後)`
+                );
+              } else {
+                setStatusMessage(
+                  message.resume_frame === undefined
+                    ? "上流サーバーに再接続しました"
+                    : `上流サーバーに再接続しました (フレーム ${message.resume_frame} から再開)`
+                );
+              }
+              break;
           }
         } else if (event.data instanceof ArrayBuffer) {
           if (!audioInfoRef.current) {
@@ -269,13 +466,38 @@ const App: React.FC = () => {
             return;
           }
 
-          const pcmDataInt16 = new Int16Array(event.data);
-          const pcmDataFloat32 = new Float32Array(pcmDataInt16.length);
-          for (let i = 0; i < pcmDataInt16.length; i++) {
-            pcmDataFloat32[i] = pcmDataInt16[i] / 32768.0;
+          const { window: windowData, bpm } = unpackWindow(event.data);
+          if (bpm !== null) {
+            setBpm(bpm);
           }
 
-          pcmBufferRef.current.push(pcmDataFloat32);
+          for (const { header, samples } of decodeFrames(
+            windowData,
+            audioInfoRef.current.sample_rate
+          )) {
+            // ウィンドウは重なり得るので、再生済みのフレームを飛ばす (first_frame で判定する)
+            const last = lastFrameRef.current;
+            let skip = 0;
+            if (last) {
+              const playedEnd = last.first_frame + last.frames;
+              if (header.first_frame > playedEnd) {
+                console.warn(
+                  `frames ${playedEnd}-${header.first_frame} are missing`
+                );
+              }
+              // 前へ戻った (シーク・ループ) ウィンドウはそのまま再生する
+              if (header.first_frame >= last.first_frame) {
+                skip = Math.max(0, playedEnd - header.first_frame);
+              }
+            }
+            if (skip >= header.frames) {
+              continue;
+            }
+            lastFrameRef.current = header;
+            pcmBufferRef.current.push(
+              samples.subarray(skip * header.info.channels)
+            );
+          }
           const currentBufferSize = pcmBufferRef.current.length;
           setBufferSize(currentBufferSize);
           setStatusMessage(
@@ -291,9 +513,16 @@ const App: React.FC = () => {
         }
       };
 
-      ws.onclose = () => {
-        console.log("WebSocket connection closed.");
+      ws.onclose = (event) => {
+        // 4000: idle timeout, 4001: handshake timeout, 4002: server lost,
+        // 4000 + HTTP status: error (see protocol.rs)
+        console.log(
+          `WebSocket connection closed: ${event.code} ${event.reason}`
+        );
         handleDisconnect();
+        if (event.code >= CLOSE_ERROR_BASE) {
+          setStatusMessage(`切断されました (${event.code}): ${event.reason}`);
+        }
       };
 
       ws.onerror = (error) => {
@@ -314,7 +543,7 @@ const App: React.FC = () => {
     return () => {
       handleDisconnect();
     };
-  }, [handleDisconnect]);
+  }, [handleDisconnect, trackId, upstream]);
 
   return (
     <div style={styles.container}>
@@ -329,6 +558,22 @@ const App: React.FC = () => {
           style={styles.input}
           placeholder="例: ws://localhost:8080"
         />
+        <input
+          type="text"
+          value={trackId}
+          onChange={(e) => setTrackId(e.target.value)}
+          disabled={isConnected}
+          style={styles.input}
+          placeholder="トラックID (例: sample3)"
+        />
+        <input
+          type="text"
+          value={upstream}
+          onChange={(e) => setUpstream(e.target.value)}
+          disabled={isConnected}
+          style={styles.input}
+          placeholder="接続先サーバー (例: main)"
+        />
         <button
           onClick={isConnected ? handleDisconnect : handleConnect}
           style={{
@@ -338,6 +583,16 @@ const App: React.FC = () => {
         >
           {isConnected ? "切断" : "接続"}
         </button>
+        {(["pause", "resume", "stop"] as const).map((command) => (
+          <button
+            key={command}
+            onClick={() => sendTransportCommand(command)}
+            disabled={!isConnected}
+            style={styles.button}
+          >
+            {command}
+          </button>
+        ))}
       </div>
 
       <div style={styles.statusPanel}>
@@ -353,12 +608,17 @@ const App: React.FC = () => {
           <span style={styles.statusLabel}>オーディオ情報:</span>
           <span style={styles.statusValue}>
             {audioInfo
-              ? `${audioInfo.channel}ch @ ${audioInfo.sample_rate}Hz`
+              ? `${audioInfo.channels}ch @ ${audioInfo.sample_rate}Hz`
               : "N/A"}
           </span>
 
           <span style={styles.statusLabel}>再生バッファサイズ:</span>
           <span style={styles.statusValue}>{bufferSize}</span>
+
+          <span style={styles.statusLabel}>BPM:</span>
+          <span style={styles.statusValue}>
+            {bpm !== null ? bpm.toFixed(1) : "N/A"}
+          </span>
         </div>
       </div>
     </div>
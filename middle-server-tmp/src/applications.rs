@@ -1,4 +0,0 @@
-pub mod client_to_server;
-pub mod pcm;
-pub mod server_to_client;
-pub mod window;
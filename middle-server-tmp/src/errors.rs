@@ -1,3 +0,0 @@
-pub mod app;
-pub mod handler;
-pub mod root;
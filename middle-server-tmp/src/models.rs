@@ -1,4 +0,0 @@
-pub mod audio;
-pub mod packet;
-pub mod shared_state;
-pub mod ws;
@@ -1 +0,0 @@
-pub type RwLockSharedState = std::sync::Arc<tokio::sync::RwLock<i32>>;
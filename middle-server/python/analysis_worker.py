@@ -0,0 +1,54 @@
+"""
+The analysis worker of the middle-server (see middle-server/src/models/python_worker.rs).
+
+The relay starts it with `--analyzers librosa-worker` and talks to it over stdin and stdout,
+so a crash or a hang of librosa only takes this process down.
+Needs numpy, librosa and msgpack.
+
+FORMAT: every message is [u32 big endian: length | MessagePack map]
+    worker -> relay, once started: {"ready": true, "librosa": <version>}
+    relay -> worker: {"id": <int>, "sample_rate": <float>, "samples": <bin: mono float32 little endian>}
+    worker -> relay: {"id": <int>, "features": {"bpm": <float>}} | {"id": <int>, "error": <str>}
+"""
+
+import struct
+import sys
+
+import librosa
+import msgpack
+import numpy
+
+
+def read_message(stream):
+    header = stream.read(4)
+    if len(header) < 4:
+        return None
+    (length,) = struct.unpack(">I", header)
+    return msgpack.unpackb(stream.read(length))
+
+
+def write_message(stream, message):
+    body = msgpack.packb(message, use_bin_type=True)
+    stream.write(struct.pack(">I", len(body)) + body)
+    stream.flush()
+
+
+def main():
+    requests, replies = sys.stdin.buffer, sys.stdout.buffer
+    # stdout carries the messages only, prints end up in the log of the relay
+    sys.stdout = sys.stderr
+
+    write_message(replies, {"ready": True, "librosa": librosa.__version__})
+    while (request := read_message(requests)) is not None:
+        try:
+            samples = numpy.frombuffer(request["samples"], dtype="<f4")
+            tempo, _beats = librosa.beat.beat_track(y=samples, sr=request["sample_rate"])
+            # librosa >= 0.10 returns the tempo as an array
+            reply = {"id": request["id"], "features": {"bpm": float(numpy.mean(tempo))}}
+        except Exception as e:
+            reply = {"id": request["id"], "error": repr(e)}
+        write_message(replies, reply)
+
+
+if __name__ == "__main__":
+    main()
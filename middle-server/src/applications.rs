@@ -1,3 +1,11 @@
+pub mod analysis;
 pub mod client_to_server;
+pub mod keepalive;
+#[cfg(feature = "python-analysis")]
+pub mod librosa;
 pub mod pcm;
+pub mod room;
 pub mod server_to_client;
+pub mod tempo;
+pub mod upstream;
+pub mod window;
@@ -0,0 +1,128 @@
+use crate::{
+    applications::tempo::tempo_detector,
+    errors::analysis::AnalysisError,
+    models::{
+        analysis::{Analyzer, AnalyzerRegistry, DecodedWindow, Features},
+        python_worker::{PythonWorkerConfig, PythonWorkerPool},
+    },
+};
+use protocol::models::audio::AudioInfo;
+use std::sync::Arc;
+
+//* constant values *//
+static MOCK_BPM: f64 = 120.0;
+
+/// The tempo in Rust (see tempo_detector).
+pub struct NativeAnalyzer;
+
+impl Analyzer for NativeAnalyzer {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn analyze(
+        &self,
+        window: &DecodedWindow,
+        audio_info: &AudioInfo,
+    ) -> Result<Features, AnalysisError> {
+        if audio_info.sample_rate == 0 {
+            return Err(AnalysisError::InvalidWindowError(
+                "the sample rate is 0".into(),
+            ));
+        }
+        Ok(Features {
+            bpm: Some(tempo_detector(
+                &window.samples,
+                audio_info.sample_rate as f64,
+            )),
+        })
+    }
+}
+
+/// The tempo with librosa through pyo3 (see pcm_detector).
+#[cfg(feature = "python-analysis")]
+pub struct LibrosaAnalyzer;
+
+#[cfg(feature = "python-analysis")]
+impl Analyzer for LibrosaAnalyzer {
+    fn name(&self) -> &'static str {
+        "librosa"
+    }
+
+    fn analyze(
+        &self,
+        window: &DecodedWindow,
+        audio_info: &AudioInfo,
+    ) -> Result<Features, AnalysisError> {
+        use crate::applications::librosa::pcm_detector;
+
+        let bpm = pyo3::Python::attach(|py| {
+            pcm_detector(py, window.samples.clone(), audio_info.sample_rate as f64)
+        })?;
+        Ok(Features { bpm: Some(bpm) })
+    }
+}
+
+/// The tempo with librosa in supervised Python processes (see PythonWorkerPool),
+/// which needs Python with librosa but not the python-analysis feature.
+pub struct LibrosaWorkerAnalyzer {
+    workers: PythonWorkerPool,
+}
+
+impl LibrosaWorkerAnalyzer {
+    pub fn new(config: PythonWorkerConfig) -> Self {
+        LibrosaWorkerAnalyzer {
+            workers: PythonWorkerPool::new(config),
+        }
+    }
+}
+
+impl Analyzer for LibrosaWorkerAnalyzer {
+    fn name(&self) -> &'static str {
+        "librosa-worker"
+    }
+
+    fn analyze(
+        &self,
+        window: &DecodedWindow,
+        audio_info: &AudioInfo,
+    ) -> Result<Features, AnalysisError> {
+        self.workers
+            .analyze(&window.samples, audio_info.sample_rate as f64)
+    }
+
+    fn health(&self) -> Option<String> {
+        Some(format!("{:?}", self.workers.health()))
+    }
+}
+
+/// The same features for every window whatever it holds, for tests and deployments without an analysis host.
+pub struct MockAnalyzer;
+
+impl Analyzer for MockAnalyzer {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn analyze(
+        &self,
+        _window: &DecodedWindow,
+        _audio_info: &AudioInfo,
+    ) -> Result<Features, AnalysisError> {
+        Ok(Features {
+            bpm: Some(MOCK_BPM),
+        })
+    }
+}
+
+/// The analyzers built into this build of the relay.
+pub fn analyzer_registry(python_worker: PythonWorkerConfig) -> AnalyzerRegistry {
+    let mut registry = AnalyzerRegistry::default();
+    registry.register(Arc::new(NativeAnalyzer));
+    #[cfg(feature = "python-analysis")]
+    registry.register(Arc::new(LibrosaAnalyzer));
+    // the workers only start once a room selects the analyzer
+    registry.register(Arc::new(LibrosaWorkerAnalyzer::new(python_worker)));
+    registry.register(Arc::new(MockAnalyzer));
+    registry
+}
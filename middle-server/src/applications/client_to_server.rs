@@ -1,55 +1,218 @@
 use crate::{
+    applications::{
+        keepalive::{close_client, ping_client},
+        room::{room_forwarder, room_joiner, room_leaver},
+    },
     errors::handler::HandlerError,
-    models::ws::{WebSocketClientReader, WebSocketServerWriter},
+    models::{
+        analysis::Analyzers,
+        client_queue::ClientQueue,
+        room::{MutexRoomRegistry, MutexSubscription, RoomConfig},
+        upstream::Upstream,
+    },
 };
 use axum::extract::ws::Message;
-use futures_util::{SinkExt, StreamExt};
-use tokio_tungstenite::tungstenite;
+use futures_util::StreamExt;
+use protocol::{
+    errors::app::AppError,
+    models::{
+        keepalive::{Keepalive, KeepaliveConfig},
+        protocol::{ControlMessage, EndOfStreamReason, PROTOCOL_VERSION},
+        transport::TransportCommand,
+        ws::{MutexWebSocketClientWriter, WebSocketClientReader},
+    },
+};
+use std::sync::Arc;
+
+/// The hello of the client.
+pub struct ClientHello {
+    /// The upstream the client asked for.
+    pub upstream: Option<String>,
+    /// The analyzers the client asked for.
+    pub analyzers: Option<Vec<String>>,
+}
 
-// [task1] client -> server
+/// Waits for the hello of the client, which names the upstream to relay to and the analyzers to run.
+///
+/// Returns `None` if the client leaves before saying hello.
+pub async fn hello_receiver(
+    client_reader: &mut WebSocketClientReader,
+) -> Result<Option<ClientHello>, HandlerError> {
+    while let Some(Ok(message)) = client_reader.next().await {
+        match message {
+            Message::Text(text) => {
+                let message = ControlMessage::try_from(text.as_str()).map_err(|e| {
+                    HandlerError::InvalidControlMessageError(format!(
+                        "expected a JSON control message of protocol version {PROTOCOL_VERSION} ({e})"
+                    ))
+                })?;
+                return match message {
+                    ControlMessage::Hello { version, .. } if version != PROTOCOL_VERSION => {
+                        Err(HandlerError::ProtocolVersionError(version))
+                    }
+                    ControlMessage::Hello {
+                        upstream,
+                        analyzers,
+                        ..
+                    } => Ok(Some(ClientHello {
+                        upstream,
+                        analyzers,
+                    })),
+                    message => Err(HandlerError::UnexpectedMessageError(format!(
+                        "{} before hello",
+                        message.kind()
+                    ))),
+                };
+            }
+            Message::Close(_) => return Ok(None),
+            Message::Ping(_) | Message::Pong(_) => {}
+            Message::Binary(_) => return Err(HandlerError::UnexpectedMessageTypeError),
+        }
+    }
+    Ok(None)
+}
+
+/// The names of the analyzers of a session, as the hello reports them.
+pub fn analyzer_names(analyzers: &Analyzers) -> Vec<String> {
+    analyzers.iter().map(|a| a.name().to_string()).collect()
+}
+
+// [task1] client -> room of the stream it opened
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_client_to_server(
     mut client_reader: WebSocketClientReader,
-    mut server_writer: WebSocketServerWriter,
+    shared_client_writer: MutexWebSocketClientWriter,
+    queue: Arc<ClientQueue>,
+    shared_subscription: MutexSubscription,
+    rooms: MutexRoomRegistry,
+    upstream: Upstream,
+    analyzers: Analyzers,
+    room_config: RoomConfig,
+    keepalive: KeepaliveConfig,
 ) -> Result<(), HandlerError> {
-    while let Some(Ok(message)) = client_reader.next().await {
+    let mut keepalive = Keepalive::new(keepalive);
+    // the hello was already received by hello_receiver
+    keepalive.handshake_done();
+
+    loop {
+        let message = tokio::select! {
+            message = client_reader.next() => message,
+            event = keepalive.tick() => {
+                //? keep the client alive, or close it when it is gone (its room is left by relay_session) //
+                let Some((code, reason)) = event.close_reason() else {
+                    ping_client(&shared_client_writer).await;
+                    continue;
+                };
+                tracing::warn!("Client timed out: {}", reason);
+                close_client(&shared_client_writer, code, reason).await;
+                return Ok(());
+            }
+        };
+        let message = match message {
+            Some(Ok(message)) => message,
+            // the connection was dropped without a close frame
+            _ => {
+                tracing::warn!("Client connection lost");
+                return Ok(());
+            }
+        };
+        keepalive.seen();
+
         match message {
             Message::Text(text) => {
                 tracing::info!("Received text from client: {:?}", text);
-                // send only "open" or "accept" messages to server
-                //* step1: receive open message from client and send to server *//
-                //* step4: receive accept message from client and send to server *//
-                if text == "open" || text == "accept" {
-                    tracing::info!("Forwarding message from client to server: {}", text);
-                    server_writer
-                        .send(tungstenite::Message::Text(text.to_string().into()))
-                        .await
-                        .map_err(HandlerError::TokioTungsteniteError)?;
+                let message = ControlMessage::try_from(text.as_str()).map_err(|e| {
+                    HandlerError::InvalidControlMessageError(format!(
+                        "expected a JSON control message of protocol version {PROTOCOL_VERSION} ({e})"
+                    ))
+                })?;
+                let mut subscription = shared_subscription.lock().await;
+                match message {
+                    // an old or newer client is rejected here instead of misparsing upstream
+                    ControlMessage::Hello { version, .. } if version != PROTOCOL_VERSION => {
+                        return Err(HandlerError::ProtocolVersionError(version));
+                    }
+                    ControlMessage::Hello { .. } => {
+                        let hello = ControlMessage::Hello {
+                            version: PROTOCOL_VERSION,
+                            upstream: Some(upstream.name.clone()),
+                            analyzers: Some(analyzer_names(&analyzers)),
+                        };
+                        queue.push(Message::Text(hello.to_json().into())).await;
+                    }
+                    //* step1: receive open message from client and join the room of its stream *//
+                    ControlMessage::Open { .. } => {
+                        if let Some(previous) = subscription.take() {
+                            room_leaver(&rooms, previous).await;
+                        }
+                        *subscription = room_joiner(
+                            &rooms,
+                            &upstream,
+                            &message,
+                            &analyzers,
+                            Arc::clone(&queue),
+                            &room_config,
+                        )
+                        .await;
+                    }
+                    //* step4: receive accept message from client, the first one starts the stream *//
+                    ControlMessage::Accept => {
+                        let Some(subscription) = subscription.as_ref() else {
+                            return Err(HandlerError::UnexpectedMessageError(
+                                "accept before open".into(),
+                            ));
+                        };
+                        subscription.room.accept(subscription.id).await;
+                        room_forwarder(&subscription.room, &message).await;
+                    }
+                    //? stop only ends the stream of this client, the others keep listening //
+                    ControlMessage::Transport(TransportCommand::Stop) => {
+                        if let Some(previous) = subscription.take() {
+                            room_leaver(&rooms, previous).await;
+                            let end = ControlMessage::EndOfStream {
+                                reason: EndOfStreamReason::Stopped,
+                            };
+                            queue.push(Message::Text(end.to_json().into())).await;
+                        }
+                    }
+                    //* (while streaming) receive transport commands, which move the stream of every client of the room *//
+                    ControlMessage::Transport(_) => {
+                        let Some(subscription) = subscription.as_ref() else {
+                            return Err(HandlerError::UnexpectedMessageError(format!(
+                                "{} before open",
+                                message.kind()
+                            )));
+                        };
+                        //? only the client that controls the room moves the shared stream, the others are told so and keep listening //
+                        if !subscription.room.controls(subscription.id).await {
+                            let error: AppError = HandlerError::TransportForbiddenError(
+                                "the stream is controlled by the client that opened it".into(),
+                            )
+                            .into();
+                            let error = error.to_control_message();
+                            queue.push(Message::Text(error.to_json().into())).await;
+                            continue;
+                        }
+                        room_forwarder(&subscription.room, &message).await;
+                    }
+                    message => {
+                        return Err(HandlerError::UnexpectedMessageError(format!(
+                            "{} is only sent by the server",
+                            message.kind()
+                        )));
+                    }
                 }
             }
             Message::Close(close) => {
                 tracing::info!("Client disconnected: {:?}", close);
-                //? send close frame to server //
-                // convert axum CloseFrame to tungstenite CloseFrame
-                let tungstenite_close =
-                    close.map(
-                        |close_frame| tokio_tungstenite::tungstenite::protocol::CloseFrame {
-                            code: tungstenite::protocol::frame::coding::CloseCode::from(
-                                close_frame.code,
-                            ),
-                            reason: close_frame.reason.to_string().into(),
-                        },
-                    );
-                // send close frame to server
-                server_writer
-                    .send(tungstenite::Message::Close(tungstenite_close))
-                    .await
-                    .map_err(HandlerError::TokioTungsteniteError)?;
+                return Ok(());
             }
-            _ => {
+            // pings are answered by axum, pongs only prove that the client is alive
+            Message::Ping(_) | Message::Pong(_) => {}
+            Message::Binary(_) => {
                 tracing::error!("Received unsupported message type from client");
                 return Err(HandlerError::UnexpectedMessageTypeError);
             }
         };
     }
-    Ok(())
 }
@@ -0,0 +1,62 @@
+use axum::extract::ws::{CloseFrame, Message};
+use futures_util::SinkExt;
+use protocol::models::ws::{MutexWebSocketClientWriter, MutexWebSocketServerWriter};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite;
+
+// how long a ping or close frame to an unresponsive peer may take
+static SEND_TIMEOUT: Duration = Duration::from_secs(1);
+
+//? the peer may be gone, so pings and close frames are sent on a best-effort basis //
+// (a peer that does not answer is caught by the idle timeout)
+pub async fn ping_client(client_writer: &MutexWebSocketClientWriter) {
+    let ping = async {
+        let mut writer = client_writer.lock().await;
+        writer.send(Message::Ping(Default::default())).await
+    };
+    if !matches!(tokio::time::timeout(SEND_TIMEOUT, ping).await, Ok(Ok(()))) {
+        tracing::warn!("Failed to ping client");
+    }
+}
+
+pub async fn ping_server(server_writer: &MutexWebSocketServerWriter) {
+    let ping = async {
+        let mut writer = server_writer.lock().await;
+        writer
+            .send(tungstenite::Message::Ping(Default::default()))
+            .await
+    };
+    if !matches!(tokio::time::timeout(SEND_TIMEOUT, ping).await, Ok(Ok(()))) {
+        tracing::warn!("Failed to ping server");
+    }
+}
+
+pub async fn close_client(client_writer: &MutexWebSocketClientWriter, code: u16, reason: &str) {
+    tracing::info!("Closing client connection: {} {}", code, reason);
+    let close = async {
+        let mut writer = client_writer.lock().await;
+        writer
+            .send(Message::Close(Some(CloseFrame {
+                code,
+                reason: reason.into(),
+            })))
+            .await
+    };
+    let _ = tokio::time::timeout(SEND_TIMEOUT, close).await;
+}
+
+pub async fn close_server(server_writer: &MutexWebSocketServerWriter, code: u16, reason: &str) {
+    tracing::info!("Closing server connection: {} {}", code, reason);
+    let close = async {
+        let mut writer = server_writer.lock().await;
+        writer
+            .send(tungstenite::Message::Close(Some(
+                tungstenite::protocol::CloseFrame {
+                    code: code.into(),
+                    reason: reason.into(),
+                },
+            )))
+            .await
+    };
+    let _ = tokio::time::timeout(SEND_TIMEOUT, close).await;
+}
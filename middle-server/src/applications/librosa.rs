@@ -0,0 +1,33 @@
+use numpy::IntoPyArray;
+use pyo3::{
+    PyResult, Python,
+    types::{PyAnyMethods, PyDict},
+};
+
+/// Estimates the tempo of `samples` (mono) with librosa.
+pub fn pcm_detector(py: Python<'_>, samples: Vec<f32>, sample_rate: f64) -> PyResult<f64> {
+    // [python code]
+    // import librosa, numpy
+    let librosa = py.import("librosa")?;
+    let numpy = py.import("numpy")?;
+
+    // [python code]
+    // kwargs = {"y": samples, "sr": sample_rate}
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("y", samples.into_pyarray(py))?;
+    kwargs.set_item("sr", sample_rate)?;
+
+    // [python code]
+    // tempo, _beats = librosa.beat.beat_track(**kwargs)
+    let result = librosa
+        .getattr("beat")?
+        .getattr("beat_track")?
+        .call((), Some(&kwargs))?;
+
+    // [python code]
+    // float(numpy.mean(tempo))  (librosa >= 0.10 returns the tempo as an array)
+    numpy
+        .getattr("mean")?
+        .call1((result.get_item(0)?,))?
+        .extract::<f64>()
+}
@@ -1,53 +1,114 @@
-use crate::{errors::handler::HandlerError, models::ws::MutexWebSocketClientWriter};
-use axum::extract::ws::Message;
-use futures_util::SinkExt;
-use std::collections::VecDeque;
+use crate::{
+    errors::handler::HandlerError,
+    models::{
+        audio::RwLockAudioInfo,
+        frame_ring::{Continuity, FrameRing, SequenceGap, sequence_gap},
+        packet::WindowPacket,
+        stage::Stage,
+        window::WindowConfig,
+    },
+};
+use protocol::models::frame::frame_splitter;
+use std::{sync::Arc, time::Instant};
 
 // [task3] pcm data processing
 pub async fn pcm_data_processing(
-    window_size: u64,
-    slide_size: u64,
-    mut pcm_rx: tokio::sync::mpsc::Receiver<Vec<u8>>,
-    shared_client_writer: MutexWebSocketClientWriter,
+    window_config: WindowConfig,
+    shared_audio_info: RwLockAudioInfo,
+    pcm_stage: Arc<Stage<Vec<u8>>>,
+    window_stage: Arc<Stage<WindowPacket>>,
 ) -> Result<(), HandlerError> {
-    let mut counter: u64 = 0;
-    let mut stock_buffer: VecDeque<Vec<u8>> = VecDeque::new();
-    let mut send_buffer: Vec<u8> = Vec::new();
+    // created with the first chunk, the audio info tells the sample rate
+    let mut frame_ring: Option<FrameRing> = None;
+    // (stream ID, next sequence number) of the stream being received
+    let mut expected: Option<(u32, u32)> = None;
+    let mut lost_chunks: u64 = 0;
+    let mut reordered_chunks: u64 = 0;
 
     //* step6: receive binary from sender (producer) *//
-    //* step7: do sliding window (while loop) *//
+    //* step7: do sliding window (loop) *//
     //? Receiver (Consumer) //
-    while let Some(bin) = pcm_rx.recv().await {
-        //* collect buffer *//
-        stock_buffer.push_back(bin);
-        counter += 1;
-
-        tracing::info!("counter: {}", counter);
+    loop {
+        let bin = pcm_stage.pop().await;
+        // a coalesced item holds several chunks
+        for (header, payload) in frame_splitter(&bin)? {
+            //* check the frame header for lost and reordered chunks *//
+            let next_sequence = match expected {
+                Some((stream_id, sequence)) if stream_id == header.stream_id => {
+                    match sequence_gap(sequence, header.sequence) {
+                        SequenceGap::InOrder => header.sequence.wrapping_add(1),
+                        SequenceGap::Lost(lost) => {
+                            lost_chunks += lost as u64;
+                            tracing::warn!(
+                                "Stream {}: lost {} chunk(s) before #{} ({} lost in total)",
+                                header.stream_id,
+                                lost,
+                                header.sequence,
+                                lost_chunks
+                            );
+                            header.sequence.wrapping_add(1)
+                        }
+                        SequenceGap::Late => {
+                            reordered_chunks += 1;
+                            tracing::warn!(
+                                "Stream {}: chunk #{} arrived after #{} ({} reordered in total)",
+                                header.stream_id,
+                                header.sequence,
+                                sequence.wrapping_sub(1),
+                                reordered_chunks
+                            );
+                            // a late chunk does not move the expected sequence number back
+                            sequence
+                        }
+                    }
+                }
+                _ => {
+                    tracing::info!("Receiving stream {}", header.stream_id);
+                    header.sequence.wrapping_add(1)
+                }
+            };
+            expected = Some((header.stream_id, next_sequence));
 
-        if counter >= window_size {
-            // prepare send buffer
-            for _ in 0..slide_size {
-                if let Some(buf) = stock_buffer.pop_front() {
-                    send_buffer.extend(buf);
+            //* collect frames, whatever the chunks they arrived in *//
+            let frame_ring = match &mut frame_ring {
+                Some(frame_ring) => frame_ring,
+                None => {
+                    let sample_rate = shared_audio_info
+                        .read()
+                        .await
+                        .as_ref()
+                        .map(|audio_info| audio_info.sample_rate)
+                        .ok_or(HandlerError::AudioInfoUndefinedError)?;
+                    let (window_frames, hop_frames) = window_config.frames(sample_rate);
+                    tracing::info!(
+                        "Windows of {} frames every {} frames, {} overlapping ({} Hz, {:?} window)",
+                        window_frames,
+                        hop_frames,
+                        window_frames - hop_frames,
+                        sample_rate,
+                        window_config.function
+                    );
+                    frame_ring.insert(FrameRing::new(window_frames, hop_frames, sample_rate))
+                }
+            };
+            match frame_ring.push(&header, payload) {
+                Continuity::Continuous => {}
+                Continuity::Restarted => {
+                    tracing::info!("Windows start at frame {}", header.first_frame)
                 }
+                Continuity::Late => continue,
             }
 
-            // TODO: ここで時間のかかる解析処理を実行する
-            // let events = analyze(&send_buffer);
-            // let packet = VJDataPacket { pcm_data: send_buffer.clone(), events };
-            // let serialized_packet = rmp_serde::to_vec(&packet).unwrap();
-
-            //* step8: send binary data to client with window size *//
-            let mut writer = shared_client_writer.lock().await;
-            writer
-                .send(Message::Binary(send_buffer.clone().into()))
-                .await?;
-
-            // reset counter
-            counter -= slide_size;
-            // reset send buffer
-            send_buffer.clear();
+            //* step8: send every complete window to window_data_processing *//
+            while let Some(window) = frame_ring.next_window() {
+                //? Sender (Producer) //
+                window_stage
+                    .push(WindowPacket {
+                        bytes: window,
+                        cut_at: Instant::now(),
+                    })
+                    .await;
+            }
         }
     }
-    Ok(())
 }
@@ -0,0 +1,253 @@
+use crate::{
+    applications::{
+        keepalive::close_server,
+        pcm::pcm_data_processing,
+        upstream::{UpstreamConnection, upstream_connector, upstream_supervisor},
+        window::window_data_processing,
+    },
+    errors::handler::HandlerError,
+    models::{
+        analysis::Analyzers,
+        client_queue::ClientQueue,
+        packet::WindowPacket,
+        room::{MutexRoomRegistry, Room, RoomConfig, RoomKey, Subscription},
+        stage::Stage,
+        upstream::Upstream,
+    },
+};
+use axum::extract::ws::{Message, close_code};
+use futures_util::SinkExt;
+use protocol::{
+    errors::app::AppError,
+    models::{protocol::ControlMessage, ws::MutexWebSocketClientWriter},
+};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio_tungstenite::tungstenite;
+
+/// Adds a client to the room of its `open`, opening the room if there is none.
+pub async fn room_joiner(
+    rooms: &MutexRoomRegistry,
+    upstream: &Upstream,
+    open: &ControlMessage,
+    analyzers: &Analyzers,
+    queue: Arc<ClientQueue>,
+    config: &RoomConfig,
+) -> Option<Subscription> {
+    let key = RoomKey::new(upstream, open, analyzers)?;
+    let mut rooms_guard = rooms.lock().await;
+    if let Some(room) = rooms_guard.get(&key)
+        && let Some(id) = room.join(Arc::clone(&queue)).await
+    {
+        tracing::info!("Client {} joined room {:?}", id, key);
+        return Some(Subscription {
+            room: Arc::clone(room),
+            id,
+        });
+    }
+
+    //? the first client of a stream opens the room, which connects to the server on its own //
+    let room = Arc::new(Room::new(
+        key.clone(),
+        upstream.clone(),
+        analyzers.clone(),
+        open,
+    ));
+    let id = room.join(queue).await?;
+    rooms_guard.insert(key.clone(), Arc::clone(&room));
+    drop(rooms_guard);
+    tracing::info!("Client {} opened room {:?}", id, key);
+    tokio::spawn(room_runner(
+        Arc::clone(&room),
+        Arc::clone(rooms),
+        config.clone(),
+    ));
+    Some(Subscription { room, id })
+}
+
+/// Removes a client from its room, closing the room when it was the last client.
+pub async fn room_leaver(rooms: &MutexRoomRegistry, subscription: Subscription) {
+    let Subscription { room, id } = subscription;
+    let mut rooms = rooms.lock().await;
+    if !room.leave(id).await {
+        tracing::info!("Client {} left room {:?}", id, room.key);
+        return;
+    }
+    tracing::info!("Last client {} left room {:?}", id, room.key);
+    if rooms.get(&room.key).is_some_and(|r| Arc::ptr_eq(r, &room)) {
+        rooms.remove(&room.key);
+    }
+    room.abandoned.notify_one();
+}
+
+/// Records a message of a client in the session of its room and forwards it to the server.
+///
+/// The stream is shared, so only the first `accept` is forwarded.
+pub async fn room_forwarder(room: &Room, message: &ControlMessage) {
+    // the session is held while the message is forwarded,
+    // so it cannot overtake the replay on a new server connection
+    let mut session = room.session.lock().await;
+    if let ControlMessage::Accept = message
+        && session.accepted
+    {
+        return;
+    }
+    session.record(message);
+    // before the first server connection the message is sent with the replay of the session
+    let Some(server_writer) = room.server_writer.get() else {
+        return;
+    };
+    tracing::info!(
+        "Forwarding {} to upstream {}",
+        message.kind(),
+        room.upstream.name
+    );
+    let mut server_writer = server_writer.lock().await;
+    if let Err(e) = server_writer
+        .send(tungstenite::Message::Text(message.to_json().into()))
+        .await
+    {
+        // the server leg is being replaced, the session is replayed on the new one
+        tracing::warn!(
+            "Upstream unavailable, {} not forwarded: {}",
+            message.kind(),
+            e
+        );
+    }
+}
+
+// [room] server -> clients, until the stream ends or the last client leaves
+async fn room_runner(room: Arc<Room>, rooms: MutexRoomRegistry, config: RoomConfig) {
+    let result = room_streamer(Arc::clone(&room), config).await;
+
+    // the clients that opened the stream meanwhile get a new room
+    {
+        let mut rooms = rooms.lock().await;
+        if rooms.get(&room.key).is_some_and(|r| Arc::ptr_eq(r, &room)) {
+            rooms.remove(&room.key);
+        }
+    }
+    if let Some(server_writer) = room.server_writer.get() {
+        close_server(server_writer, close_code::NORMAL, "room closed").await;
+    }
+    // report the failure to every client and close their connections with the matching close code
+    if let Err(error) = result {
+        tracing::error!("Room {:?} failed: {:?}", room.key, error);
+        let error = AppError::from(error);
+        room.end(&[
+            Message::Text(error.to_control_message().to_json().into()),
+            Message::Close(Some(error.to_close_frame())),
+        ])
+        .await;
+    }
+    tracing::info!("Room {:?} closed.", room.key);
+}
+
+async fn room_streamer(room: Arc<Room>, config: RoomConfig) -> Result<(), HandlerError> {
+    let RoomConfig {
+        keepalive,
+        reconnect,
+        window,
+        pcm_stage,
+        window_stage,
+        analysis_pool,
+    } = config;
+    //* step0: connect to the server and replay the open of the first client (retried with backoff) *//
+    let connection = tokio::select! {
+        connection = upstream_connector(&room, reconnect, keepalive, true) => connection?,
+        _ = room.abandoned.notified() => return Ok(()),
+    };
+    let UpstreamConnection {
+        writer: server_writer,
+        reader: server_reader,
+        session,
+        ..
+    } = connection;
+    tracing::info!("Connection to upstream {} established.", room.upstream.name);
+    // the server is written to by the clients (forwarding) and task2 (pings)
+    let shared_server_writer = Arc::new(Mutex::new(server_writer));
+    let _ = room.server_writer.set(Arc::clone(&shared_server_writer));
+    drop(session);
+
+    // the queues between the tasks, with the policy of each one when its consumer is behind
+    let pcm_stage = Arc::new(Stage::<Vec<u8>>::new(
+        format!("pcm of room {:?}", room.key),
+        pcm_stage,
+    ));
+    let window_stage = Arc::new(Stage::<WindowPacket>::new(
+        format!("window of room {:?}", room.key),
+        window_stage,
+    ));
+
+    //* --- Start independent tasks --- *//
+    // [task2] server -> clients (reconnecting to the server when it is lost)
+    let mut server_read_task = tokio::spawn(upstream_supervisor(
+        server_reader,
+        shared_server_writer,
+        Arc::clone(&room),
+        reconnect,
+        Arc::clone(&pcm_stage),
+        keepalive,
+    ));
+    // [task3] pcm data processing
+    let mut pcm_processing_task = tokio::spawn(pcm_data_processing(
+        window,
+        Arc::clone(&room.audio_info),
+        Arc::clone(&pcm_stage),
+        Arc::clone(&window_stage),
+    ));
+    // [task4] window data processing (the analyzers of the room), once for all clients
+    let mut window_processing_task = tokio::spawn(window_data_processing(
+        Arc::clone(&window_stage),
+        Arc::clone(&room),
+        window.function,
+        Arc::clone(&analysis_pool),
+    ));
+
+    //* When one of the tasks is completed or the last client left, the other tasks are aborted. *//
+    let result = tokio::select! {
+        response = &mut server_read_task => response,
+        response = &mut pcm_processing_task => response,
+        response = &mut window_processing_task => response,
+        _ = room.abandoned.notified() => Ok(Ok(())),
+    };
+    // a dropped JoinHandle would leave the task running
+    server_read_task.abort();
+    pcm_processing_task.abort();
+    window_processing_task.abort();
+    tracing::info!(
+        "Room {:?}: pcm stage {:?}, window stage {:?}",
+        room.key,
+        pcm_stage.counters().await,
+        window_stage.counters().await
+    );
+    tracing::info!(
+        "Analysis of every room so far: {:?}",
+        analysis_pool.metrics()
+    );
+    for analyzer in &room.analyzers {
+        if let Some(health) = analyzer.health() {
+            tracing::info!("Analyzer {}: {}", analyzer.name(), health);
+        }
+    }
+    result.map_err(HandlerError::TokioJoinError)?
+}
+
+// [task2] room -> client
+pub async fn client_queue_sender(
+    queue: Arc<ClientQueue>,
+    shared_client_writer: MutexWebSocketClientWriter,
+) -> Result<(), HandlerError> {
+    loop {
+        let message = queue.pop().await;
+        let close = matches!(message, Message::Close(_));
+        let mut writer = shared_client_writer.lock().await;
+        writer
+            .send(message)
+            .await
+            .map_err(HandlerError::AxumError)?;
+        if close {
+            return Ok(());
+        }
+    }
+}
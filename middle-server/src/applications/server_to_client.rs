@@ -1,55 +1,112 @@
 use crate::{
+    applications::keepalive::{close_server, ping_server},
     errors::handler::HandlerError,
-    models::ws::{MutexWebSocketClientWriter, WebSocketServerReader},
+    models::{room::Room, session::UpstreamEnd, stage::Stage},
 };
 use axum::extract::ws::Message;
-use futures_util::{SinkExt, StreamExt};
+use futures_util::StreamExt;
+use protocol::models::{
+    frame::PcmFrameHeader,
+    keepalive::{Keepalive, KeepaliveConfig},
+    protocol::ControlMessage,
+    ws::{MutexWebSocketServerWriter, WebSocketServerReader},
+};
+use std::sync::Arc;
 use tokio_tungstenite::tungstenite;
 
-// [task2] server -> client
+// server -> clients of the room, until the server connection ends (see upstream_supervisor)
 pub async fn handle_server_to_client(
     mut server_reader: WebSocketServerReader,
-    pcm_tx: tokio::sync::mpsc::Sender<Vec<u8>>,
-    shared_client_writer: MutexWebSocketClientWriter,
-) -> Result<(), HandlerError> {
-    while let Some(Ok(message)) = server_reader.next().await {
+    pcm_stage: Arc<Stage<Vec<u8>>>,
+    shared_server_writer: MutexWebSocketServerWriter,
+    room: &Room,
+    keepalive: KeepaliveConfig,
+) -> Result<UpstreamEnd, HandlerError> {
+    let mut keepalive = Keepalive::new(keepalive);
+    // the hello of the server was already received by session_replayer
+    keepalive.handshake_done();
+
+    loop {
+        let message = tokio::select! {
+            message = server_reader.next() => message,
+            event = keepalive.tick() => {
+                //? keep the server leg alive, or give it up when the server is gone //
+                let Some((code, reason)) = event.close_reason() else {
+                    ping_server(&shared_server_writer).await;
+                    continue;
+                };
+                tracing::warn!("Server timed out: {}", reason);
+                close_server(&shared_server_writer, code, reason).await;
+                return Ok(UpstreamEnd::Lost(reason));
+            }
+        };
+        let message = match message {
+            Some(Ok(message)) => message,
+            // the connection was dropped without a close frame
+            _ => {
+                tracing::warn!("Server connection lost");
+                return Ok(UpstreamEnd::Lost("connection lost"));
+            }
+        };
+        keepalive.seen();
+
         match message {
             tungstenite::Message::Text(text) => {
-                //* step2: receive audio info from server *//
-                tracing::info!("Received text from client: {:?}", text);
-                //* step3: send audio info to client *//
-                let mut writer = shared_client_writer.lock().await;
-                writer
-                    .send(Message::Text(text.to_string().into()))
-                    .await
-                    .map_err(HandlerError::AxumError)?;
+                //* step2: receive audio info, end of stream or errors from server *//
+                let message = ControlMessage::try_from(text.as_str()).map_err(|e| {
+                    HandlerError::InvalidControlMessageError(format!("from server: {e}"))
+                })?;
+                tracing::info!("Received control message from server: {:?}", message);
+                // an error of the server is relayed as it is, the close frame follows
+                if let ControlMessage::Error { status, message } = &message {
+                    tracing::warn!("Server reported an error ({}): {}", status, message);
+                }
+                match &message {
+                    // keep the format of the stream for the analysis of its windows
+                    ControlMessage::AudioInfo { info, seekable } => {
+                        *room.audio_info.write().await = Some(info.clone());
+                        let mut session = room.session.lock().await;
+                        session.audio_info_sent = true;
+                        session.seekable = *seekable;
+                    }
+                    //* step3: the stream of the room is over, the clients may open another one *//
+                    ControlMessage::EndOfStream { .. } => {
+                        room.session.lock().await.accepted = false;
+                        room.end(&[Message::Text(text.to_string().into())]).await;
+                        return Ok(UpstreamEnd::Closed);
+                    }
+                    _ => {}
+                }
+                //* step3: send them to every client of the room *//
+                room.broadcast(&message).await;
             }
             tungstenite::Message::Binary(binary) => {
                 //* step5: receive PCM data from server *//
                 tracing::info!("Received binary from client: {:?}", binary);
+                // remember the position, a new server continues the stream there
+                let header = PcmFrameHeader::from_frame(&binary)?;
+                room.session.lock().await.next_frame =
+                    Some(header.first_frame + header.frames as u64);
                 //? Sender (Producer) //
-                pcm_tx.send(binary.to_vec()).await?;
+                pcm_stage.push(binary.to_vec()).await;
             }
             tungstenite::Message::Close(close) => {
                 tracing::info!("Server disconnected: {:?}", close);
-                let mut writer = shared_client_writer.lock().await;
-                //? send close frame to client //
+                //? send close frame to every client of the room //
                 // convert tungstenite CloseFrame to axum CloseFrame
                 let axum_close = close.map(|close_frame| axum::extract::ws::CloseFrame {
                     code: axum::extract::ws::CloseCode::from(u16::from(close_frame.code)),
                     reason: close_frame.reason.to_string().into(),
                 });
-                // send close frame to client
-                writer
-                    .send(axum::extract::ws::Message::Close(axum_close))
-                    .await
-                    .map_err(HandlerError::AxumError)?;
+                room.end(&[Message::Close(axum_close)]).await;
+                return Ok(UpstreamEnd::Closed);
             }
-            _ => {
+            // pings are answered by tungstenite, pongs only prove that the server is alive
+            tungstenite::Message::Ping(_) | tungstenite::Message::Pong(_) => {}
+            tungstenite::Message::Frame(_) => {
                 tracing::error!("Received unsupported message type from server");
                 return Err(HandlerError::UnexpectedMessageTypeError);
             }
         }
     }
-    Ok(())
 }
@@ -0,0 +1,245 @@
+use rustfft::{FftPlanner, num_complex::Complex};
+
+//* constant values (the defaults of librosa.beat.beat_track) *//
+static N_FFT: usize = 2048;
+static HOP_LENGTH: usize = 512;
+static N_MELS: usize = 128;
+static TOP_DB: f32 = 80.0;
+static START_BPM: f64 = 120.0;
+static STD_BPM: f64 = 1.0;
+static MAX_TEMPO: f64 = 320.0;
+
+/// Estimates the tempo of `samples` (mono) in Rust, the way librosa.beat.beat_track does.
+///
+/// Returns 0 when the samples have no onsets (silence or shorter than two STFT frames).
+pub fn tempo_detector(samples: &[f32], sample_rate: f64) -> f64 {
+    let envelope = onset_envelope(samples, sample_rate);
+    tempo_estimator(&envelope, sample_rate)
+}
+
+/// The onset strength of every STFT frame: the rise of the log-power mel spectrogram, averaged over the bands.
+fn onset_envelope(samples: &[f32], sample_rate: f64) -> Vec<f32> {
+    let spectrogram = mel_spectrogram(samples, sample_rate);
+
+    // power_to_db(ref=1.0, top_db=80)
+    let max_db = spectrogram
+        .iter()
+        .flatten()
+        .map(|&power| 10.0 * power.max(1e-10).log10())
+        .fold(f32::NEG_INFINITY, f32::max);
+    let db = |power: f32| (10.0 * power.max(1e-10).log10()).max(max_db - TOP_DB);
+
+    let mut envelope = vec![0.0; spectrogram.len()];
+    for t in 1..spectrogram.len() {
+        let flux: f32 = spectrogram[t]
+            .iter()
+            .zip(&spectrogram[t - 1])
+            .map(|(&now, &before)| (db(now) - db(before)).max(0.0))
+            .sum();
+        envelope[t] = flux / N_MELS as f32;
+    }
+    envelope
+}
+
+/// The mel power spectrogram, one row of N_MELS bands per frame of HOP_LENGTH samples (centered, Hann window).
+fn mel_spectrogram(samples: &[f32], sample_rate: f64) -> Vec<Vec<f32>> {
+    let filters = mel_filters(sample_rate);
+    let window: Vec<f32> = (0..N_FFT)
+        .map(|n| (0.5 - 0.5 * (2.0 * std::f64::consts::PI * n as f64 / N_FFT as f64).cos()) as f32)
+        .collect();
+    let fft = FftPlanner::<f32>::new().plan_fft_forward(N_FFT);
+
+    // the frames are centered on their sample, the signal is zero padded by half a frame
+    let frames = samples.len() / HOP_LENGTH + 1;
+    let mut buffer = vec![Complex::default(); N_FFT];
+    let mut power = vec![0.0f32; N_FFT / 2 + 1];
+    (0..frames)
+        .map(|frame| {
+            let start = (frame * HOP_LENGTH) as isize - (N_FFT / 2) as isize;
+            for (n, value) in buffer.iter_mut().enumerate() {
+                let sample = usize::try_from(start + n as isize)
+                    .ok()
+                    .and_then(|index| samples.get(index))
+                    .copied()
+                    .unwrap_or(0.0);
+                *value = Complex::new(sample * window[n], 0.0);
+            }
+            fft.process(&mut buffer);
+            for (bin, value) in power.iter_mut().enumerate() {
+                *value = buffer[bin].norm_sqr();
+            }
+            filters
+                .iter()
+                .map(|(first_bin, weights)| {
+                    weights
+                        .iter()
+                        .zip(&power[*first_bin..])
+                        .map(|(weight, power)| weight * power)
+                        .sum()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Triangular mel filters (Slaney scale and area normalization as in librosa.filters.mel),
+/// each one as its first FFT bin and the weights from there.
+fn mel_filters(sample_rate: f64) -> Vec<(usize, Vec<f32>)> {
+    let hz_to_mel = |hz: f64| {
+        if hz < 1000.0 {
+            hz * 3.0 / 200.0
+        } else {
+            15.0 + (hz / 1000.0).ln() * 27.0 / 6.4f64.ln()
+        }
+    };
+    let mel_to_hz = |mel: f64| {
+        if mel < 15.0 {
+            mel * 200.0 / 3.0
+        } else {
+            1000.0 * ((mel - 15.0) * 6.4f64.ln() / 27.0).exp()
+        }
+    };
+
+    let max_mel = hz_to_mel(sample_rate / 2.0);
+    let edges: Vec<f64> = (0..N_MELS + 2)
+        .map(|m| mel_to_hz(max_mel * m as f64 / (N_MELS + 1) as f64))
+        .collect();
+    let bin_hz = sample_rate / N_FFT as f64;
+    (0..N_MELS)
+        .map(|m| {
+            let (lower, center, upper) = (edges[m], edges[m + 1], edges[m + 2]);
+            let norm = 2.0 / (upper - lower);
+            let first_bin = (lower / bin_hz).ceil() as usize;
+            let last_bin = ((upper / bin_hz).floor() as usize).min(N_FFT / 2);
+            let weights = (first_bin..=last_bin)
+                .map(|bin| {
+                    let hz = bin as f64 * bin_hz;
+                    let rise = (hz - lower) / (center - lower);
+                    let fall = (upper - hz) / (upper - center);
+                    (rise.min(fall).max(0.0) * norm) as f32
+                })
+                .collect();
+            (first_bin.min(N_FFT / 2), weights)
+        })
+        .collect()
+}
+
+/// The tempo of an onset envelope: the period with the strongest autocorrelation,
+/// weighted by a log-normal prior around START_BPM (librosa.feature.rhythm.tempo).
+///
+/// The windows are shorter than the tempogram of librosa (384 frames),
+/// so the autocorrelation spans the whole envelope.
+fn tempo_estimator(envelope: &[f32], sample_rate: f64) -> f64 {
+    let frames = envelope.len();
+    let energy: f64 = envelope.iter().map(|&x| (x as f64).powi(2)).sum();
+    if frames < 2 || energy <= 0.0 {
+        return 0.0;
+    }
+
+    let mut best = (f64::NEG_INFINITY, 0.0);
+    for lag in 1..frames {
+        let bpm = 60.0 * sample_rate / (HOP_LENGTH * lag) as f64;
+        if bpm > MAX_TEMPO {
+            continue;
+        }
+        let autocorrelation: f64 = envelope[lag..]
+            .iter()
+            .zip(envelope)
+            .map(|(&a, &b)| a as f64 * b as f64)
+            .sum::<f64>()
+            / energy;
+        let log_prior = -0.5 * ((bpm.log2() - START_BPM.log2()) / STD_BPM).powi(2);
+        let score = (1e6 * autocorrelation.max(0.0)).ln_1p() + log_prior;
+        if score > best.0 {
+            best = (score, bpm);
+        }
+    }
+    best.1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A click (a decaying 1 kHz burst of 10 ms) on every beat.
+    fn click_track(bpm: f64, seconds: f64, sample_rate: f64) -> Vec<f32> {
+        let period = 60.0 / bpm;
+        (0..(seconds * sample_rate) as usize)
+            .map(|n| {
+                let t = n as f64 / sample_rate;
+                let since_beat = t % period;
+                if since_beat < 0.01 {
+                    ((2.0 * std::f64::consts::PI * 1000.0 * t).sin() * (-since_beat * 300.0).exp())
+                        as f32
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    /// The tempo librosa reports for a click track: the period of the clicks rounded to a lag of whole frames.
+    fn librosa_tempo(bpm: f64, sample_rate: f64) -> f64 {
+        let lag = (60.0 * sample_rate / (HOP_LENGTH as f64 * bpm)).round();
+        60.0 * sample_rate / (HOP_LENGTH as f64 * lag)
+    }
+
+    #[test]
+    fn click_tracks_are_estimated_at_their_tempo() {
+        for sample_rate in [22050.0, 44100.0] {
+            for bpm in [90.0, 100.0, 120.0, 128.0, 140.0] {
+                let samples = click_track(bpm, 8.0, sample_rate);
+                let estimate = tempo_detector(&samples, sample_rate);
+                assert!(
+                    (estimate - librosa_tempo(bpm, sample_rate)).abs() < 1e-9,
+                    "{bpm} BPM at {sample_rate} Hz estimated at {estimate}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn short_windows_stay_within_one_frame_of_lag() {
+        // a window of 4s at 8 kHz holds only 63 frames
+        let sample_rate = 8000.0;
+        for bpm in [100.0, 120.0, 140.0] {
+            let samples = click_track(bpm, 4.0, sample_rate);
+            let estimate = tempo_detector(&samples, sample_rate);
+            let lag = 60.0 * sample_rate / (HOP_LENGTH as f64 * bpm);
+            let resolution = bpm - 60.0 * sample_rate / (HOP_LENGTH as f64 * (lag + 1.0));
+            assert!(
+                (estimate - bpm).abs() <= resolution,
+                "{bpm} BPM estimated at {estimate}"
+            );
+        }
+    }
+
+    #[test]
+    fn silence_has_no_tempo() {
+        assert_eq!(tempo_detector(&vec![0.0; 44100], 44100.0), 0.0);
+        assert_eq!(tempo_detector(&[], 44100.0), 0.0);
+    }
+
+    /// Compares with librosa itself, which needs Python with librosa (cargo test --features python-analysis -- --ignored).
+    #[cfg(feature = "python-analysis")]
+    #[test]
+    #[ignore]
+    fn matches_librosa_on_the_same_audio() {
+        use crate::applications::librosa::pcm_detector;
+
+        let sample_rate = 22050.0;
+        for bpm in [90.0, 120.0, 128.0, 140.0] {
+            let samples = click_track(bpm, 8.0, sample_rate);
+            let librosa = pyo3::Python::attach(|py| pcm_detector(py, samples.clone(), sample_rate))
+                .expect("librosa is installed");
+            let estimate = tempo_detector(&samples, sample_rate);
+            // one frame of lag apart at most
+            let lag = 60.0 * sample_rate / (HOP_LENGTH as f64 * librosa);
+            let resolution = librosa - 60.0 * sample_rate / (HOP_LENGTH as f64 * (lag + 1.0));
+            assert!(
+                (estimate - librosa).abs() <= resolution,
+                "{bpm} BPM: librosa {librosa}, estimated {estimate}"
+            );
+        }
+    }
+}
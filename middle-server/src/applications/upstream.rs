@@ -0,0 +1,266 @@
+use crate::{
+    applications::server_to_client::handle_server_to_client,
+    errors::handler::HandlerError,
+    models::{
+        reconnect::ReconnectConfig,
+        room::Room,
+        session::{SessionState, UpstreamEnd},
+        stage::Stage,
+    },
+};
+use axum::extract::ws::{CloseFrame, Message};
+use futures_util::{SinkExt, StreamExt};
+use protocol::models::{
+    keepalive::KeepaliveConfig,
+    protocol::{CLOSE_UPSTREAM_LOST, ControlMessage, PROTOCOL_VERSION, UpstreamStatus},
+    transport::TransportCommand,
+    ws::{MutexWebSocketServerWriter, WebSocketServerReader, WebSocketServerWriter},
+};
+use std::sync::Arc;
+use tokio::sync::OwnedMutexGuard;
+use tokio_tungstenite::{connect_async, tungstenite};
+
+/// A server connection on which the session of the client was restored.
+pub struct UpstreamConnection {
+    pub writer: WebSocketServerWriter,
+    pub reader: WebSocketServerReader,
+    /// Held until `writer` replaces the old one, so no message of the client overtakes the replay.
+    pub session: OwnedMutexGuard<SessionState>,
+    /// The frame the stream was resumed at, if it was resumed by seeking.
+    pub resume_frame: Option<u64>,
+}
+
+// [task2] server -> clients of the room, replacing the server connection when it is lost
+pub async fn upstream_supervisor(
+    mut server_reader: WebSocketServerReader,
+    shared_server_writer: MutexWebSocketServerWriter,
+    room: Arc<Room>,
+    reconnect: ReconnectConfig,
+    pcm_stage: Arc<Stage<Vec<u8>>>,
+    keepalive: KeepaliveConfig,
+) -> Result<(), HandlerError> {
+    loop {
+        let end = handle_server_to_client(
+            server_reader,
+            Arc::clone(&pcm_stage),
+            Arc::clone(&shared_server_writer),
+            &room,
+            keepalive,
+        )
+        .await?;
+        let reason = match end {
+            UpstreamEnd::Closed => return Ok(()),
+            UpstreamEnd::Lost(reason) => reason,
+        };
+        tracing::warn!("Upstream {} lost: {}", room.upstream.name, reason);
+        if reconnect.max_attempts == 0 {
+            room.end(&[Message::Close(Some(CloseFrame {
+                code: CLOSE_UPSTREAM_LOST,
+                reason: format!("server {reason}").into(),
+            }))])
+            .await;
+            return Ok(());
+        }
+
+        //? keep the clients connected while the server leg is replaced //
+        let UpstreamConnection {
+            writer,
+            reader,
+            session,
+            resume_frame,
+        } = upstream_connector(&room, reconnect, keepalive, false).await?;
+        *shared_server_writer.lock().await = writer;
+        drop(session);
+        server_reader = reader;
+        tracing::info!(
+            "Upstream {} reconnected (resumed at frame {:?})",
+            room.upstream.name,
+            resume_frame
+        );
+        room.broadcast(&ControlMessage::Upstream(UpstreamStatus::Reconnected {
+            resume_frame,
+        }))
+        .await;
+    }
+}
+
+/// Connects to the upstream of `room` and restores its session, retrying with exponential backoff.
+///
+/// The `initial` connection is tried at once, a lost one only after the first delay.
+pub async fn upstream_connector(
+    room: &Room,
+    reconnect: ReconnectConfig,
+    keepalive: KeepaliveConfig,
+    initial: bool,
+) -> Result<UpstreamConnection, HandlerError> {
+    let upstream = &room.upstream;
+    let mut attempt = if initial { 0 } else { 1 };
+    loop {
+        if attempt > 0 {
+            let delay = reconnect.delay(attempt);
+            room.broadcast(&ControlMessage::Upstream(UpstreamStatus::Reconnecting {
+                attempt,
+                retry_in_ms: delay.as_millis() as u64,
+            }))
+            .await;
+            tokio::time::sleep(delay).await;
+        }
+        match upstream_opener(room, keepalive).await {
+            Ok(connection) => return Ok(connection),
+            // the server is reachable but refuses the session, so retrying does not help
+            Err(
+                error @ (HandlerError::UpstreamResumeError(_)
+                | HandlerError::UpstreamRefusedError(..)),
+            ) => return Err(error),
+            Err(error) if attempt < reconnect.max_attempts => {
+                tracing::warn!(
+                    "Upstream {} unavailable (attempt {}): {}",
+                    upstream.name,
+                    attempt,
+                    error
+                );
+                attempt += 1;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+async fn upstream_opener(
+    room: &Room,
+    keepalive: KeepaliveConfig,
+) -> Result<UpstreamConnection, HandlerError> {
+    let upstream = &room.upstream;
+    // an unreachable server must not hold the client forever
+    let (server_socket, _) =
+        tokio::time::timeout(keepalive.handshake_timeout, connect_async(&upstream.url))
+            .await
+            .map_err(|_| HandlerError::UpstreamTimeoutError(upstream.name.clone()))?
+            .map_err(HandlerError::UpstreamConnectError)?;
+    let (mut writer, mut reader) = server_socket.split();
+
+    // hold the session, so the messages of the client wait until it is restored
+    let mut session = Arc::clone(&room.session).lock_owned().await;
+    let resume_frame = tokio::time::timeout(
+        keepalive.handshake_timeout,
+        session_replayer(&mut writer, &mut reader, &mut session, room),
+    )
+    .await
+    .map_err(|_| HandlerError::UpstreamTimeoutError(upstream.name.clone()))??;
+    Ok(UpstreamConnection {
+        writer,
+        reader,
+        session,
+        resume_frame,
+    })
+}
+
+/// Says hello to a new server and re-opens the stream of the room, where it was lost if possible.
+async fn session_replayer(
+    writer: &mut WebSocketServerWriter,
+    reader: &mut WebSocketServerReader,
+    session: &mut SessionState,
+    room: &Room,
+) -> Result<Option<u64>, HandlerError> {
+    control_sender(
+        writer,
+        &ControlMessage::Hello {
+            version: PROTOCOL_VERSION,
+            upstream: None,
+            analyzers: None,
+        },
+    )
+    .await?;
+    match control_receiver(reader).await? {
+        ControlMessage::Hello { version, .. } if version != PROTOCOL_VERSION => {
+            return Err(HandlerError::ProtocolVersionError(version));
+        }
+        ControlMessage::Hello { .. } => {}
+        message => return Err(unexpected_message(&message, "hello")),
+    }
+
+    let resume_frame = session.resume_frame();
+    let Some(open) = session.resume_open() else {
+        return Ok(None);
+    };
+    control_sender(writer, &open).await?;
+    match control_receiver(reader).await? {
+        ControlMessage::AudioInfo { info, seekable } => {
+            let mut audio_info = room.audio_info.write().await;
+            // the windows of the analysis must not mix formats
+            if session.accepted && audio_info.as_ref().is_some_and(|known| known != &info) {
+                return Err(HandlerError::UpstreamResumeError(format!(
+                    "the stream continues in another format ({info:?})"
+                )));
+            }
+            // an open that was never answered is answered now
+            if !session.audio_info_sent {
+                room.broadcast(&ControlMessage::AudioInfo {
+                    info: info.clone(),
+                    seekable,
+                })
+                .await;
+                session.audio_info_sent = true;
+            }
+            *audio_info = Some(info);
+            session.seekable = seekable;
+        }
+        message => return Err(unexpected_message(&message, "audio-info")),
+    }
+    if !session.accepted {
+        return Ok(None);
+    }
+
+    control_sender(writer, &ControlMessage::Accept).await?;
+    if session.paused {
+        control_sender(writer, &ControlMessage::Transport(TransportCommand::Pause)).await?;
+    }
+    if let Some(loop_command) = session.loop_command {
+        control_sender(writer, &ControlMessage::Transport(loop_command)).await?;
+    }
+    Ok(resume_frame)
+}
+
+async fn control_sender(
+    writer: &mut WebSocketServerWriter,
+    message: &ControlMessage,
+) -> Result<(), HandlerError> {
+    writer
+        .send(tungstenite::Message::Text(message.to_json().into()))
+        .await
+        .map_err(HandlerError::TokioTungsteniteError)
+}
+
+/// The next control message of the server; an error event of the server fails the replay.
+async fn control_receiver(
+    reader: &mut WebSocketServerReader,
+) -> Result<ControlMessage, HandlerError> {
+    while let Some(Ok(message)) = reader.next().await {
+        match message {
+            tungstenite::Message::Text(text) => {
+                let message = ControlMessage::try_from(text.as_str()).map_err(|e| {
+                    HandlerError::InvalidControlMessageError(format!("from server: {e}"))
+                })?;
+                if let ControlMessage::Error { status, message } = message {
+                    return Err(HandlerError::UpstreamRefusedError(status, message));
+                }
+                return Ok(message);
+            }
+            tungstenite::Message::Ping(_) | tungstenite::Message::Pong(_) => {}
+            tungstenite::Message::Close(_) => break,
+            tungstenite::Message::Binary(_) | tungstenite::Message::Frame(_) => {
+                return Err(HandlerError::UnexpectedMessageTypeError);
+            }
+        }
+    }
+    Err(HandlerError::UpstreamLostError(
+        "the connection closed while the session was restored".into(),
+    ))
+}
+
+fn unexpected_message(message: &ControlMessage, expected: &str) -> HandlerError {
+    HandlerError::UnexpectedMessageError(format!(
+        "{} from server, expected {expected}",
+        message.kind()
+    ))
+}
@@ -0,0 +1,108 @@
+use crate::{
+    errors::handler::HandlerError,
+    models::{
+        analysis::{Analyzers, DecodedWindow},
+        analysis_pool::AnalysisPool,
+        audio::RwLockAudioInfo,
+        packet::{MessagePack, WindowPacket},
+        room::Room,
+        stage::Stage,
+        window::WindowFunction,
+    },
+};
+use futures_util::future::join_all;
+use protocol::models::frame::frame_splitter;
+use std::{collections::BTreeMap, sync::Arc};
+
+// [task4] window data processing
+pub async fn window_data_processing(
+    window_stage: Arc<Stage<WindowPacket>>,
+    room: Arc<Room>,
+    window_function: WindowFunction,
+    analysis_pool: Arc<AnalysisPool>,
+) -> Result<(), HandlerError> {
+    //? Receiver (Consumer) //
+    loop {
+        let window_packet = window_stage.pop().await;
+        //* step9: analyze pcm data *//
+        let binary = window_encoder(
+            window_packet,
+            &room.audio_info,
+            window_function,
+            &room.analyzers,
+            &analysis_pool,
+        )
+        .await?;
+
+        //* step10: send binary data to every client of the room *//
+        room.broadcast_window(binary).await;
+    }
+}
+
+/// Sends the window in a MessagePack together with the features of every analyzer,
+/// found in the mono downmix of the window weighted by `window_function`.
+///
+/// Without analyzers the window is sent as it is.
+async fn window_encoder(
+    window_packet: WindowPacket,
+    shared_audio_info: &RwLockAudioInfo,
+    window_function: WindowFunction,
+    analyzers: &Analyzers,
+    analysis_pool: &AnalysisPool,
+) -> Result<Vec<u8>, HandlerError> {
+    if analyzers.is_empty() {
+        return Ok(window_packet.bytes);
+    }
+    let audio_info = shared_audio_info
+        .read()
+        .await
+        .clone()
+        .ok_or(HandlerError::AudioInfoUndefinedError)?;
+
+    // downmix the frames of the window to mono
+    let frames = frame_splitter(&window_packet.bytes)?;
+    let mut window = DecodedWindow {
+        first_frame: frames.first().map_or(0, |(header, _)| header.first_frame),
+        samples: Vec::new(),
+    };
+    for (header, payload) in frames {
+        let channels = header.channels.max(1) as usize;
+        window.samples.extend(
+            header
+                .decode_samples(payload)
+                .chunks_exact(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+        );
+    }
+    // taper the analyzed samples only, the clients play the PCM of the window as it is
+    window_function.apply(&mut window.samples);
+
+    // the analyzers run side by side on the pool, off the async runtime,
+    // an analyzer that fails or misses the deadline leaves the features of the others
+    let window = Arc::new(window);
+    let audio_info = Arc::new(audio_info);
+    let results = join_all(analyzers.iter().map(|analyzer| {
+        analysis_pool.run(
+            Arc::clone(analyzer),
+            Arc::clone(&window),
+            Arc::clone(&audio_info),
+            window_packet.cut_at,
+        )
+    }))
+    .await;
+    let features: BTreeMap<_, _> = analyzers
+        .iter()
+        .zip(results)
+        .filter_map(|(analyzer, found)| Some((analyzer.name(), found?)))
+        .collect();
+    let bpm = analyzers
+        .iter()
+        .find_map(|analyzer| features.get(analyzer.name())?.bpm);
+
+    //* step9.5: create message pack *//
+    Ok(rmp_serde::to_vec_named(&MessagePack {
+        pcm: window_packet.bytes,
+        bpm,
+        features,
+    })?)
+}
@@ -1,3 +1,3 @@
-pub mod app;
+pub mod analysis;
 pub mod handler;
 pub mod root;
@@ -0,0 +1,11 @@
+/// The failure of an analyzer on one window, the other analyzers of the room go on (see window_encoder).
+#[derive(Debug, thiserror::Error)]
+pub enum AnalysisError {
+    #[error("InvalidWindowError: {0}")]
+    InvalidWindowError(String),
+    #[error("PythonWorkerError: {0}")]
+    PythonWorkerError(String),
+    #[cfg(feature = "python-analysis")]
+    #[error(transparent)]
+    PyError(#[from] pyo3::PyErr),
+}
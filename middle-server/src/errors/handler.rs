@@ -1,5 +1,6 @@
-use super::app::AppError;
 use axum::http::StatusCode;
+use protocol::errors::app::AppError;
+use protocol::{errors::protocol::ProtocolError, models::protocol::PROTOCOL_VERSION};
 
 #[derive(Debug, thiserror::Error)]
 pub enum HandlerError {
@@ -7,6 +8,31 @@ pub enum HandlerError {
     UnexpectedMessageTypeError,
     #[error("UnexpectedMessageError: {0}")]
     UnexpectedMessageError(String),
+    #[error("InvalidControlMessageError: {0}")]
+    InvalidControlMessageError(String),
+    #[error(
+        "ProtocolVersionError: peer speaks version {0}, this server speaks version {PROTOCOL_VERSION}"
+    )]
+    ProtocolVersionError(u32),
+    #[error(transparent)]
+    ProtocolError(#[from] ProtocolError),
+    #[error("UpstreamTimeoutError: no connection to {0} within the handshake timeout")]
+    UpstreamTimeoutError(String),
+    #[error("UpstreamConnectError: {0}")]
+    UpstreamConnectError(tokio_tungstenite::tungstenite::Error),
+    #[error("UnknownUpstreamError: {0} is not an allowed upstream")]
+    UnknownUpstreamError(String),
+    #[error("UnknownAnalyzerError: {0}")]
+    UnknownAnalyzerError(String),
+    #[error("UpstreamLostError: {0}")]
+    UpstreamLostError(String),
+    #[error("TransportForbiddenError: {0}")]
+    TransportForbiddenError(String),
+    #[error("UpstreamResumeError: {0}")]
+    UpstreamResumeError(String),
+    /// The error event of the server, relayed with its status and message.
+    #[error("{1}")]
+    UpstreamRefusedError(u16, String),
     #[error(transparent)]
     SetGlobalDefaultError(#[from] tracing::subscriber::SetGlobalDefaultError),
     #[error(transparent)]
@@ -19,8 +45,10 @@ pub enum HandlerError {
     ParseIntError(#[from] std::num::ParseIntError),
     #[error("AudioInfoError: {0}")]
     AudioInfoError(String),
+    #[error("AudioInfoUndefinedError: Audio info is not set")]
+    AudioInfoUndefinedError,
     #[error(transparent)]
-    MpscSenderError(#[from] tokio::sync::mpsc::error::SendError<Vec<u8>>),
+    RmpSerdeEncodeError(#[from] rmp_serde::encode::Error),
     #[error(transparent)]
     TokioJoinError(#[from] tokio::task::JoinError),
 }
@@ -36,6 +64,59 @@ impl From<HandlerError> for AppError {
                 status_code: StatusCode::BAD_REQUEST,
                 message: format!("UnexpectedMessageError: {e}"),
             },
+            HandlerError::InvalidControlMessageError(e) => AppError {
+                status_code: StatusCode::BAD_REQUEST,
+                message: format!("InvalidControlMessageError: {e}"),
+            },
+            HandlerError::ProtocolVersionError(version) => AppError {
+                status_code: StatusCode::UPGRADE_REQUIRED,
+                message: format!(
+                    "ProtocolVersionError: peer speaks version {version}, this server speaks version {PROTOCOL_VERSION}"
+                ),
+            },
+            // the message of a protocol error already starts with its variant name
+            HandlerError::ProtocolError(e) => AppError {
+                status_code: match e {
+                    // frames come from the server
+                    ProtocolError::InvalidFrameError(_) => StatusCode::BAD_GATEWAY,
+                    ProtocolError::InvalidCommandError(_) => StatusCode::BAD_REQUEST,
+                },
+                message: e.to_string(),
+            },
+            HandlerError::UpstreamConnectError(e) => AppError {
+                status_code: StatusCode::BAD_GATEWAY,
+                message: format!("UpstreamConnectError: {e}"),
+            },
+            HandlerError::UnknownUpstreamError(name) => AppError {
+                status_code: StatusCode::FORBIDDEN,
+                message: format!("UnknownUpstreamError: {name} is not an allowed upstream"),
+            },
+            HandlerError::UnknownAnalyzerError(e) => AppError {
+                status_code: StatusCode::BAD_REQUEST,
+                message: format!("UnknownAnalyzerError: {e}"),
+            },
+            HandlerError::UpstreamLostError(e) => AppError {
+                status_code: StatusCode::BAD_GATEWAY,
+                message: format!("UpstreamLostError: {e}"),
+            },
+            HandlerError::TransportForbiddenError(e) => AppError {
+                status_code: StatusCode::FORBIDDEN,
+                message: format!("TransportForbiddenError: {e}"),
+            },
+            HandlerError::UpstreamResumeError(e) => AppError {
+                status_code: StatusCode::BAD_GATEWAY,
+                message: format!("UpstreamResumeError: {e}"),
+            },
+            HandlerError::UpstreamRefusedError(status, message) => AppError {
+                status_code: StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY),
+                message,
+            },
+            HandlerError::UpstreamTimeoutError(e) => AppError {
+                status_code: StatusCode::GATEWAY_TIMEOUT,
+                message: format!(
+                    "UpstreamTimeoutError: no connection to {e} within the handshake timeout"
+                ),
+            },
             HandlerError::SetGlobalDefaultError(e) => AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
                 message: format!("SetGlobalDefaultError: {e}"),
@@ -60,9 +141,13 @@ impl From<HandlerError> for AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
                 message: format!("AudioInfoError: {e}"),
             },
-            HandlerError::MpscSenderError(e) => AppError {
+            HandlerError::AudioInfoUndefinedError => AppError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: "AudioInfoUndefinedError: Audio info is not set".into(),
+            },
+            HandlerError::RmpSerdeEncodeError(e) => AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
-                message: format!("MpscSenderError: {e}"),
+                message: format!("RmpSerdeEncodeError: {e}"),
             },
             HandlerError::TokioJoinError(e) => AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
@@ -4,4 +4,16 @@ pub enum RootError {
     SetGlobalDefaultError(#[from] tracing::subscriber::SetGlobalDefaultError),
     #[error(transparent)]
     IoError(#[from] std::io::Error),
+    #[error("InvalidKeepaliveError: {0}")]
+    InvalidKeepaliveError(String),
+    #[error("InvalidUpstreamError: {0}")]
+    InvalidUpstreamError(String),
+    #[error("InvalidReconnectError: {0}")]
+    InvalidReconnectError(String),
+    #[error("InvalidWindowError: {0}")]
+    InvalidWindowError(String),
+    #[error("InvalidStageError: {0}")]
+    InvalidStageError(String),
+    #[error("InvalidAnalysisError: {0}")]
+    InvalidAnalysisError(String),
 }
@@ -1,34 +1,75 @@
 use crate::{
     applications::{
-        client_to_server::handle_client_to_server, pcm::pcm_data_processing,
-        server_to_client::handle_server_to_client,
+        client_to_server::{analyzer_names, handle_client_to_server, hello_receiver},
+        keepalive::close_client,
+        room::{client_queue_sender, room_leaver},
+    },
+    errors::handler::HandlerError,
+    models::{
+        analysis::AnalysisConfig,
+        client_queue::ClientQueue,
+        room::{MutexRoomRegistry, MutexSubscription, RoomConfig},
+        shared_state::RwLockSharedState,
+        upstream::{SessionQuery, UpstreamConfig},
     },
-    errors::{app::AppError, handler::HandlerError},
-    models::{shared_state::RwLockSharedState, ws::MutexWebSocketClientWriter},
 };
-use axum::extract::ws::WebSocket;
+use axum::extract::ws::{Message, WebSocket};
 use axum::{
-    extract::{State, WebSocketUpgrade},
+    extract::{Query, State, WebSocketUpgrade},
     response::IntoResponse,
 };
-use futures_util::StreamExt;
+use futures_util::{SinkExt, StreamExt};
+use protocol::{
+    errors::app::AppError,
+    models::{
+        keepalive::KeepaliveConfig,
+        protocol::{CLOSE_HANDSHAKE_TIMEOUT, ControlMessage, PROTOCOL_VERSION},
+        ws::{MutexWebSocketClientWriter, WebSocketClientReader},
+    },
+};
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tokio_tungstenite::connect_async;
 
 //* constant values *//
-static SERVER_URL: &str = "ws://localhost:5001";
-static WINDOW_SIZE: u64 = 200;
-static SLIDE_SIZE: u64 = 100;
-static PCM_CHANNEL_CAPACITY: u64 = 1000;
+// windows queued for a client before its oldest one is dropped (see ClientQueue)
+static CLIENT_WINDOW_CAPACITY: usize = 16;
 
 // handler
 pub async fn websocket_handler(
-    State(_shared_state): State<RwLockSharedState>, // unused shared state
+    State(shared_state): State<RwLockSharedState>,
+    Query(query): Query<SessionQuery>,
     web_socket: WebSocketUpgrade,
 ) -> Result<impl IntoResponse, AppError> {
-    let response = web_socket.on_upgrade(|socket| async move {
-        if let Err(error) = websocket_processing(socket).await {
+    let (keepalive, upstream_config, analysis, room_config, rooms) = {
+        let shared_state = shared_state.read().await;
+        let room_config = RoomConfig {
+            keepalive: shared_state.keepalive,
+            reconnect: shared_state.reconnect,
+            window: shared_state.window,
+            pcm_stage: shared_state.pcm_stage,
+            window_stage: shared_state.window_stage,
+            analysis_pool: Arc::clone(&shared_state.analysis_pool),
+        };
+        (
+            shared_state.keepalive,
+            Arc::clone(&shared_state.upstream),
+            Arc::clone(&shared_state.analysis),
+            room_config,
+            Arc::clone(&shared_state.rooms),
+        )
+    };
+    let response = web_socket.on_upgrade(move |socket| async move {
+        if let Err(error) = websocket_processing(
+            socket,
+            keepalive,
+            upstream_config,
+            analysis,
+            room_config,
+            rooms,
+            query,
+        )
+        .await
+        {
             tracing::error!("WebSocket processing error: {:?}", error);
         }
         tracing::info!("WebSocket connection closed.");
@@ -37,13 +78,15 @@ pub async fn websocket_handler(
 }
 
 // websocket
-pub async fn websocket_processing(client_socket: WebSocket) -> Result<(), AppError> {
-    // connect to the server
-    let (server_socket, _) = connect_async(SERVER_URL)
-        .await
-        .map_err(HandlerError::TokioTungsteniteError)?;
-    tracing::info!("Connection to server established.");
-
+pub async fn websocket_processing(
+    client_socket: WebSocket,
+    keepalive: KeepaliveConfig,
+    upstream_config: Arc<UpstreamConfig>,
+    analysis: Arc<AnalysisConfig>,
+    room_config: RoomConfig,
+    rooms: MutexRoomRegistry,
+    query: SessionQuery,
+) -> Result<(), AppError> {
     // split client and server sockets
     /*
         - tokio (tokio::net::TcpStream)
@@ -60,37 +103,135 @@ pub async fn websocket_processing(client_socket: WebSocket) -> Result<(), AppErr
         https://docs.rs/tokio-tungstenite/latest/tokio_tungstenite/struct.WebSocketStream.html#method.split
     */
     let (client_writer, client_reader) = client_socket.split();
-    let (server_writer, server_reader) = server_socket.split();
 
     // wrap in Arc<Mutex<T>> to safely write to the client from multiple tasks
     let shared_client_writer: MutexWebSocketClientWriter = Arc::new(Mutex::new(client_writer));
 
-    // create tokio::sync::mpsc channel for streaming PCM data
-    let (pcm_tx, pcm_rx) = tokio::sync::mpsc::channel::<Vec<u8>>(PCM_CHANNEL_CAPACITY as usize);
+    let result = relay_session(
+        client_reader,
+        Arc::clone(&shared_client_writer),
+        keepalive,
+        &upstream_config,
+        &analysis,
+        room_config,
+        rooms,
+        query,
+    )
+    .await;
+
+    // report the failure to the client and close the connection with the matching close code
+    // (errors of the server and of the room are relayed by task2 as they are)
+    if let Err(error) = &result {
+        let message = error.to_control_message();
+        let report = async {
+            let mut writer = shared_client_writer.lock().await;
+            writer.send(Message::Text(message.to_json().into())).await?;
+            writer
+                .send(Message::Close(Some(error.to_close_frame())))
+                .await
+        };
+        let _ = tokio::time::timeout(keepalive.idle_timeout, report).await;
+    }
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn relay_session(
+    mut client_reader: WebSocketClientReader,
+    shared_client_writer: MutexWebSocketClientWriter,
+    keepalive: KeepaliveConfig,
+    upstream_config: &UpstreamConfig,
+    analysis: &AnalysisConfig,
+    room_config: RoomConfig,
+    rooms: MutexRoomRegistry,
+    query: SessionQuery,
+) -> Result<(), AppError> {
+    //* step0: receive hello from client *//
+    let hello = match tokio::time::timeout(
+        keepalive.handshake_timeout,
+        hello_receiver(&mut client_reader),
+    )
+    .await
+    {
+        Ok(hello) => hello?,
+        Err(_) => {
+            tracing::warn!("Client timed out: handshake timeout");
+            close_client(
+                &shared_client_writer,
+                CLOSE_HANDSHAKE_TIMEOUT,
+                "handshake timeout",
+            )
+            .await;
+            return Ok(());
+        }
+    };
+    let Some(hello) = hello else {
+        tracing::info!("Client disconnected before hello");
+        return Ok(());
+    };
+
+    // the hello field takes precedence over the query, only names of the allowlist are accepted
+    let requested_upstream = hello.upstream.or(query.upstream);
+    let upstream = upstream_config
+        .find(requested_upstream.as_deref())
+        .ok_or_else(|| HandlerError::UnknownUpstreamError(requested_upstream.unwrap_or_default()))?
+        .clone();
+    // the same for the analyzers, only the ones of this build are accepted
+    let analyzers = analysis
+        .find(hello.analyzers.as_deref(), query.analyzers.as_deref())
+        .map_err(HandlerError::UnknownAnalyzerError)?;
+
+    // everything sent to the client goes through its queue, the windows of its room included
+    let queue = Arc::new(ClientQueue::new(CLIENT_WINDOW_CAPACITY));
+    // the room of the stream the client opened, the server is connected by the room
+    let shared_subscription: MutexSubscription = Arc::new(Mutex::new(None));
+
+    //* step0: answer the hello of the client with the upstream it is relayed to and its analyzers *//
+    let hello = ControlMessage::Hello {
+        version: PROTOCOL_VERSION,
+        upstream: Some(upstream.name.clone()),
+        analyzers: Some(analyzer_names(&analyzers)),
+    };
+    queue.push(Message::Text(hello.to_json().into())).await;
 
     //* --- Start independent tasks --- *//
-    // [task1] client -> server
-    let client_read_task = tokio::spawn(handle_client_to_server(client_reader, server_writer));
-    // [task2] server -> client
-    let server_read_task = tokio::spawn(handle_server_to_client(
-        server_reader,
-        pcm_tx,
+    // [task1] client -> room
+    let mut client_read_task = tokio::spawn(handle_client_to_server(
+        client_reader,
         Arc::clone(&shared_client_writer),
+        Arc::clone(&queue),
+        Arc::clone(&shared_subscription),
+        Arc::clone(&rooms),
+        upstream,
+        analyzers,
+        room_config,
+        keepalive,
     ));
-    // [task3] pcm data processing
-    let pcm_processing_task = tokio::spawn(pcm_data_processing(
-        WINDOW_SIZE,
-        SLIDE_SIZE,
-        pcm_rx,
+    // [task2] room -> client
+    let mut client_write_task = tokio::spawn(client_queue_sender(
+        Arc::clone(&queue),
         Arc::clone(&shared_client_writer),
     ));
 
-    //* When one of the tasks is completed, tokio make the other tasks also complete. *//
-    (tokio::select! {
-        response = client_read_task => response,
-        response = server_read_task => response,
-        response = pcm_processing_task => response,
-    })
-    .map_err(HandlerError::TokioJoinError)??;
-    Ok(())
+    //* When one of the tasks is completed, the other task is aborted. *//
+    let result = tokio::select! {
+        response = &mut client_read_task => response,
+        response = &mut client_write_task => response,
+    };
+    // a dropped JoinHandle would leave the task running
+    client_read_task.abort();
+    client_write_task.abort();
+
+    // the room is closed with its last client
+    if let Some(subscription) = shared_subscription.lock().await.take() {
+        room_leaver(&rooms, subscription).await;
+    }
+    let dropped_windows = queue.dropped_windows().await;
+    if dropped_windows > 0 {
+        tracing::warn!(
+            "{} window(s) were dropped for a slow client",
+            dropped_windows
+        );
+    }
+    Ok(result.map_err(HandlerError::TokioJoinError)??)
 }
@@ -1,7 +1,24 @@
-use crate::{errors::root::RootError, handlers::ws::websocket_handler};
+use crate::{
+    applications::analysis::analyzer_registry,
+    errors::root::RootError,
+    handlers::ws::websocket_handler,
+    models::{
+        analysis::AnalysisConfig,
+        analysis_pool::{AnalysisPool, AnalysisPoolConfig},
+        cli::Cli,
+        python_worker::PythonWorkerConfig,
+        reconnect::ReconnectConfig,
+        shared_state::SharedState,
+        stage::StageConfig,
+        upstream::{Upstream, UpstreamConfig},
+        window::WindowConfig,
+    },
+};
 use axum::{Router, extract::DefaultBodyLimit, routing::get};
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use clap::Parser;
+use protocol::models::keepalive::KeepaliveConfig;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::sync::{Mutex, RwLock};
 use tower_http::cors::CorsLayer;
 
 pub mod applications;
@@ -15,13 +32,80 @@ static PORT: u16 = 7001;
 
 #[tokio::main]
 async fn main() -> Result<(), RootError> {
-    // shared object
-    let shared_state = Arc::new(RwLock::new(0));
+    let cli = Cli::parse();
     // tracing
     let subscriber = tracing_subscriber::FmtSubscriber::builder()
         .with_max_level(tracing::Level::DEBUG)
         .finish();
     tracing::subscriber::set_global_default(subscriber)?;
+    // ping interval, idle and handshake timeouts of both legs (--keepalive-interval-secs / --idle-timeout-secs / --handshake-timeout-secs)
+    let keepalive = KeepaliveConfig::new(
+        Duration::from_secs(cli.keepalive_interval_secs),
+        Duration::from_secs(cli.idle_timeout_secs),
+        Duration::from_secs(cli.handshake_timeout_secs),
+    )
+    .map_err(RootError::InvalidKeepaliveError)?;
+    // the servers the clients may choose from (--upstreams / UPSTREAMS, see Upstream::parse_list)
+    let upstreams =
+        Upstream::parse_list(&cli.upstreams).map_err(RootError::InvalidUpstreamError)?;
+    let upstream = UpstreamConfig::new(upstreams, cli.default_upstream)
+        .map_err(RootError::InvalidUpstreamError)?;
+    for Upstream { name, url } in &upstream.upstreams {
+        tracing::info!("upstream {}: {}", name, url);
+    }
+    // backoff of the upstream reconnects (see Cli for the variables)
+    let reconnect = ReconnectConfig::new(
+        cli.reconnect_attempts,
+        Duration::from_millis(cli.reconnect_delay_ms),
+        Duration::from_millis(cli.reconnect_max_delay_ms),
+    )
+    .map_err(RootError::InvalidReconnectError)?;
+    // the analysis windows of every room (--window / --hop / --window-function, see WindowConfig)
+    let window = WindowConfig::new(&cli.window, &cli.hop, &cli.window_function)
+        .map_err(RootError::InvalidWindowError)?;
+    // the processes of the librosa-worker analyzer (--python / --python-workers / --python-worker-timeout-ms)
+    let python_worker = PythonWorkerConfig::new(
+        cli.python,
+        cli.python_worker_script,
+        cli.python_workers,
+        Duration::from_millis(cli.python_worker_timeout_ms),
+    )
+    .map_err(RootError::InvalidAnalysisError)?;
+    // the analyzers of this build and the default ones (--analyzers, see AnalyzerRegistry::parse_list)
+    let analysis = AnalysisConfig::new(analyzer_registry(python_worker), &cli.analyzers)
+        .map_err(RootError::InvalidAnalysisError)?;
+    tracing::info!(
+        "analyzers: {:?} (default {:?})",
+        analysis.registry.names(),
+        analysis
+            .default
+            .iter()
+            .map(|a| a.name())
+            .collect::<Vec<_>>()
+    );
+    // the threads the analyzers run on (--analysis-workers / --analysis-deadline-ms)
+    let analysis_pool = AnalysisPoolConfig::new(
+        cli.analysis_workers,
+        Duration::from_millis(cli.analysis_deadline_ms),
+    )
+    .map_err(RootError::InvalidAnalysisError)?;
+    // the queues between the PCM, windowing and analysis tasks of every room (see StagePolicy)
+    let pcm_stage = StageConfig::new(cli.pcm_capacity, &cli.pcm_policy)
+        .map_err(RootError::InvalidStageError)?;
+    let window_stage = StageConfig::new(cli.window_capacity, &cli.window_policy)
+        .map_err(RootError::InvalidStageError)?;
+    // shared object
+    let shared_state = Arc::new(RwLock::new(SharedState {
+        keepalive,
+        upstream: Arc::new(upstream),
+        reconnect,
+        window,
+        analysis: Arc::new(analysis),
+        analysis_pool: Arc::new(AnalysisPool::new(analysis_pool)),
+        pcm_stage,
+        window_stage,
+        rooms: Arc::new(Mutex::new(HashMap::new())),
+    }));
     // cors
     let cors = CorsLayer::new().allow_origin(tower_http::cors::Any);
 
@@ -1,2 +1,15 @@
+pub mod analysis;
+pub mod analysis_pool;
+pub mod audio;
+pub mod cli;
+pub mod client_queue;
+pub mod frame_ring;
+pub mod packet;
+pub mod python_worker;
+pub mod reconnect;
+pub mod room;
+pub mod session;
 pub mod shared_state;
-pub mod ws;
+pub mod stage;
+pub mod upstream;
+pub mod window;
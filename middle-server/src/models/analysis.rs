@@ -0,0 +1,174 @@
+use crate::errors::analysis::AnalysisError;
+use protocol::models::audio::AudioInfo;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// A window decoded for the analysis.
+pub struct DecodedWindow {
+    /// The position of the first frame of the window in the stream.
+    pub first_frame: u64,
+    /// The mono downmix of the window, weighted by the window function (see WindowFunction).
+    pub samples: Vec<f32>,
+}
+
+/// What an analyzer found in a window, the fields it does not estimate are left out.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Features {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bpm: Option<f64>,
+}
+
+/// A backend that extracts features from every window of a room.
+pub trait Analyzer: Send + Sync {
+    /// The name clients and the configuration select the analyzer with.
+    fn name(&self) -> &'static str;
+
+    /// Analyzes a window, on a thread that may block (see window_encoder).
+    fn analyze(
+        &self,
+        window: &DecodedWindow,
+        audio_info: &AudioInfo,
+    ) -> Result<Features, AnalysisError>;
+
+    /// The state of the processes the analyzer runs on, for the ones outside the relay.
+    fn health(&self) -> Option<String> {
+        None
+    }
+}
+
+/// The analyzers run on the windows of a room, in the order they were selected.
+pub type Analyzers = Vec<Arc<dyn Analyzer>>;
+
+/// The analyzers built into the relay, by name.
+#[derive(Default)]
+pub struct AnalyzerRegistry {
+    analyzers: Vec<Arc<dyn Analyzer>>,
+}
+
+impl AnalyzerRegistry {
+    pub fn register(&mut self, analyzer: Arc<dyn Analyzer>) {
+        self.analyzers.retain(|a| a.name() != analyzer.name());
+        self.analyzers.push(analyzer);
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.analyzers.iter().map(|a| a.name()).collect()
+    }
+
+    /// Looks up the analyzers named in a selection, e.g. `["native", "mock"]` (none for an empty one).
+    pub fn select<'a>(
+        &self,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Analyzers, String> {
+        let mut selected: Analyzers = Vec::new();
+        for name in names.into_iter().map(str::trim) {
+            let analyzer = self
+                .analyzers
+                .iter()
+                .find(|a| a.name() == name)
+                .ok_or_else(|| format!("unknown analyzer: {name}"))?;
+            if selected.iter().any(|a| a.name() == name) {
+                return Err(format!("duplicate analyzer: {name}"));
+            }
+            selected.push(Arc::clone(analyzer));
+        }
+        Ok(selected)
+    }
+
+    /*
+        FORMAT: <name>[,...] | off
+        e.g. native, native,librosa
+    */
+    pub fn parse_list(&self, text: &str) -> Result<Analyzers, String> {
+        match text.trim() {
+            "off" | "" => Ok(Vec::new()),
+            text => self.select(text.split(',')),
+        }
+    }
+}
+
+// librosa stays the default of the builds that ship it
+#[cfg(feature = "python-analysis")]
+pub static DEFAULT_ANALYZERS: &str = "librosa";
+#[cfg(not(feature = "python-analysis"))]
+pub static DEFAULT_ANALYZERS: &str = "native";
+
+/// The analyzers of the deployment and the ones run for clients that do not choose.
+pub struct AnalysisConfig {
+    pub registry: AnalyzerRegistry,
+    pub default: Analyzers,
+}
+
+impl AnalysisConfig {
+    pub fn new(registry: AnalyzerRegistry, default: &str) -> Result<Self, String> {
+        let default = registry.parse_list(default)?;
+        Ok(AnalysisConfig { registry, default })
+    }
+
+    /// The analyzers a client asked for in its hello (a list) or its query (`<name>[,...] | off`),
+    /// the default ones if it asked for none.
+    pub fn find(&self, hello: Option<&[String]>, query: Option<&str>) -> Result<Analyzers, String> {
+        match (hello, query) {
+            (Some(names), _) => self.registry.select(names.iter().map(String::as_str)),
+            (None, Some(query)) => self.registry.parse_list(query),
+            (None, None) => Ok(self.default.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(&'static str);
+
+    impl Analyzer for Fixed {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn analyze(&self, _: &DecodedWindow, _: &AudioInfo) -> Result<Features, AnalysisError> {
+            Ok(Features::default())
+        }
+    }
+
+    fn config(default: &str) -> AnalysisConfig {
+        let mut registry = AnalyzerRegistry::default();
+        registry.register(Arc::new(Fixed("a")));
+        registry.register(Arc::new(Fixed("b")));
+        AnalysisConfig::new(registry, default).unwrap()
+    }
+
+    fn names(analyzers: Analyzers) -> Vec<&'static str> {
+        analyzers.iter().map(|a| a.name()).collect()
+    }
+
+    #[test]
+    fn the_hello_takes_precedence_over_the_query_and_the_default() {
+        let config = config("a");
+        let hello = vec!["b".to_string(), "a".to_string()];
+        assert_eq!(
+            names(config.find(Some(&hello), Some("a")).unwrap()),
+            ["b", "a"]
+        );
+        assert_eq!(
+            names(config.find(Some(&[]), Some("a")).unwrap()),
+            Vec::<&str>::new()
+        );
+        assert_eq!(names(config.find(None, Some("b")).unwrap()), ["b"]);
+        assert_eq!(
+            names(config.find(None, Some("off")).unwrap()),
+            Vec::<&str>::new()
+        );
+        assert_eq!(names(config.find(None, None).unwrap()), ["a"]);
+    }
+
+    #[test]
+    fn only_registered_analyzers_are_selected_once() {
+        let config = config("off");
+        assert!(config.default.is_empty());
+        assert!(config.find(None, Some("a,c")).is_err());
+        assert!(config.find(None, Some("a,a")).is_err());
+        assert!(AnalysisConfig::new(AnalyzerRegistry::default(), "a").is_err());
+    }
+}
@@ -0,0 +1,275 @@
+use crate::models::analysis::{Analyzer, DecodedWindow, Features};
+use protocol::models::audio::AudioInfo;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::Semaphore;
+
+/// How many analyses run at once (across every room) and how long a window may wait for its features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnalysisPoolConfig {
+    pub workers: usize,
+    /// From the moment the window is cut, `None` to wait for every analysis.
+    pub deadline: Option<Duration>,
+}
+
+impl AnalysisPoolConfig {
+    pub fn new(workers: usize, deadline: Duration) -> Result<Self, String> {
+        if workers == 0 {
+            return Err("the analysis pool needs at least 1 worker".into());
+        }
+        Ok(AnalysisPoolConfig {
+            workers,
+            deadline: (!deadline.is_zero()).then_some(deadline),
+        })
+    }
+}
+
+/// What became of the analyses of one analyzer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AnalyzerMetrics {
+    pub completed: u64,
+    pub failed: u64,
+    /// The deadline passed while the analysis waited for a worker, it never ran.
+    pub skipped: u64,
+    /// The deadline passed while the analysis ran, its result was dropped.
+    pub overdue: u64,
+    /// From the window being cut to a worker picking the analysis up.
+    pub total_queue_wait: Duration,
+    pub max_queue_wait: Duration,
+    /// The time spent on a worker (overdue analyses included once they finish).
+    pub total_execution: Duration,
+    pub max_execution: Duration,
+}
+
+/// The blocking threads the analyzers run on, away from the async runtime (see spawn_blocking).
+///
+/// A semaphore bounds the analyses running at once, since a librosa call holds a thread for hundreds of milliseconds.
+pub struct AnalysisPool {
+    config: AnalysisPoolConfig,
+    workers: Arc<Semaphore>,
+    // also written by the blocking threads
+    metrics: Arc<Mutex<BTreeMap<&'static str, AnalyzerMetrics>>>,
+}
+
+impl AnalysisPool {
+    pub fn new(config: AnalysisPoolConfig) -> Self {
+        AnalysisPool {
+            config,
+            workers: Arc::new(Semaphore::new(config.workers)),
+            metrics: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    fn record(
+        metrics: &Mutex<BTreeMap<&'static str, AnalyzerMetrics>>,
+        analyzer: &'static str,
+        update: impl FnOnce(&mut AnalyzerMetrics),
+    ) {
+        let mut metrics = metrics.lock().unwrap_or_else(|e| e.into_inner());
+        update(metrics.entry(analyzer).or_default());
+    }
+
+    /// Runs an analyzer on a window cut at `cut_at`, returning `None` if it failed or missed the deadline.
+    pub async fn run(
+        &self,
+        analyzer: Arc<dyn Analyzer>,
+        window: Arc<DecodedWindow>,
+        audio_info: Arc<AudioInfo>,
+        cut_at: Instant,
+    ) -> Option<Features> {
+        let name = analyzer.name();
+        let deadline = self.config.deadline.map(|deadline| cut_at + deadline);
+        let until_deadline = async {
+            match deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(until_deadline);
+
+        //* wait for a worker, unless the window is overdue by then *//
+        let permit = tokio::select! {
+            permit = Arc::clone(&self.workers).acquire_owned() => permit.ok()?,
+            _ = &mut until_deadline => {
+                Self::record(&self.metrics, name, |m| m.skipped += 1);
+                tracing::warn!(
+                    "Analyzer {} skipped the window at frame {}: no worker within the deadline",
+                    name,
+                    window.first_frame
+                );
+                return None;
+            }
+        };
+        let queue_wait = cut_at.elapsed();
+        Self::record(&self.metrics, name, |m| {
+            m.total_queue_wait += queue_wait;
+            m.max_queue_wait = m.max_queue_wait.max(queue_wait);
+        });
+
+        //* analyze on a blocking thread, which holds the worker until the analyzer returns *//
+        let metrics = Arc::clone(&self.metrics);
+        let first_frame = window.first_frame;
+        let mut analysis = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let started = Instant::now();
+            let result = analyzer.analyze(&window, &audio_info);
+            let execution = started.elapsed();
+            Self::record(&metrics, name, |m| {
+                m.total_execution += execution;
+                m.max_execution = m.max_execution.max(execution);
+            });
+            result
+        });
+        let result = tokio::select! {
+            result = &mut analysis => result,
+            _ = &mut until_deadline => {
+                // a running analysis cannot be interrupted, its worker is released once it returns
+                Self::record(&self.metrics, name, |m| m.overdue += 1);
+                tracing::warn!(
+                    "Analyzer {} overran the deadline on the window at frame {}, its result is dropped",
+                    name,
+                    first_frame
+                );
+                return None;
+            }
+        };
+        match result {
+            Ok(Ok(features)) => {
+                Self::record(&self.metrics, name, |m| m.completed += 1);
+                Some(features)
+            }
+            Ok(Err(e)) => {
+                Self::record(&self.metrics, name, |m| m.failed += 1);
+                tracing::warn!(
+                    "Analyzer {} failed on the window at frame {}: {}",
+                    name,
+                    first_frame,
+                    e
+                );
+                None
+            }
+            Err(e) => {
+                Self::record(&self.metrics, name, |m| m.failed += 1);
+                tracing::error!("Analyzer {} panicked: {}", name, e);
+                None
+            }
+        }
+    }
+
+    pub fn metrics(&self) -> BTreeMap<&'static str, AnalyzerMetrics> {
+        self.metrics
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::analysis::AnalysisError;
+    use protocol::models::audio::PcmFormat;
+
+    /// Replies at once.
+    struct Immediate(&'static str);
+
+    impl Analyzer for Immediate {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn analyze(&self, _: &DecodedWindow, _: &AudioInfo) -> Result<Features, AnalysisError> {
+            Ok(Features { bpm: Some(120.0) })
+        }
+    }
+
+    /// Holds its worker from the moment it starts until the test releases it.
+    struct Gated {
+        started: tokio::sync::mpsc::UnboundedSender<()>,
+        release: Mutex<std::sync::mpsc::Receiver<()>>,
+    }
+
+    impl Analyzer for Gated {
+        fn name(&self) -> &'static str {
+            "gated"
+        }
+
+        fn analyze(&self, _: &DecodedWindow, _: &AudioInfo) -> Result<Features, AnalysisError> {
+            let _ = self.started.send(());
+            let _ = self
+                .release
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .recv();
+            Ok(Features { bpm: Some(90.0) })
+        }
+    }
+
+    fn inputs() -> (Arc<DecodedWindow>, Arc<AudioInfo>) {
+        let window = DecodedWindow {
+            first_frame: 0,
+            samples: vec![0.0; 16],
+        };
+        let audio_info = AudioInfo {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            pcm_format: PcmFormat::Int,
+        };
+        (Arc::new(window), Arc::new(audio_info))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn analyses_past_the_deadline_are_dropped_or_skipped() {
+        let deadline = Duration::from_millis(200);
+        let pool = Arc::new(AnalysisPool::new(
+            AnalysisPoolConfig::new(1, deadline).unwrap(),
+        ));
+        let (window, audio_info) = inputs();
+        let (started_sender, mut started) = tokio::sync::mpsc::unbounded_channel();
+        let (release, release_receiver) = std::sync::mpsc::channel();
+        let gated: Arc<dyn Analyzer> = Arc::new(Gated {
+            started: started_sender,
+            release: Mutex::new(release_receiver),
+        });
+        let fast: Arc<dyn Analyzer> = Arc::new(Immediate("fast"));
+
+        // the gated analysis holds the only worker until it is released, past its deadline
+        let gated_run = tokio::spawn({
+            let pool = Arc::clone(&pool);
+            let (window, audio_info) = (Arc::clone(&window), Arc::clone(&audio_info));
+            async move { pool.run(gated, window, audio_info, Instant::now()).await }
+        });
+        started.recv().await.unwrap();
+
+        // a window whose deadline has passed never gets the worker
+        let overdue_cut = Instant::now() - deadline;
+        let fast_result = pool
+            .run(
+                Arc::clone(&fast),
+                Arc::clone(&window),
+                Arc::clone(&audio_info),
+                overdue_cut,
+            )
+            .await;
+        assert_eq!(fast_result, None);
+        assert_eq!(gated_run.await.unwrap(), None);
+
+        // once the worker is released, a fresh window is analyzed
+        release.send(()).unwrap();
+        while pool.workers.available_permits() == 0 {
+            tokio::task::yield_now().await;
+        }
+        let features = pool.run(fast, window, audio_info, Instant::now()).await;
+        assert_eq!(features, Some(Features { bpm: Some(120.0) }));
+
+        let metrics = pool.metrics();
+        assert_eq!(metrics["gated"].overdue, 1);
+        assert_eq!(metrics["gated"].completed, 0);
+        assert_eq!(metrics["fast"].skipped, 1);
+        assert_eq!(metrics["fast"].completed, 1);
+    }
+}
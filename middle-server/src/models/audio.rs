@@ -0,0 +1,5 @@
+use protocol::models::audio::AudioInfo;
+
+// the format of the stream being relayed, from the last audio-info message of the server
+// (frame headers carry everything but the sample rate)
+pub type RwLockAudioInfo = std::sync::Arc<tokio::sync::RwLock<Option<AudioInfo>>>;
@@ -0,0 +1,77 @@
+use crate::models::analysis::DEFAULT_ANALYZERS;
+use clap::Parser;
+
+/// Relays the control messages and PCM of the server to the browser, in windows.
+///
+/// Every option can also be set with the environment variable in brackets.
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+    /// Upstream servers the clients may choose from (`<name>=<ws url>[,...]`)
+    #[arg(long, env = "UPSTREAMS", default_value = "main=ws://localhost:5001")]
+    pub upstreams: String,
+    /// The upstream of clients that do not choose one (the first upstream if omitted)
+    #[arg(long, env = "DEFAULT_UPSTREAM")]
+    pub default_upstream: Option<String>,
+    /// Reconnect attempts when the upstream is unreachable or lost (0 closes the session instead)
+    #[arg(long, env = "RECONNECT_ATTEMPTS", default_value_t = 5)]
+    pub reconnect_attempts: u32,
+    /// How often the clients and the upstream are pinged, in seconds
+    #[arg(long, env = "KEEPALIVE_INTERVAL_SECS", default_value_t = 15)]
+    pub keepalive_interval_secs: u64,
+    /// How long a client or the upstream may stay silent in seconds before it is closed (longer than the interval)
+    #[arg(long, env = "IDLE_TIMEOUT_SECS", default_value_t = 45)]
+    pub idle_timeout_secs: u64,
+    /// How long a client or the upstream may take to say hello after connecting, in seconds
+    #[arg(long, env = "HANDSHAKE_TIMEOUT_SECS", default_value_t = 10)]
+    pub handshake_timeout_secs: u64,
+    /// The delay before the first reconnect attempt in milliseconds, doubled for each further one
+    #[arg(long, env = "RECONNECT_DELAY_MS", default_value_t = 500)]
+    pub reconnect_delay_ms: u64,
+    /// The upper bound of the reconnect delay in milliseconds
+    #[arg(long, env = "RECONNECT_MAX_DELAY_MS", default_value_t = 8000)]
+    pub reconnect_max_delay_ms: u64,
+    /// The length of the analysis windows, in seconds (`2.5s`) or frames (`4096`)
+    #[arg(long, env = "WINDOW", default_value = "4s")]
+    pub window: String,
+    /// How far each window starts after the previous one, in seconds or frames (at most the window)
+    #[arg(long, env = "HOP", default_value = "2s")]
+    pub hop: String,
+    /// The taper applied to each window before the analysis (`rectangular`, `hann` or `hamming`)
+    #[arg(long, env = "WINDOW_FUNCTION", default_value = "rectangular")]
+    pub window_function: String,
+    /// The analyzers run on every window for clients that do not choose (`<name>[,...]` or `off`),
+    /// from `native`, `mock`, `librosa-worker` and `librosa` (python-analysis feature)
+    #[arg(long, env = "ANALYZERS", default_value = DEFAULT_ANALYZERS)]
+    pub analyzers: String,
+    /// Analyses running at once across every room, each on a blocking thread
+    #[arg(long, env = "ANALYSIS_WORKERS", default_value_t = 2)]
+    pub analysis_workers: usize,
+    /// How long after a window is cut its features may arrive in milliseconds, later ones are dropped (0 waits for them)
+    #[arg(long, env = "ANALYSIS_DEADLINE_MS", default_value_t = 2000)]
+    pub analysis_deadline_ms: u64,
+    /// The Python interpreter of the `librosa-worker` analyzer, with numpy, librosa and msgpack
+    #[arg(long, env = "PYTHON", default_value = "python3")]
+    pub python: String,
+    /// The worker script run by the interpreter (the one built into the relay if omitted)
+    #[arg(long, env = "PYTHON_WORKER_SCRIPT")]
+    pub python_worker_script: Option<String>,
+    /// Python worker processes started at most, each one analyzing one window at a time
+    #[arg(long, env = "PYTHON_WORKERS", default_value_t = 1)]
+    pub python_workers: usize,
+    /// How long a Python worker may take for a window in milliseconds before it is killed and restarted
+    #[arg(long, env = "PYTHON_WORKER_TIMEOUT_MS", default_value_t = 5000)]
+    pub python_worker_timeout_ms: u64,
+    /// PCM chunks queued for the windowing of a room
+    #[arg(long, env = "PCM_CAPACITY", default_value_t = 1000)]
+    pub pcm_capacity: usize,
+    /// What a full PCM queue does (`block`, `drop-oldest`, `keep-latest` or `coalesce`)
+    #[arg(long, env = "PCM_POLICY", default_value = "block")]
+    pub pcm_policy: String,
+    /// Windows queued for the analysis of a room
+    #[arg(long, env = "WINDOW_CAPACITY", default_value_t = 1000)]
+    pub window_capacity: usize,
+    /// What a full window queue does, `drop-oldest` or `keep-latest` keep the analysis on time
+    #[arg(long, env = "WINDOW_POLICY", default_value = "block")]
+    pub window_policy: String,
+}
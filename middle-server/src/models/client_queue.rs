@@ -0,0 +1,142 @@
+use axum::extract::ws::Message;
+use std::collections::VecDeque;
+use tokio::sync::{Mutex, Notify};
+
+/// The messages on their way to one client (see client_queue_sender).
+///
+/// Control messages are always queued. Windows are queued up to `window_capacity`,
+/// beyond that the oldest queued window gives way to the new one,
+/// so a slow client skips windows instead of holding up the room it listens to.
+pub struct ClientQueue {
+    state: Mutex<ClientQueueState>,
+    // a single consumer, so the permit of notify_one is never lost
+    notify: Notify,
+    window_capacity: usize,
+}
+
+#[derive(Default)]
+struct ClientQueueState {
+    messages: VecDeque<Message>,
+    windows: usize,
+    dropped_windows: u64,
+}
+
+impl ClientQueue {
+    pub fn new(window_capacity: usize) -> Self {
+        ClientQueue {
+            state: Mutex::new(ClientQueueState::default()),
+            notify: Notify::new(),
+            window_capacity: window_capacity.max(1),
+        }
+    }
+
+    /// Queues a control or close message.
+    pub async fn push(&self, message: Message) {
+        self.state.lock().await.messages.push_back(message);
+        self.notify.notify_one();
+    }
+
+    /// Queues a window, returning the number of windows dropped so far if one was dropped for it.
+    pub async fn push_window(&self, window: Message) -> Option<u64> {
+        let mut state = self.state.lock().await;
+        let mut dropped = None;
+        if state.windows >= self.window_capacity
+            && let Some(oldest) = state
+                .messages
+                .iter()
+                .position(|message| matches!(message, Message::Binary(_)))
+        {
+            state.messages.remove(oldest);
+            state.windows -= 1;
+            state.dropped_windows += 1;
+            dropped = Some(state.dropped_windows);
+        }
+        state.messages.push_back(window);
+        state.windows += 1;
+        drop(state);
+        self.notify.notify_one();
+        dropped
+    }
+
+    /// Waits for the next message.
+    pub async fn pop(&self) -> Message {
+        loop {
+            {
+                let mut state = self.state.lock().await;
+                if let Some(message) = state.messages.pop_front() {
+                    if let Message::Binary(_) = message {
+                        state.windows -= 1;
+                    }
+                    return message;
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    pub async fn dropped_windows(&self) -> u64 {
+        self.state.lock().await.dropped_windows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn text(text: &str) -> Message {
+        Message::Text(text.into())
+    }
+
+    fn window(byte: u8) -> Message {
+        Message::Binary(vec![byte].into())
+    }
+
+    async fn drain(queue: &ClientQueue) -> Vec<Message> {
+        let mut messages = Vec::new();
+        while let Ok(message) = tokio::time::timeout(Duration::ZERO, queue.pop()).await {
+            messages.push(message);
+        }
+        messages
+    }
+
+    #[tokio::test]
+    async fn windows_beyond_the_capacity_drop_the_oldest_one() {
+        let queue = ClientQueue::new(2);
+        assert_eq!(queue.push_window(window(1)).await, None);
+        queue.push(text("audio-info")).await;
+        assert_eq!(queue.push_window(window(2)).await, None);
+        assert_eq!(queue.push_window(window(3)).await, Some(1));
+        assert_eq!(queue.push_window(window(4)).await, Some(2));
+
+        assert_eq!(
+            drain(&queue).await,
+            vec![text("audio-info"), window(3), window(4)]
+        );
+        assert_eq!(queue.dropped_windows().await, 2);
+
+        // the popped windows made room again
+        assert_eq!(queue.push_window(window(5)).await, None);
+        assert_eq!(queue.push_window(window(6)).await, None);
+    }
+
+    #[tokio::test]
+    async fn control_messages_are_never_dropped() {
+        let queue = ClientQueue::new(1);
+        for n in 0..100 {
+            queue.push(text(&n.to_string())).await;
+        }
+        queue.push_window(window(1)).await;
+        queue.push_window(window(2)).await;
+        queue.push(text("end-of-stream")).await;
+
+        let messages = drain(&queue).await;
+        assert_eq!(messages.len(), 102);
+        assert_eq!(
+            messages[..100],
+            (0..100).map(|n| text(&n.to_string())).collect::<Vec<_>>()
+        );
+        assert_eq!(messages[100..], [window(2), text("end-of-stream")]);
+        assert_eq!(queue.dropped_windows().await, 1);
+    }
+}
@@ -0,0 +1,305 @@
+use protocol::models::frame::PcmFrameHeader;
+use std::collections::VecDeque;
+
+/// How a chunk relates to the frames already buffered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Continuity {
+    /// The chunk continues the buffered frames.
+    Continuous,
+    /// A new format, a gap or a jump: the buffer starts over at the chunk.
+    Restarted,
+    /// A chunk of the stream that arrived after later ones, it is dropped.
+    Late,
+}
+
+/// The PCM frames of a stream, keyed on their position instead of the chunks they arrived in.
+///
+/// Windows of exactly `window_frames` frames are cut every `hop_frames` frames,
+/// whatever the size of the chunks of the server.
+pub struct FrameRing {
+    window_frames: u64,
+    hop_frames: u64,
+    sample_rate: u32,
+    /// The header of the last chunk: stream, format and timing of the buffered frames.
+    last: Option<PcmFrameHeader>,
+    /// The position of the first buffered frame.
+    start_frame: u64,
+    bytes: VecDeque<u8>,
+    /// The number of windows cut so far, the sequence number of the next one.
+    windows: u32,
+}
+
+impl FrameRing {
+    pub fn new(window_frames: u64, hop_frames: u64, sample_rate: u32) -> Self {
+        FrameRing {
+            window_frames: window_frames.max(1),
+            hop_frames: hop_frames.clamp(1, window_frames.max(1)),
+            sample_rate: sample_rate.max(1),
+            last: None,
+            start_frame: 0,
+            bytes: VecDeque::new(),
+            windows: 0,
+        }
+    }
+
+    fn bytes_per_frame(header: &PcmFrameHeader) -> usize {
+        header.channels as usize * (header.bits_per_sample as usize).div_ceil(8)
+    }
+
+    /// The position after the last buffered frame.
+    fn end_frame(&self) -> u64 {
+        let bytes_per_frame = self.last.as_ref().map_or(1, Self::bytes_per_frame).max(1);
+        self.start_frame + (self.bytes.len() / bytes_per_frame) as u64
+    }
+
+    /// Buffers the frames of a chunk (`payload` is the PCM after its header).
+    pub fn push(&mut self, header: &PcmFrameHeader, payload: &[u8]) -> Continuity {
+        let continuity = match &self.last {
+            Some(last)
+                if last.stream_id == header.stream_id
+                    && sequence_gap(last.sequence, header.sequence) == SequenceGap::Late =>
+            {
+                return Continuity::Late;
+            }
+            // a new stream continues the old one if it resumes at its last frame (see upstream_supervisor)
+            Some(last)
+                if last.channels == header.channels
+                    && last.bits_per_sample == header.bits_per_sample
+                    && last.pcm_format == header.pcm_format
+                    && header.first_frame == self.end_frame() =>
+            {
+                Continuity::Continuous
+            }
+            _ => Continuity::Restarted,
+        };
+        if continuity == Continuity::Restarted {
+            self.bytes.clear();
+            self.start_frame = header.first_frame;
+        }
+        self.bytes.extend(payload);
+        self.last = Some(header.clone());
+        continuity
+    }
+
+    /// Cuts the next window once enough frames are buffered.
+    /*
+        FORMAT: a single frame (see PcmFrameHeader), so the window is read like a chunk
+            [header | PCM of `window_frames` frames]
+            sequence = the number of the window, first_frame = the position of its first frame
+    */
+    pub fn next_window(&mut self) -> Option<Vec<u8>> {
+        let last = self.last.as_ref()?;
+        let bytes_per_frame = Self::bytes_per_frame(last);
+        let window_bytes = self.window_frames as usize * bytes_per_frame;
+        if bytes_per_frame == 0 || self.bytes.len() < window_bytes {
+            return None;
+        }
+
+        // the timestamp of the first frame, from the timing of the last chunk
+        let offset_us = (self.start_frame as i128 - last.first_frame as i128) * 1_000_000
+            / self.sample_rate as i128;
+        let header = PcmFrameHeader {
+            sequence: self.windows,
+            frames: self.window_frames as u32,
+            first_frame: self.start_frame,
+            timestamp_us: (last.timestamp_us as i128 + offset_us).max(0) as u64,
+            ..last.clone()
+        };
+        let mut window = Vec::with_capacity(PcmFrameHeader::SIZE + window_bytes);
+        window.extend_from_slice(&header.to_bytes());
+        window.extend(self.bytes.range(..window_bytes));
+
+        // slide by the hop, the rest of the window is the start of the next one
+        self.bytes
+            .drain(..self.hop_frames as usize * bytes_per_frame);
+        self.start_frame += self.hop_frames;
+        self.windows = self.windows.wrapping_add(1);
+        Some(window)
+    }
+}
+
+/// Where a chunk falls relative to the next expected sequence number.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SequenceGap {
+    InOrder,
+    /// The chunks between the expected one and this one never arrived.
+    Lost(u32),
+    /// The chunk was sent before the last one received.
+    Late,
+}
+
+/// Compares sequence numbers modulo 2^32 (as in RFC 1982), since the sequence of a long-running station wraps.
+pub fn sequence_gap(expected: u32, sequence: u32) -> SequenceGap {
+    match sequence.wrapping_sub(expected) as i32 {
+        0 => SequenceGap::InOrder,
+        gap if gap > 0 => SequenceGap::Lost(gap as u32),
+        _ => SequenceGap::Late,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::models::audio::PcmFormat;
+
+    const CHANNELS: u16 = 2;
+    const BYTES_PER_FRAME: usize = 4;
+
+    /// A chunk of 16-bit stereo frames, each sample holding the low bits of its frame position.
+    fn chunk(
+        stream_id: u32,
+        sequence: u32,
+        first_frame: u64,
+        frames: u64,
+    ) -> (PcmFrameHeader, Vec<u8>) {
+        let header = PcmFrameHeader {
+            stream_id,
+            sequence,
+            frames: frames as u32,
+            first_frame,
+            timestamp_us: first_frame * 1_000_000 / 8000,
+            channels: CHANNELS,
+            bits_per_sample: 16,
+            pcm_format: PcmFormat::Int,
+        };
+        let payload = (first_frame..first_frame + frames)
+            .flat_map(|frame| {
+                let sample = (frame as u16).to_le_bytes();
+                [sample, sample].concat()
+            })
+            .collect();
+        (header, payload)
+    }
+
+    /// The position of every frame of a window, checking that its channels agree.
+    fn positions(window: &[u8]) -> (PcmFrameHeader, Vec<u16>) {
+        let header = PcmFrameHeader::from_frame(window).unwrap();
+        let positions = window[PcmFrameHeader::SIZE..]
+            .chunks_exact(BYTES_PER_FRAME)
+            .map(|frame| {
+                assert_eq!(frame[..2], frame[2..]);
+                u16::from_le_bytes([frame[0], frame[1]])
+            })
+            .collect();
+        (header, positions)
+    }
+
+    /// Pushes `total` frames in chunks cycling through `sizes` and cuts every window.
+    fn windows(ring: &mut FrameRing, total: u64, sizes: &[u64]) -> Vec<Vec<u8>> {
+        let mut windows = Vec::new();
+        let mut first_frame = 0;
+        for (sequence, size) in sizes.iter().cycle().enumerate() {
+            if first_frame >= total {
+                break;
+            }
+            let size = (*size).min(total - first_frame);
+            let (header, payload) = chunk(1, sequence as u32, first_frame, size);
+            ring.push(&header, &payload);
+            windows.extend(std::iter::from_fn(|| ring.next_window()));
+            first_frame += size;
+        }
+        windows
+    }
+
+    #[test]
+    fn windows_have_the_window_length_and_start_every_hop() {
+        for (window_frames, hop_frames) in [(1000, 250), (1000, 500), (1000, 1000), (7, 3)] {
+            for sizes in [&[1024][..], &[1, 17, 333, 4096], &[999]] {
+                let mut ring = FrameRing::new(window_frames, hop_frames, 8000);
+                let total = 10_000;
+                let windows = windows(&mut ring, total, sizes);
+                // every window that fits in the frames pushed so far
+                assert_eq!(
+                    windows.len() as u64,
+                    (total - window_frames) / hop_frames + 1,
+                    "window {window_frames}, hop {hop_frames}, chunks {sizes:?}"
+                );
+                for (n, window) in windows.iter().enumerate() {
+                    let (header, positions) = positions(window);
+                    let start = n as u64 * hop_frames;
+                    assert_eq!(header.sequence, n as u32);
+                    assert_eq!(header.frames as u64, window_frames);
+                    assert_eq!(header.first_frame, start);
+                    assert_eq!(header.timestamp_us, start * 1_000_000 / 8000);
+                    // the frames of the window are the consecutive frames of the stream
+                    let expected: Vec<u16> = (start..start + window_frames)
+                        .map(|frame| frame as u16)
+                        .collect();
+                    assert_eq!(positions, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_resumed_stream_continues_the_windows() {
+        let mut ring = FrameRing::new(100, 50, 8000);
+        let (header, payload) = chunk(1, 0, 0, 120);
+        assert_eq!(ring.push(&header, &payload), Continuity::Restarted);
+        // a new stream that resumes at the last frame (a reconnect)
+        let (header, payload) = chunk(2, 0, 120, 80);
+        assert_eq!(ring.push(&header, &payload), Continuity::Continuous);
+        let starts: Vec<u64> = std::iter::from_fn(|| ring.next_window())
+            .map(|window| positions(&window).0.first_frame)
+            .collect();
+        assert_eq!(starts, vec![0, 50, 100]);
+    }
+
+    #[test]
+    fn a_gap_restarts_the_windows() {
+        let mut ring = FrameRing::new(100, 100, 8000);
+        let (header, payload) = chunk(1, 0, 0, 150);
+        ring.push(&header, &payload);
+        assert!(ring.next_window().is_some());
+        // a seek: the 50 buffered frames are not mixed with the new position
+        let (header, payload) = chunk(1, 1, 5000, 100);
+        assert_eq!(ring.push(&header, &payload), Continuity::Restarted);
+        let (header, positions) = positions(&ring.next_window().unwrap());
+        assert_eq!(header.first_frame, 5000);
+        assert_eq!(positions[0], 5000);
+        assert!(ring.next_window().is_none());
+    }
+
+    #[test]
+    fn a_late_chunk_is_dropped() {
+        let mut ring = FrameRing::new(100, 100, 8000);
+        let (header, payload) = chunk(1, 1, 100, 60);
+        ring.push(&header, &payload);
+        let (header, payload) = chunk(1, 0, 0, 100);
+        assert_eq!(ring.push(&header, &payload), Continuity::Late);
+        let (header, payload) = chunk(1, 2, 160, 40);
+        assert_eq!(ring.push(&header, &payload), Continuity::Continuous);
+        let (header, _) = positions(&ring.next_window().unwrap());
+        assert_eq!(header.first_frame, 100);
+    }
+
+    #[test]
+    fn the_windows_go_on_across_the_sequence_wrap() {
+        let mut ring = FrameRing::new(100, 100, 8000);
+        let (header, payload) = chunk(1, u32::MAX, 0, 60);
+        assert_eq!(ring.push(&header, &payload), Continuity::Restarted);
+        let (header, payload) = chunk(1, 0, 60, 60);
+        assert_eq!(ring.push(&header, &payload), Continuity::Continuous);
+        assert_eq!(positions(&ring.next_window().unwrap()).0.first_frame, 0);
+        // the chunk before the wrap is still late after it
+        let (header, payload) = chunk(1, u32::MAX, 0, 60);
+        assert_eq!(ring.push(&header, &payload), Continuity::Late);
+        let (header, payload) = chunk(1, 1, 120, 80);
+        assert_eq!(ring.push(&header, &payload), Continuity::Continuous);
+        assert_eq!(positions(&ring.next_window().unwrap()).0.first_frame, 100);
+    }
+
+    #[test]
+    fn sequence_gaps_are_counted_across_the_wrap() {
+        assert_eq!(sequence_gap(7, 7), SequenceGap::InOrder);
+        assert_eq!(sequence_gap(7, 10), SequenceGap::Lost(3));
+        assert_eq!(sequence_gap(7, 5), SequenceGap::Late);
+
+        // u32::MAX is followed by 0
+        assert_eq!(sequence_gap(u32::MAX, u32::MAX), SequenceGap::InOrder);
+        assert_eq!(sequence_gap(u32::MAX, 0), SequenceGap::Lost(1));
+        assert_eq!(sequence_gap(u32::MAX - 1, 1), SequenceGap::Lost(3));
+        assert_eq!(sequence_gap(1, u32::MAX), SequenceGap::Late);
+        assert_eq!(sequence_gap(0, u32::MAX), SequenceGap::Late);
+    }
+}
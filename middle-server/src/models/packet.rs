@@ -0,0 +1,27 @@
+use crate::models::analysis::Features;
+use std::{collections::BTreeMap, time::Instant};
+
+/// A window of exactly the configured number of frames behind a single header (see FrameRing::next_window).
+pub struct WindowPacket {
+    pub bytes: Vec<u8>,
+    /// When the window was cut, the analysis deadline runs from here (see AnalysisPool::run).
+    pub cut_at: Instant,
+}
+
+/// The binary message sent to the client for every window when its room runs analyzers.
+/*
+    FORMAT: MessagePack map
+        {
+            "pcm": <bin: the window>,
+            "bpm": <float64 | nil: the first BPM of the analyzers>,
+            "features": {<analyzer name>: {"bpm": <float64>}, ...}
+        }
+*/
+#[derive(Debug, serde::Serialize)]
+pub struct MessagePack {
+    // bin instead of an array of integers
+    #[serde(with = "serde_bytes")]
+    pub pcm: Vec<u8>,
+    pub bpm: Option<f64>,
+    pub features: BTreeMap<&'static str, Features>,
+}
@@ -0,0 +1,485 @@
+use crate::{errors::analysis::AnalysisError, models::analysis::Features};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{Read, Write},
+    process::{Child, Command, Stdio},
+    sync::{Condvar, Mutex, MutexGuard, mpsc},
+    time::{Duration, Instant},
+};
+
+//* constant values *//
+static WORKER_SCRIPT: &str = include_str!("../../python/analysis_worker.py");
+// importing librosa takes seconds, far more than a window
+static STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+// replies are a few bytes, a longer length means the worker broke the protocol
+static MAX_FRAME_BYTES: usize = 4 * 1024 * 1024;
+
+/// How the Python analysis workers are started (see PythonWorkerPool).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PythonWorkerConfig {
+    /// The Python interpreter.
+    pub python: String,
+    /// The worker script, the one embedded in the relay if `None`.
+    pub script: Option<String>,
+    /// The processes started at most, one window is analyzed by each at a time.
+    pub workers: usize,
+    /// How long a worker may take for a window (or to start) before it is killed and replaced.
+    pub timeout: Duration,
+}
+
+impl PythonWorkerConfig {
+    pub fn new(
+        python: String,
+        script: Option<String>,
+        workers: usize,
+        timeout: Duration,
+    ) -> Result<Self, String> {
+        if workers == 0 {
+            return Err("at least 1 Python worker is needed".into());
+        }
+        if timeout.is_zero() {
+            return Err("the Python worker timeout must be positive".into());
+        }
+        Ok(PythonWorkerConfig {
+            python,
+            script,
+            workers,
+            timeout,
+        })
+    }
+}
+
+/// The state of the Python workers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PythonWorkerHealth {
+    /// The processes running now, idle or analyzing.
+    pub running: usize,
+    pub idle: usize,
+    pub started: u64,
+    /// Workers that exited, broke the pipe protocol or failed to start.
+    pub crashed: u64,
+    /// Workers killed for taking longer than the timeout.
+    pub timed_out: u64,
+    pub last_error: Option<String>,
+}
+
+/// A request to a worker (see python/analysis_worker.py for the protocol).
+#[derive(Serialize)]
+struct WorkerRequest {
+    id: u64,
+    sample_rate: f64,
+    /// The mono samples as float32 little endian.
+    #[serde(with = "serde_bytes")]
+    samples: Vec<u8>,
+}
+
+/// A message of a worker: the ready message once started, then one reply per request.
+#[derive(Debug, Deserialize)]
+struct WorkerReply {
+    id: Option<u64>,
+    #[serde(default)]
+    ready: bool,
+    features: Option<Features>,
+    error: Option<String>,
+}
+
+/// One child Python process, with a thread writing its stdin and one reading its stdout
+/// so that a hung worker never blocks the analysis past the timeout.
+struct PythonWorker {
+    child: Child,
+    requests: mpsc::Sender<Vec<u8>>,
+    replies: mpsc::Receiver<Result<WorkerReply, String>>,
+    next_id: u64,
+}
+
+impl PythonWorker {
+    fn spawn(config: &PythonWorkerConfig) -> Result<Self, String> {
+        let mut command = Command::new(&config.python);
+        match &config.script {
+            Some(script) => command.arg("-u").arg(script),
+            None => command.arg("-u").arg("-c").arg(WORKER_SCRIPT),
+        };
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| format!("cannot start {}: {e}", config.python))?;
+        let (Some(mut stdin), Some(mut stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            let _ = child.kill();
+            return Err("the pipes of the worker are missing".into());
+        };
+
+        // [thread1] relay -> worker, ends with the worker or the Sender
+        let (requests, pending) = mpsc::channel::<Vec<u8>>();
+        std::thread::spawn(move || {
+            for message in pending {
+                if write_frame(&mut stdin, &message).is_err() {
+                    break;
+                }
+            }
+        });
+        // [thread2] worker -> relay, ends with the worker
+        let (replies_sender, replies) = mpsc::channel();
+        std::thread::spawn(move || {
+            loop {
+                let reply = read_frame(&mut stdout).and_then(|frame| {
+                    rmp_serde::from_slice::<WorkerReply>(&frame)
+                        .map_err(|e| format!("invalid message: {e}"))
+                });
+                let failed = reply.is_err();
+                if replies_sender.send(reply).is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        let worker = PythonWorker {
+            child,
+            requests,
+            replies,
+            next_id: 0,
+        };
+        match worker.replies.recv_timeout(STARTUP_TIMEOUT) {
+            Ok(Ok(WorkerReply { ready: true, .. })) => Ok(worker),
+            Ok(Ok(reply)) => Err(worker.kill(format!("unexpected message on start: {reply:?}"))),
+            Ok(Err(e)) => Err(worker.kill(format!("failed to start: {e}"))),
+            Err(_) => Err(worker.kill("not ready in time".into())),
+        }
+    }
+
+    /// Ends the process, returning the reason completed with its exit status.
+    fn kill(mut self, reason: String) -> String {
+        let _ = self.child.kill();
+        match self.child.wait() {
+            Ok(status) => format!("{reason} ({status})"),
+            Err(_) => reason,
+        }
+    }
+
+    fn analyze(&mut self, samples: &[f32], sample_rate: f64, timeout: Duration) -> Reply {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = WorkerRequest {
+            id,
+            sample_rate,
+            samples: samples.iter().flat_map(|s| s.to_le_bytes()).collect(),
+        };
+        let message = match rmp_serde::to_vec_named(&request) {
+            Ok(message) => message,
+            Err(e) => return Reply::Failed(format!("cannot encode the window: {e}")),
+        };
+        if self.requests.send(message).is_err() {
+            return Reply::Crashed("the worker stopped reading".into());
+        }
+        let until = Instant::now() + timeout;
+        loop {
+            match self
+                .replies
+                .recv_timeout(until.saturating_duration_since(Instant::now()))
+            {
+                // a reply to this window
+                Ok(Ok(reply)) if reply.id == Some(id) => {
+                    return match (reply.features, reply.error) {
+                        (_, Some(error)) => Reply::Failed(error),
+                        (Some(features), None) => Reply::Analyzed(features),
+                        (None, None) => Reply::Failed("a reply without features".into()),
+                    };
+                }
+                Ok(Ok(reply)) => tracing::warn!("Python worker: unexpected message {:?}", reply),
+                Ok(Err(e)) => return Reply::Crashed(e),
+                Err(mpsc::RecvTimeoutError::Timeout) => return Reply::TimedOut,
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    return Reply::Crashed("the worker exited".into());
+                }
+            }
+        }
+    }
+}
+
+enum Reply {
+    Analyzed(Features),
+    /// The worker reported an error on the window, it goes on.
+    Failed(String),
+    /// The worker is gone or broke the protocol.
+    Crashed(String),
+    TimedOut,
+}
+
+/*
+    FORMAT: [u32 big endian: length | body]
+*/
+fn read_frame(reader: &mut impl Read) -> Result<Vec<u8>, String> {
+    let mut length = [0u8; 4];
+    reader
+        .read_exact(&mut length)
+        .map_err(|e| format!("the worker closed its output: {e}"))?;
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FRAME_BYTES {
+        return Err(format!("a message of {length} bytes"));
+    }
+    let mut frame = vec![0u8; length];
+    reader
+        .read_exact(&mut frame)
+        .map_err(|e| format!("truncated message: {e}"))?;
+    Ok(frame)
+}
+
+fn write_frame(writer: &mut impl Write, body: &[u8]) -> std::io::Result<()> {
+    let length = u32::try_from(body.len()).map_err(std::io::Error::other)?;
+    writer.write_all(&length.to_be_bytes())?;
+    writer.write_all(body)?;
+    writer.flush()
+}
+
+struct Slots {
+    idle: Vec<PythonWorker>,
+    /// The workers not running, started again when a window needs them.
+    stopped: usize,
+    health: PythonWorkerHealth,
+}
+
+/// The supervised Python processes an analyzer sends its windows to (see python/analysis_worker.py).
+///
+/// A worker that crashes or overruns the timeout is killed and replaced by a new process on the next window,
+/// so librosa failing never takes the relay down.
+pub struct PythonWorkerPool {
+    config: PythonWorkerConfig,
+    slots: Mutex<Slots>,
+    released: Condvar,
+}
+
+impl PythonWorkerPool {
+    pub fn new(config: PythonWorkerConfig) -> Self {
+        PythonWorkerPool {
+            slots: Mutex::new(Slots {
+                idle: Vec::new(),
+                stopped: config.workers,
+                health: PythonWorkerHealth::default(),
+            }),
+            released: Condvar::new(),
+            config,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Slots> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// An idle worker, a new one if a slot is free, or the first one released within the timeout.
+    fn acquire(&self) -> Result<PythonWorker, AnalysisError> {
+        let until = Instant::now() + self.config.timeout;
+        let mut slots = self.lock();
+        loop {
+            if let Some(worker) = slots.idle.pop() {
+                slots.health.idle = slots.idle.len();
+                return Ok(worker);
+            }
+            if slots.stopped > 0 {
+                slots.stopped -= 1;
+                drop(slots);
+                return self.start();
+            }
+            let timeout = until.saturating_duration_since(Instant::now());
+            if timeout.is_zero() {
+                return Err(AnalysisError::PythonWorkerError(
+                    "every worker is busy".into(),
+                ));
+            }
+            slots = self
+                .released
+                .wait_timeout(slots, timeout)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    fn start(&self) -> Result<PythonWorker, AnalysisError> {
+        let spawned = PythonWorker::spawn(&self.config);
+        let mut slots = self.lock();
+        match spawned {
+            Ok(worker) => {
+                slots.health.running += 1;
+                slots.health.started += 1;
+                tracing::info!("Python worker started: {:?}", slots.health);
+                Ok(worker)
+            }
+            Err(e) => {
+                slots.stopped += 1;
+                slots.health.crashed += 1;
+                slots.health.last_error = Some(e.clone());
+                tracing::error!("Python worker failed to start: {} {:?}", e, slots.health);
+                self.released.notify_one();
+                Err(AnalysisError::PythonWorkerError(e))
+            }
+        }
+    }
+
+    fn release(&self, worker: PythonWorker) {
+        let mut slots = self.lock();
+        slots.idle.push(worker);
+        slots.health.idle = slots.idle.len();
+        self.released.notify_one();
+    }
+
+    /// Kills a worker, its slot starts a new one on the next window.
+    fn replace(&self, worker: PythonWorker, reason: String, timed_out: bool) -> AnalysisError {
+        let reason = worker.kill(reason);
+        let mut slots = self.lock();
+        slots.stopped += 1;
+        slots.health.running -= 1;
+        if timed_out {
+            slots.health.timed_out += 1;
+        } else {
+            slots.health.crashed += 1;
+        }
+        slots.health.last_error = Some(reason.clone());
+        tracing::warn!("Python worker replaced: {} {:?}", reason, slots.health);
+        self.released.notify_one();
+        AnalysisError::PythonWorkerError(reason)
+    }
+
+    /// Analyzes the mono `samples` on a worker, blocking until it replies or the timeout passes.
+    pub fn analyze(&self, samples: &[f32], sample_rate: f64) -> Result<Features, AnalysisError> {
+        let mut worker = self.acquire()?;
+        match worker.analyze(samples, sample_rate, self.config.timeout) {
+            Reply::Analyzed(features) => {
+                self.release(worker);
+                Ok(features)
+            }
+            Reply::Failed(e) => {
+                self.release(worker);
+                Err(AnalysisError::PythonWorkerError(e))
+            }
+            Reply::Crashed(e) => Err(self.replace(worker, e, false)),
+            Reply::TimedOut => Err(self.replace(
+                worker,
+                format!("no reply within {:?}", self.config.timeout),
+                true,
+            )),
+        }
+    }
+
+    pub fn health(&self) -> PythonWorkerHealth {
+        self.lock().health.clone()
+    }
+}
+
+impl Drop for PythonWorkerPool {
+    fn drop(&mut self) {
+        for worker in self.lock().idle.drain(..) {
+            worker.kill(String::new());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_round_trip_and_truncation_is_an_error() {
+        let mut pipe = Vec::new();
+        write_frame(&mut pipe, b"first").unwrap();
+        write_frame(&mut pipe, b"").unwrap();
+        assert_eq!(&pipe[..4], &5u32.to_be_bytes());
+
+        let mut reader = pipe.as_slice();
+        assert_eq!(read_frame(&mut reader).unwrap(), b"first");
+        assert_eq!(read_frame(&mut reader).unwrap(), b"");
+        assert!(read_frame(&mut reader).is_err());
+        assert!(read_frame(&mut &pipe[..7]).is_err());
+
+        let oversized = u32::MAX.to_be_bytes();
+        assert_eq!(
+            read_frame(&mut oversized.as_slice()),
+            Err(format!("a message of {} bytes", u32::MAX))
+        );
+    }
+
+    #[test]
+    fn replies_decode_with_or_without_features() {
+        let ready = rmp_serde::to_vec_named(&serde_json::json!({"ready": true})).unwrap();
+        let reply: WorkerReply = rmp_serde::from_slice(&ready).unwrap();
+        assert!(reply.ready && reply.id.is_none());
+
+        let analyzed =
+            rmp_serde::to_vec_named(&serde_json::json!({"id": 3, "features": {"bpm": 128.0}}))
+                .unwrap();
+        let reply: WorkerReply = rmp_serde::from_slice(&analyzed).unwrap();
+        assert_eq!(reply.id, Some(3));
+        assert_eq!(reply.features, Some(Features { bpm: Some(128.0) }));
+
+        assert!(
+            PythonWorkerConfig::new("python3".into(), None, 0, Duration::from_secs(1)).is_err()
+        );
+        assert!(PythonWorkerConfig::new("python3".into(), None, 1, Duration::ZERO).is_err());
+    }
+
+    /// A pool of one worker running a stub that says it is ready, then does `behavior`.
+    fn stub_pool(name: &str, behavior: &str) -> PythonWorkerPool {
+        let script = std::env::temp_dir().join(format!("{name}-{}.py", std::process::id()));
+        let ready = r#"sys.stdout.buffer.write(b"\x00\x00\x00\x08\x81\xa5ready\xc3")"#;
+        std::fs::write(
+            &script,
+            format!("import sys\n{ready}\nsys.stdout.buffer.flush()\n{behavior}\n"),
+        )
+        .unwrap();
+        let config = PythonWorkerConfig::new(
+            "python3".into(),
+            Some(script.display().to_string()),
+            1,
+            Duration::from_millis(300),
+        )
+        .unwrap();
+        PythonWorkerPool::new(config)
+    }
+
+    #[test]
+    fn workers_that_never_reply_are_killed_and_replaced() {
+        let pool = stub_pool("hung-worker", "sys.stdin.buffer.read()");
+        for windows in 1..=2 {
+            assert!(pool.analyze(&[0.0; 64], 8000.0).is_err());
+            let health = pool.health();
+            assert_eq!((health.started, health.timed_out), (windows, windows));
+            assert_eq!((health.running, health.idle, health.crashed), (0, 0, 0));
+            assert_eq!(
+                health.last_error.unwrap().split(" (").next(),
+                Some("no reply within 300ms")
+            );
+        }
+    }
+
+    #[test]
+    fn crashed_workers_are_replaced() {
+        let pool = stub_pool("crashing-worker", "sys.stdin.buffer.read(4)\nsys.exit(3)");
+        for windows in 1..=2 {
+            assert!(pool.analyze(&[0.0; 64], 8000.0).is_err());
+            let health = pool.health();
+            assert_eq!((health.started, health.crashed), (windows, windows));
+            assert_eq!((health.running, health.timed_out), (0, 0));
+        }
+
+        // an oversized reply is not allocated, the worker is treated as crashed
+        let pool = stub_pool(
+            "oversized-worker",
+            r#"
+sys.stdin.buffer.read(4)
+sys.stdout.buffer.write(b"\xff\xff\xff\xff")
+sys.stdout.buffer.flush()
+sys.stdin.buffer.read()"#,
+        );
+        assert!(pool.analyze(&[0.0; 64], 8000.0).is_err());
+        let health = pool.health();
+        assert_eq!(
+            (health.started, health.crashed, health.timed_out),
+            (1, 1, 0)
+        );
+        assert!(
+            health
+                .last_error
+                .unwrap()
+                .starts_with("a message of 4294967295 bytes")
+        );
+    }
+}
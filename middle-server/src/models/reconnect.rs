@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+/// How the relay replaces a lost or unreachable upstream connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectConfig {
+    /// Attempts after the first connection failed or was lost (0 disables reconnecting).
+    pub max_attempts: u32,
+    /// The delay before the first attempt, doubled for each further attempt.
+    pub initial_delay: Duration,
+    /// The upper bound of the delay.
+    pub max_delay: Duration,
+}
+
+impl ReconnectConfig {
+    pub fn new(
+        max_attempts: u32,
+        initial_delay: Duration,
+        max_delay: Duration,
+    ) -> Result<Self, String> {
+        if initial_delay.is_zero() || max_delay < initial_delay {
+            return Err(format!(
+                "the reconnect delays must satisfy 0 < {initial_delay:?} <= {max_delay:?}"
+            ));
+        }
+        Ok(ReconnectConfig {
+            max_attempts,
+            initial_delay,
+            max_delay,
+        })
+    }
+
+    /// The exponential backoff before `attempt` (1-based).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_backoff_doubles_up_to_the_maximum_delay() {
+        let config =
+            ReconnectConfig::new(5, Duration::from_millis(500), Duration::from_secs(10)).unwrap();
+        let delays: Vec<Duration> = (1..=7).map(|attempt| config.delay(attempt)).collect();
+        assert_eq!(
+            delays,
+            [500, 1000, 2000, 4000, 8000, 10000, 10000].map(Duration::from_millis)
+        );
+        // 2^(attempt - 1) and the product saturate instead of overflowing
+        assert_eq!(config.delay(33), Duration::from_secs(10));
+        assert_eq!(config.delay(u32::MAX), Duration::from_secs(10));
+        assert_eq!(config.delay(0), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn reconnect_delays_are_validated() {
+        assert!(ReconnectConfig::new(0, Duration::ZERO, Duration::from_secs(1)).is_err());
+        assert!(ReconnectConfig::new(3, Duration::from_secs(2), Duration::from_secs(1)).is_err());
+        assert!(ReconnectConfig::new(0, Duration::from_secs(1), Duration::from_secs(1)).is_ok());
+    }
+}
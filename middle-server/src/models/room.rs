@@ -0,0 +1,288 @@
+use crate::models::{
+    analysis::Analyzers,
+    analysis_pool::AnalysisPool,
+    audio::RwLockAudioInfo,
+    client_queue::ClientQueue,
+    reconnect::ReconnectConfig,
+    session::{MutexSessionState, SessionState},
+    stage::StageConfig,
+    upstream::Upstream,
+    window::WindowConfig,
+};
+use axum::extract::ws::Message;
+use protocol::models::{
+    format::FormatRequest, keepalive::KeepaliveConfig, protocol::ControlMessage,
+    ws::MutexWebSocketServerWriter,
+};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::{Mutex, Notify, OnceCell, RwLock};
+
+/// Clients share a room when they open the same track in the same format on the same upstream
+/// and ask for the same analyzers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RoomKey {
+    pub upstream: String,
+    pub track_id: Option<String>,
+    pub format: FormatRequest,
+    pub analyzers: Vec<&'static str>,
+}
+
+impl RoomKey {
+    /// The room of an `open` (its start position only applies to a new room).
+    pub fn new(upstream: &Upstream, open: &ControlMessage, analyzers: &Analyzers) -> Option<Self> {
+        match open {
+            ControlMessage::Open {
+                track_id, format, ..
+            } => Some(RoomKey {
+                upstream: upstream.name.clone(),
+                track_id: track_id.clone(),
+                format: format.clone(),
+                analyzers: analyzers.iter().map(|a| a.name()).collect(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// How every room connects to its server, cuts its windows and analyzes them.
+#[derive(Clone)]
+pub struct RoomConfig {
+    pub keepalive: KeepaliveConfig,
+    pub reconnect: ReconnectConfig,
+    pub window: WindowConfig,
+    pub pcm_stage: StageConfig,
+    pub window_stage: StageConfig,
+    /// Shared by every room.
+    pub analysis_pool: Arc<AnalysisPool>,
+}
+
+/// A client of a room.
+pub struct Subscriber {
+    pub queue: Arc<ClientQueue>,
+    /// Windows are only sent after the client accepted the stream.
+    pub accepted: bool,
+}
+
+#[derive(Default)]
+pub struct Subscribers {
+    next_id: u64,
+    clients: HashMap<u64, Subscriber>,
+    /// The last audio-info of the server, sent to clients that join later.
+    audio_info: Option<Message>,
+    /// The stream of the room is over, new clients open a new room.
+    ended: bool,
+}
+
+/// One upstream connection with its windowing and analysis, relayed to every client that opened its stream.
+pub struct Room {
+    pub key: RoomKey,
+    pub upstream: Upstream,
+    /// Run on every window, once for all clients.
+    pub analyzers: Analyzers,
+    /// The session replayed on every server connection of the room (the first one included).
+    pub session: MutexSessionState,
+    pub audio_info: RwLockAudioInfo,
+    /// Set once the first server connection is established, swapped on reconnects.
+    pub server_writer: OnceCell<MutexWebSocketServerWriter>,
+    pub subscribers: Mutex<Subscribers>,
+    /// Notified when the last client left.
+    pub abandoned: Notify,
+}
+
+impl Room {
+    /// A room that streams the `open` of its first client.
+    pub fn new(
+        key: RoomKey,
+        upstream: Upstream,
+        analyzers: Analyzers,
+        open: &ControlMessage,
+    ) -> Self {
+        let mut session = SessionState::default();
+        session.record(open);
+        Room {
+            key,
+            upstream,
+            analyzers,
+            session: Arc::new(Mutex::new(session)),
+            audio_info: Arc::new(RwLock::new(None)),
+            server_writer: OnceCell::new(),
+            subscribers: Mutex::new(Subscribers::default()),
+            abandoned: Notify::new(),
+        }
+    }
+
+    /// Adds a client, which gets the audio-info at once if the server already sent it.
+    ///
+    /// Returns `None` if the stream of the room is over.
+    pub async fn join(&self, queue: Arc<ClientQueue>) -> Option<u64> {
+        let mut subscribers = self.subscribers.lock().await;
+        if subscribers.ended {
+            return None;
+        }
+        if let Some(audio_info) = &subscribers.audio_info {
+            queue.push(audio_info.clone()).await;
+        }
+        let id = subscribers.next_id;
+        subscribers.next_id += 1;
+        subscribers.clients.insert(
+            id,
+            Subscriber {
+                queue,
+                accepted: false,
+            },
+        );
+        Some(id)
+    }
+
+    /// Removes a client, returning whether it was the last one.
+    pub async fn leave(&self, id: u64) -> bool {
+        let mut subscribers = self.subscribers.lock().await;
+        subscribers.clients.remove(&id);
+        subscribers.clients.is_empty()
+    }
+
+    /// Whether the client may pause, resume, seek and loop the shared stream:
+    /// the one that opened the room, or the longest connected client once it left.
+    pub async fn controls(&self, id: u64) -> bool {
+        let subscribers = self.subscribers.lock().await;
+        subscribers.clients.keys().min() == Some(&id)
+    }
+
+    pub async fn accept(&self, id: u64) {
+        if let Some(subscriber) = self.subscribers.lock().await.clients.get_mut(&id) {
+            subscriber.accepted = true;
+        }
+    }
+
+    /// Sends a control message to every client.
+    pub async fn broadcast(&self, message: &ControlMessage) {
+        let text = Message::Text(message.to_json().into());
+        let mut subscribers = self.subscribers.lock().await;
+        if let ControlMessage::AudioInfo { .. } = message {
+            subscribers.audio_info = Some(text.clone());
+        }
+        for subscriber in subscribers.clients.values() {
+            subscriber.queue.push(text.clone()).await;
+        }
+    }
+
+    /// Sends a window to every client that accepted the stream.
+    pub async fn broadcast_window(&self, window: Vec<u8>) {
+        let window = Message::Binary(window.into());
+        let subscribers = self.subscribers.lock().await;
+        for (id, subscriber) in subscribers.clients.iter().filter(|(_, s)| s.accepted) {
+            if let Some(dropped) = subscriber.queue.push_window(window.clone()).await {
+                tracing::warn!(
+                    "Client {} of room {:?} is too slow, a window was dropped ({} dropped in total)",
+                    id,
+                    self.key,
+                    dropped
+                );
+            }
+        }
+    }
+
+    /// Sends the last messages of the stream to every client and ends the room.
+    pub async fn end(&self, messages: &[Message]) {
+        let mut subscribers = self.subscribers.lock().await;
+        subscribers.ended = true;
+        for subscriber in subscribers.clients.values() {
+            for message in messages {
+                subscriber.queue.push(message.clone()).await;
+            }
+        }
+    }
+}
+
+/// The rooms with at least one client, by the stream they relay.
+pub type MutexRoomRegistry = Arc<Mutex<HashMap<RoomKey, Arc<Room>>>>;
+
+/// The room a client listens to.
+pub struct Subscription {
+    pub room: Arc<Room>,
+    pub id: u64,
+}
+
+// held by task1 of a relay session and released when the session ends
+pub type MutexSubscription = Arc<Mutex<Option<Subscription>>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::models::audio::{AudioInfo, PcmFormat};
+    use std::time::Duration;
+
+    fn room() -> Room {
+        let upstream = Upstream {
+            name: "main".into(),
+            url: "ws://localhost:5001".into(),
+        };
+        let open = ControlMessage::Open {
+            track_id: Some("sample3".into()),
+            format: FormatRequest::default(),
+            start: None,
+        };
+        let key = RoomKey::new(&upstream, &open, &Vec::new()).unwrap();
+        Room::new(key, upstream, Vec::new(), &open)
+    }
+
+    async fn drain(queue: &ClientQueue) -> Vec<Message> {
+        let mut messages = Vec::new();
+        while let Ok(message) = tokio::time::timeout(Duration::ZERO, queue.pop()).await {
+            messages.push(message);
+        }
+        messages
+    }
+
+    #[tokio::test]
+    async fn clients_joining_late_get_the_audio_info() {
+        let room = room();
+        let first = Arc::new(ClientQueue::new(4));
+        room.join(Arc::clone(&first)).await.unwrap();
+
+        let audio_info = ControlMessage::AudioInfo {
+            info: AudioInfo {
+                channels: 2,
+                sample_rate: 44100,
+                bits_per_sample: 16,
+                pcm_format: PcmFormat::Int,
+            },
+            seekable: true,
+        };
+        room.broadcast(&audio_info).await;
+        let late = Arc::new(ClientQueue::new(4));
+        room.join(Arc::clone(&late)).await.unwrap();
+
+        let expected = vec![Message::Text(audio_info.to_json().into())];
+        assert_eq!(drain(&first).await, expected);
+        assert_eq!(drain(&late).await, expected);
+    }
+
+    #[tokio::test]
+    async fn clients_cannot_join_an_ended_room() {
+        let room = room();
+        let queue = Arc::new(ClientQueue::new(4));
+        let id = room.join(Arc::clone(&queue)).await.unwrap();
+
+        let end = Message::Text("end-of-stream".into());
+        room.end(std::slice::from_ref(&end)).await;
+        assert_eq!(drain(&queue).await, vec![end]);
+        assert_eq!(room.join(Arc::new(ClientQueue::new(4))).await, None);
+        assert!(room.leave(id).await);
+    }
+
+    #[tokio::test]
+    async fn the_stream_is_controlled_by_the_longest_connected_client() {
+        let room = room();
+        let opener = room.join(Arc::new(ClientQueue::new(4))).await.unwrap();
+        let second = room.join(Arc::new(ClientQueue::new(4))).await.unwrap();
+        let third = room.join(Arc::new(ClientQueue::new(4))).await.unwrap();
+        assert!(room.controls(opener).await);
+        assert!(!room.controls(second).await);
+
+        assert!(!room.leave(opener).await);
+        assert!(room.controls(second).await);
+        assert!(!room.controls(third).await);
+        assert!(!room.controls(opener).await);
+    }
+}
@@ -0,0 +1,161 @@
+use protocol::models::{
+    protocol::ControlMessage,
+    transport::{StreamPosition, TransportCommand},
+};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// What the client has asked of the server so far, replayed on a new upstream connection.
+#[derive(Debug, Clone, Default)]
+pub struct SessionState {
+    /// The last `open` of the client.
+    pub open: Option<ControlMessage>,
+    /// Whether the client has received the audio info of that `open`.
+    pub audio_info_sent: bool,
+    /// Whether the opened stream was accepted (and has not ended since).
+    pub accepted: bool,
+    pub paused: bool,
+    /// The last `loop` command, unless it was turned off.
+    pub loop_command: Option<TransportCommand>,
+    /// Whether the server can start the stream at `next_frame`.
+    pub seekable: bool,
+    /// The frame after the last frame received from the server.
+    pub next_frame: Option<u64>,
+}
+
+impl SessionState {
+    /// Records a message of the client before it is forwarded to the server.
+    pub fn record(&mut self, message: &ControlMessage) {
+        match message {
+            ControlMessage::Open { .. } => {
+                *self = SessionState {
+                    open: Some(message.clone()),
+                    ..Default::default()
+                };
+            }
+            ControlMessage::Accept => self.accepted = true,
+            ControlMessage::Transport(command) => match command {
+                TransportCommand::Pause => self.paused = true,
+                TransportCommand::Resume => self.paused = false,
+                TransportCommand::Loop { .. } => self.loop_command = Some(*command),
+                TransportCommand::LoopOff => self.loop_command = None,
+                TransportCommand::Stop => self.accepted = false,
+                TransportCommand::Seek { .. } => {}
+            },
+            _ => {}
+        }
+    }
+
+    /// The frame a new server continues the stream at, if it can seek there.
+    pub fn resume_frame(&self) -> Option<u64> {
+        if self.accepted && self.seekable {
+            self.next_frame
+        } else {
+            None
+        }
+    }
+
+    /// The `open` that continues the stream where it was lost.
+    pub fn resume_open(&self) -> Option<ControlMessage> {
+        match self.open.clone()? {
+            ControlMessage::Open {
+                track_id,
+                format,
+                start,
+            } => Some(ControlMessage::Open {
+                track_id,
+                format,
+                start: self.resume_frame().map(StreamPosition::Frame).or(start),
+            }),
+            message => Some(message),
+        }
+    }
+}
+
+pub type MutexSessionState = Arc<Mutex<SessionState>>;
+
+/// How the server leg of a session ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpstreamEnd {
+    /// The server or the client closed the session.
+    Closed,
+    /// The server connection was dropped or timed out, it may be replaced.
+    Lost(&'static str),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(start: Option<StreamPosition>) -> ControlMessage {
+        ControlMessage::Open {
+            track_id: Some("sample3".into()),
+            format: Default::default(),
+            start,
+        }
+    }
+
+    fn resume_start(state: &SessionState) -> Option<StreamPosition> {
+        match state.resume_open() {
+            Some(ControlMessage::Open { start, .. }) => start,
+            message => panic!("expected an open, got {message:?}"),
+        }
+    }
+
+    #[test]
+    fn streams_resume_at_the_next_frame_only_when_accepted_and_seekable() {
+        let start = Some(StreamPosition::Millisecond(1500));
+        let mut state = SessionState::default();
+        assert_eq!(state.resume_open(), None);
+
+        state.record(&open(start));
+        state.next_frame = Some(48000);
+        state.seekable = true;
+        // not accepted yet
+        assert_eq!(state.resume_frame(), None);
+        assert_eq!(resume_start(&state), start);
+
+        state.record(&ControlMessage::Accept);
+        assert_eq!(state.resume_frame(), Some(48000));
+        assert_eq!(resume_start(&state), Some(StreamPosition::Frame(48000)));
+
+        // a live input cannot seek
+        state.seekable = false;
+        assert_eq!(resume_start(&state), start);
+
+        state.seekable = true;
+        state.record(&ControlMessage::Transport(TransportCommand::Stop));
+        assert_eq!(resume_start(&state), start);
+    }
+
+    #[test]
+    fn an_open_resets_the_recorded_session() {
+        let loop_command = TransportCommand::Loop {
+            start: StreamPosition::Frame(0),
+            end: StreamPosition::Frame(1000),
+        };
+        let mut state = SessionState::default();
+        state.record(&open(None));
+        state.record(&ControlMessage::Accept);
+        state.record(&ControlMessage::Transport(TransportCommand::Pause));
+        state.record(&ControlMessage::Transport(loop_command));
+        state.audio_info_sent = true;
+        state.seekable = true;
+        state.next_frame = Some(500);
+        assert!(state.paused);
+        assert_eq!(state.loop_command, Some(loop_command));
+
+        state.record(&ControlMessage::Transport(TransportCommand::Resume));
+        state.record(&ControlMessage::Transport(TransportCommand::LoopOff));
+        assert!(!state.paused);
+        assert_eq!(state.loop_command, None);
+
+        state.record(&ControlMessage::Transport(TransportCommand::Pause));
+        state.record(&ControlMessage::Transport(loop_command));
+        state.record(&open(Some(StreamPosition::Frame(10))));
+        assert_eq!(state.open, Some(open(Some(StreamPosition::Frame(10)))));
+        assert!(!state.paused && !state.accepted && !state.seekable && !state.audio_info_sent);
+        assert_eq!(state.loop_command, None);
+        assert_eq!(state.next_frame, None);
+    }
+}
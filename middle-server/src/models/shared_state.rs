@@ -1 +1,21 @@
-pub type RwLockSharedState = std::sync::Arc<tokio::sync::RwLock<i32>>;
+use crate::models::{
+    analysis::AnalysisConfig, analysis_pool::AnalysisPool, reconnect::ReconnectConfig,
+    room::MutexRoomRegistry, stage::StageConfig, upstream::UpstreamConfig, window::WindowConfig,
+};
+use protocol::models::keepalive::KeepaliveConfig;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+pub struct SharedState {
+    pub keepalive: KeepaliveConfig,
+    pub upstream: Arc<UpstreamConfig>,
+    pub reconnect: ReconnectConfig,
+    pub window: WindowConfig,
+    pub analysis: Arc<AnalysisConfig>,
+    pub analysis_pool: Arc<AnalysisPool>,
+    pub pcm_stage: StageConfig,
+    pub window_stage: StageConfig,
+    pub rooms: MutexRoomRegistry,
+}
+
+pub type RwLockSharedState = Arc<RwLock<SharedState>>;
@@ -0,0 +1,274 @@
+use crate::models::packet::WindowPacket;
+use std::collections::VecDeque;
+use tokio::sync::{Mutex, Notify};
+
+/// What a stage does with a new item when its consumer is behind and the queue is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StagePolicy {
+    /// The producer waits for room in the queue (the latency grows up to the capacity).
+    #[default]
+    Block,
+    /// The oldest queued item gives way to the new one.
+    DropOldest,
+    /// Only the newest item is kept, whatever the capacity.
+    KeepLatest,
+    /// The new item is merged into the newest queued one (see Coalesce).
+    Coalesce,
+}
+
+impl StagePolicy {
+    /*
+        FORMAT: block | drop-oldest | keep-latest | coalesce
+    */
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text.trim() {
+            "block" => Ok(StagePolicy::Block),
+            "drop-oldest" => Ok(StagePolicy::DropOldest),
+            "keep-latest" => Ok(StagePolicy::KeepLatest),
+            "coalesce" => Ok(StagePolicy::Coalesce),
+            text => Err(format!("unknown stage policy: {text}")),
+        }
+    }
+}
+
+/// The queue between two stages of a room: how many items it holds and what happens beyond that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageConfig {
+    pub capacity: usize,
+    pub policy: StagePolicy,
+}
+
+impl StageConfig {
+    pub fn new(capacity: usize, policy: &str) -> Result<Self, String> {
+        if capacity == 0 {
+            return Err("the capacity of a stage must be at least 1".into());
+        }
+        Ok(StageConfig {
+            capacity,
+            policy: StagePolicy::parse(policy)?,
+        })
+    }
+}
+
+/// Items that can be merged when a stage coalesces.
+pub trait Coalesce {
+    fn coalesce(&mut self, newer: Self);
+}
+
+/// PCM chunks are concatenated, no frame is lost (see frame_splitter).
+impl Coalesce for Vec<u8> {
+    fn coalesce(&mut self, newer: Self) {
+        self.extend(newer);
+    }
+}
+
+/// A window cannot grow beyond its length, the newer one supersedes the queued one.
+impl Coalesce for WindowPacket {
+    fn coalesce(&mut self, newer: Self) {
+        *self = newer;
+    }
+}
+
+/// What happened to the items pushed into a stage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StageCounters {
+    pub pushed: u64,
+    pub dropped: u64,
+    pub coalesced: u64,
+}
+
+/// A queue between two tasks of a room (PCM -> windowing, windowing -> analysis) with a StagePolicy.
+pub struct Stage<T> {
+    name: String,
+    config: StageConfig,
+    state: Mutex<StageState<T>>,
+    // a single consumer, so the permit of notify_one is never lost
+    items: Notify,
+    // wakes a blocked producer
+    space: Notify,
+}
+
+struct StageState<T> {
+    items: VecDeque<T>,
+    counters: StageCounters,
+}
+
+impl<T: Coalesce> Stage<T> {
+    pub fn new(name: String, config: StageConfig) -> Self {
+        Stage {
+            name,
+            config,
+            state: Mutex::new(StageState {
+                items: VecDeque::new(),
+                counters: StageCounters::default(),
+            }),
+            items: Notify::new(),
+            space: Notify::new(),
+        }
+    }
+
+    /// Queues an item, applying the policy of the stage when the queue is full.
+    pub async fn push(&self, item: T) {
+        let mut state = loop {
+            let state = self.state.lock().await;
+            if self.config.policy != StagePolicy::Block || state.items.len() < self.config.capacity
+            {
+                break state;
+            }
+            drop(state);
+            self.space.notified().await;
+        };
+        state.counters.pushed += 1;
+
+        let lost = match self.config.policy {
+            StagePolicy::Block => 0,
+            StagePolicy::KeepLatest => {
+                let lost = state.items.len() as u64;
+                state.items.clear();
+                state.counters.dropped += lost;
+                lost
+            }
+            _ if state.items.len() < self.config.capacity => 0,
+            StagePolicy::DropOldest => {
+                state.items.pop_front();
+                state.counters.dropped += 1;
+                1
+            }
+            StagePolicy::Coalesce => {
+                if let Some(newest) = state.items.back_mut() {
+                    newest.coalesce(item);
+                    state.counters.coalesced += 1;
+                    let coalesced = state.counters.coalesced;
+                    drop(state);
+                    tracing::debug!(
+                        "The {} stage is behind, an item was coalesced ({} coalesced in total)",
+                        self.name,
+                        coalesced
+                    );
+                    self.items.notify_one();
+                    return;
+                }
+                0
+            }
+        };
+        state.items.push_back(item);
+        let dropped = state.counters.dropped;
+        drop(state);
+        if lost > 0 {
+            tracing::warn!(
+                "The {} stage is behind, {} item(s) dropped ({} dropped in total)",
+                self.name,
+                lost,
+                dropped
+            );
+        }
+        self.items.notify_one();
+    }
+
+    /// Waits for the next item.
+    pub async fn pop(&self) -> T {
+        loop {
+            if let Some(item) = self.state.lock().await.items.pop_front() {
+                self.space.notify_one();
+                return item;
+            }
+            self.items.notified().await;
+        }
+    }
+
+    pub async fn counters(&self) -> StageCounters {
+        self.state.lock().await.counters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::Arc, time::Duration};
+
+    async fn filled(policy: StagePolicy, items: &[&[u8]]) -> (Vec<Vec<u8>>, StageCounters) {
+        let config = StageConfig {
+            capacity: 2,
+            policy,
+        };
+        let stage = Stage::new("test".into(), config);
+        for item in items {
+            stage.push(item.to_vec()).await;
+        }
+        let counters = stage.counters().await;
+        let mut left = Vec::new();
+        while let Ok(item) = tokio::time::timeout(Duration::ZERO, stage.pop()).await {
+            left.push(item);
+        }
+        (left, counters)
+    }
+
+    #[tokio::test]
+    async fn full_stages_apply_their_policy() {
+        let (left, counters) = filled(StagePolicy::DropOldest, &[b"a", b"b", b"c", b"d"]).await;
+        assert_eq!(left, vec![b"c".to_vec(), b"d".to_vec()]);
+        assert_eq!(
+            (counters.pushed, counters.dropped, counters.coalesced),
+            (4, 2, 0)
+        );
+
+        let (left, counters) = filled(StagePolicy::KeepLatest, &[b"a", b"b", b"c", b"d"]).await;
+        assert_eq!(left, vec![b"d".to_vec()]);
+        assert_eq!(
+            (counters.pushed, counters.dropped, counters.coalesced),
+            (4, 3, 0)
+        );
+
+        // PCM chunks are merged into the newest queued one, nothing is lost
+        let (left, counters) = filled(StagePolicy::Coalesce, &[b"a", b"b", b"c", b"d"]).await;
+        assert_eq!(left, vec![b"a".to_vec(), b"bcd".to_vec()]);
+        assert_eq!(
+            (counters.pushed, counters.dropped, counters.coalesced),
+            (4, 0, 2)
+        );
+
+        let (left, counters) = filled(StagePolicy::Block, &[b"a", b"b"]).await;
+        assert_eq!(left, vec![b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!(
+            (counters.pushed, counters.dropped, counters.coalesced),
+            (2, 0, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn a_blocked_producer_is_released_by_pop() {
+        let config = StageConfig {
+            capacity: 2,
+            policy: StagePolicy::Block,
+        };
+        let stage = Arc::new(Stage::<Vec<u8>>::new("test".into(), config));
+        stage.push(b"a".to_vec()).await;
+        stage.push(b"b".to_vec()).await;
+
+        let producer = tokio::spawn({
+            let stage = Arc::clone(&stage);
+            async move { stage.push(b"c".to_vec()).await }
+        });
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        assert!(!producer.is_finished());
+        assert_eq!(stage.counters().await.pushed, 2);
+
+        assert_eq!(stage.pop().await, b"a".to_vec());
+        tokio::time::timeout(Duration::from_secs(1), producer)
+            .await
+            .expect("the producer is still blocked")
+            .unwrap();
+        assert_eq!(stage.pop().await, b"b".to_vec());
+        assert_eq!(stage.pop().await, b"c".to_vec());
+        assert_eq!(
+            stage.counters().await,
+            StageCounters {
+                pushed: 3,
+                dropped: 0,
+                coalesced: 0
+            }
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use serde::Deserialize;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+/// A server the relay may connect to, chosen by clients with its name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upstream {
+    pub name: String,
+    pub url: String,
+}
+
+impl Upstream {
+    /*
+        FORMAT: <name>=<ws url>[,...]
+        e.g. main=ws://localhost:5001,backup=ws://10.0.0.2:5001
+    */
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        let mut upstreams: Vec<Upstream> = Vec::new();
+        for upstream in text.split(',').map(str::trim).filter(|u| !u.is_empty()) {
+            let upstream = match upstream.split_once('=') {
+                Some((name, url))
+                    if !name.is_empty()
+                        && (url.starts_with("ws://") || url.starts_with("wss://"))
+                        && url.into_client_request().is_ok() =>
+                {
+                    Upstream {
+                        name: name.to_string(),
+                        url: url.to_string(),
+                    }
+                }
+                _ => return Err(format!("invalid upstream: {upstream}")),
+            };
+            if upstreams.iter().any(|u| u.name == upstream.name) {
+                return Err(format!("duplicate upstream: {}", upstream.name));
+            }
+            upstreams.push(upstream);
+        }
+        Ok(upstreams)
+    }
+}
+
+/// The allowlist of upstreams.
+///
+/// Clients only ever pass a name, so the relay cannot be pointed at an arbitrary host.
+#[derive(Debug, Clone)]
+pub struct UpstreamConfig {
+    pub upstreams: Vec<Upstream>,
+    /// The upstream of clients that do not choose one.
+    pub default: String,
+}
+
+impl UpstreamConfig {
+    /// The first upstream is the default unless `default` names another one.
+    pub fn new(upstreams: Vec<Upstream>, default: Option<String>) -> Result<Self, String> {
+        let default = match default {
+            Some(default) if upstreams.iter().any(|u| u.name == default) => default,
+            Some(default) => return Err(format!("unknown default upstream: {default}")),
+            None => match upstreams.first() {
+                Some(upstream) => upstream.name.clone(),
+                None => return Err("no upstream is configured".into()),
+            },
+        };
+        Ok(UpstreamConfig { upstreams, default })
+    }
+
+    /// Looks up the upstream named by a client (the default one if `name` is `None`).
+    pub fn find(&self, name: Option<&str>) -> Option<&Upstream> {
+        let name = name.unwrap_or(&self.default);
+        self.upstreams.iter().find(|upstream| upstream.name == name)
+    }
+}
+
+/// The query of the WebSocket URL, e.g. `ws://localhost:7001/?upstream=backup&analyzers=native,mock`.
+#[derive(Debug, Default, Deserialize)]
+pub struct SessionQuery {
+    pub upstream: Option<String>,
+    /// `<name>[,...] | off` (see AnalyzerRegistry::parse_list)
+    pub analyzers: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upstreams() -> Vec<Upstream> {
+        Upstream::parse_list("main=ws://localhost:5001, backup=ws://10.0.0.2:5001,").unwrap()
+    }
+
+    #[test]
+    fn upstream_lists_are_parsed() {
+        assert_eq!(
+            upstreams(),
+            vec![
+                Upstream {
+                    name: "main".into(),
+                    url: "ws://localhost:5001".into()
+                },
+                Upstream {
+                    name: "backup".into(),
+                    url: "ws://10.0.0.2:5001".into()
+                },
+            ]
+        );
+        assert_eq!(Upstream::parse_list(""), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn malformed_and_duplicate_upstreams_are_rejected() {
+        for text in [
+            "ws://localhost:5001",
+            "=ws://localhost:5001",
+            "main=",
+            "main=localhost:5001",
+            "main=http://localhost:5001",
+            "main=ws://localhost:5001,backup",
+        ] {
+            assert!(Upstream::parse_list(text).is_err(), "{text}");
+        }
+        assert_eq!(
+            Upstream::parse_list("main=ws://localhost:5001,main=ws://10.0.0.2:5001"),
+            Err("duplicate upstream: main".into())
+        );
+    }
+
+    #[test]
+    fn the_default_upstream_must_be_configured() {
+        let config = UpstreamConfig::new(upstreams(), None).unwrap();
+        assert_eq!(config.default, "main");
+        let config = UpstreamConfig::new(upstreams(), Some("backup".into())).unwrap();
+        assert_eq!(config.find(None).unwrap().url, "ws://10.0.0.2:5001");
+
+        assert!(UpstreamConfig::new(upstreams(), Some("other".into())).is_err());
+        assert!(UpstreamConfig::new(upstreams(), Some("ws://localhost:5001".into())).is_err());
+        assert!(UpstreamConfig::new(Vec::new(), None).is_err());
+    }
+
+    #[test]
+    fn clients_only_choose_upstreams_by_name() {
+        let config = UpstreamConfig::new(upstreams(), None).unwrap();
+        assert_eq!(
+            config.find(Some("backup")).unwrap().url,
+            "ws://10.0.0.2:5001"
+        );
+        assert_eq!(config.find(Some("other")), None);
+        // a URL is never connected to, not even the URL of a configured upstream
+        assert_eq!(config.find(Some("ws://localhost:5001")), None);
+        assert_eq!(config.find(Some("ws://evil.example:5001")), None);
+        assert_eq!(config.find(Some("")), None);
+    }
+}
@@ -0,0 +1,162 @@
+/// A length of the analysis windows, in seconds or in frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowLength {
+    Seconds(f64),
+    Frames(u64),
+}
+
+impl WindowLength {
+    /*
+        FORMAT: <seconds>s | <frames>
+        e.g. 2.5s, 0.5s, 4096
+    */
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let length = match text.strip_suffix('s') {
+            Some(seconds) => match seconds.parse::<f64>() {
+                Ok(seconds) if seconds.is_finite() && seconds > 0.0 => {
+                    WindowLength::Seconds(seconds)
+                }
+                _ => return Err(format!("invalid window length: {text}")),
+            },
+            None => match text.parse::<u64>() {
+                Ok(frames) if frames > 0 => WindowLength::Frames(frames),
+                _ => return Err(format!("invalid window length: {text}")),
+            },
+        };
+        Ok(length)
+    }
+
+    /// The length in frames at `sample_rate` (at least one frame).
+    pub fn to_frames(self, sample_rate: u32) -> u64 {
+        match self {
+            WindowLength::Seconds(seconds) => (seconds * sample_rate as f64).round() as u64,
+            WindowLength::Frames(frames) => frames,
+        }
+        .max(1)
+    }
+}
+
+/// The taper applied to the samples of a window before they are analyzed
+/// (the PCM sent to the clients is left as it is).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WindowFunction {
+    #[default]
+    Rectangular,
+    Hann,
+    Hamming,
+}
+
+impl WindowFunction {
+    /*
+        FORMAT: rectangular | hann | hamming
+    */
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text.trim() {
+            "rectangular" => Ok(WindowFunction::Rectangular),
+            "hann" => Ok(WindowFunction::Hann),
+            "hamming" => Ok(WindowFunction::Hamming),
+            text => Err(format!("unknown window function: {text}")),
+        }
+    }
+
+    /// The (symmetric) weight of sample `n` of `len`.
+    pub fn weight(self, n: usize, len: usize) -> f32 {
+        if len < 2 {
+            return 1.0;
+        }
+        let phase = 2.0 * std::f64::consts::PI * n as f64 / (len - 1) as f64;
+        match self {
+            WindowFunction::Rectangular => 1.0,
+            WindowFunction::Hann => (0.5 - 0.5 * phase.cos()) as f32,
+            WindowFunction::Hamming => (0.54 - 0.46 * phase.cos()) as f32,
+        }
+    }
+
+    pub fn apply(self, samples: &mut [f32]) {
+        if self == WindowFunction::Rectangular {
+            return;
+        }
+        let len = samples.len();
+        for (n, sample) in samples.iter_mut().enumerate() {
+            *sample *= self.weight(n, len);
+        }
+    }
+}
+
+/// The windows cut from the PCM of a room: `window` frames, one every `hop` frames.
+///
+/// A hop shorter than the window makes the windows overlap, e.g. by half with `--window 4s --hop 2s`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowConfig {
+    pub window: WindowLength,
+    pub hop: WindowLength,
+    pub function: WindowFunction,
+}
+
+impl WindowConfig {
+    pub fn new(window: &str, hop: &str, function: &str) -> Result<Self, String> {
+        let config = WindowConfig {
+            window: WindowLength::parse(window)?,
+            hop: WindowLength::parse(hop)?,
+            function: WindowFunction::parse(function)?,
+        };
+        // a longer hop would skip frames (lengths in different units are only known at runtime)
+        let too_long = match (config.window, config.hop) {
+            (WindowLength::Seconds(window), WindowLength::Seconds(hop)) => hop > window,
+            (WindowLength::Frames(window), WindowLength::Frames(hop)) => hop > window,
+            _ => false,
+        };
+        if too_long {
+            return Err(format!(
+                "the hop ({hop}) must not be longer than the window ({window})"
+            ));
+        }
+        Ok(config)
+    }
+
+    /// The window and hop in frames at `sample_rate`, the hop at most the window.
+    pub fn frames(&self, sample_rate: u32) -> (u64, u64) {
+        let window = self.window.to_frames(sample_rate);
+        let hop = self.hop.to_frames(sample_rate).min(window);
+        (window, hop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lengths_in_seconds_are_rounded_to_frames() {
+        let config = WindowConfig::new("0.5s", "0.25s", "rectangular").unwrap();
+        assert_eq!(config.frames(44100), (22050, 11025));
+        assert_eq!(WindowLength::parse("4096").unwrap().to_frames(8000), 4096);
+    }
+
+    #[test]
+    fn hop_never_exceeds_window() {
+        assert!(WindowConfig::new("1s", "2s", "hann").is_err());
+        assert!(WindowConfig::new("1024", "2048", "hann").is_err());
+        // mixed units are clamped once the sample rate is known
+        let config = WindowConfig::new("1024", "1s", "hann").unwrap();
+        assert_eq!(config.frames(8000), (1024, 1024));
+    }
+
+    #[test]
+    fn window_functions_are_symmetric_tapers() {
+        for (function, edge) in [(WindowFunction::Hann, 0.0), (WindowFunction::Hamming, 0.08)] {
+            let mut samples = vec![1.0f32; 9];
+            function.apply(&mut samples);
+            assert!((samples[0] - edge).abs() < 1e-6, "{function:?}");
+            assert!((samples[8] - edge).abs() < 1e-6, "{function:?}");
+            assert!((samples[4] - 1.0).abs() < 1e-6, "{function:?}");
+            for n in 0..9 {
+                assert!((samples[n] - samples[8 - n]).abs() < 1e-6, "{function:?}");
+            }
+        }
+        let mut samples = vec![0.5f32; 4];
+        WindowFunction::Rectangular.apply(&mut samples);
+        assert_eq!(samples, vec![0.5; 4]);
+    }
+}
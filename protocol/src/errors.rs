@@ -0,0 +1,3 @@
+#[cfg(feature = "ws")]
+pub mod app;
+pub mod protocol;
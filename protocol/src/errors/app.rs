@@ -0,0 +1,58 @@
+use crate::models::protocol::{CLOSE_ERROR_BASE, ControlMessage};
+use axum::{
+    Json,
+    extract::ws::CloseFrame,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use serde_json::json;
+
+/// The status and message of a failed request or session, the binaries convert their errors into it.
+#[derive(Debug)]
+pub struct AppError {
+    pub status_code: StatusCode,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseError {
+    pub message: String,
+}
+
+// the payload of a close frame is limited to 125 bytes, 2 of which are the code
+static MAX_CLOSE_REASON_BYTES: usize = 123;
+
+//? errors after the upgrade are sent as an error event followed by a close frame //
+impl AppError {
+    pub fn to_control_message(&self) -> ControlMessage {
+        ControlMessage::Error {
+            status: self.status_code.as_u16(),
+            message: self.message.clone(),
+        }
+    }
+
+    /// The close frame that ends the session, e.g. 4404 "UnknownTrackError: no track with ID sample9".
+    pub fn to_close_frame(&self) -> CloseFrame {
+        let mut reason_len = self.message.len().min(MAX_CLOSE_REASON_BYTES);
+        while !self.message.is_char_boundary(reason_len) {
+            reason_len -= 1;
+        }
+        CloseFrame {
+            code: CLOSE_ERROR_BASE + self.status_code.as_u16(),
+            reason: self.message[..reason_len].into(),
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        (
+            self.status_code,
+            Json(json!(ResponseError {
+                message: self.message,
+            })),
+        )
+            .into_response()
+    }
+}
@@ -0,0 +1,8 @@
+/// A message or frame that does not follow the protocol.
+#[derive(Debug, thiserror::Error)]
+pub enum ProtocolError {
+    #[error("InvalidCommandError: {0}")]
+    InvalidCommandError(String),
+    #[error("InvalidFrameError: {0}")]
+    InvalidFrameError(String),
+}
@@ -0,0 +1,5 @@
+//! The wire format shared by the server, the middle-server and their tests:
+//! control messages, PCM frame headers and the audio format types they carry.
+
+pub mod errors;
+pub mod models;
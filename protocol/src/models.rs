@@ -0,0 +1,9 @@
+pub mod audio;
+pub mod format;
+pub mod frame;
+#[cfg(feature = "ws")]
+pub mod keepalive;
+pub mod protocol;
+pub mod transport;
+#[cfg(feature = "ws")]
+pub mod ws;
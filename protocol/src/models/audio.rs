@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PcmFormat {
+    Int,
+    Float,
+}
+
+impl fmt::Display for PcmFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PcmFormat::Int => write!(f, "int"),
+            PcmFormat::Float => write!(f, "float"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AudioInfo {
+    /// The number of channels.
+    pub channels: u16,
+
+    /// The number of samples per second.
+    ///
+    /// A common value is 44100, this is 44.1 kHz which is used for CD audio.
+    pub sample_rate: u32,
+
+    /// The number of bits per sample.
+    ///
+    /// A common value is 16 bits per sample, which is used for CD audio.
+    pub bits_per_sample: u16,
+
+    /// Whether the samples are float or integer values.
+    pub pcm_format: PcmFormat,
+}
+
+impl AudioInfo {
+    /// The number of bytes a single sample occupies on the wire.
+    ///
+    /// Integer samples whose bit depth is not a multiple of 8 (e.g. 12 or 20 bits)
+    /// are sent in the smallest whole-byte container that can hold them.
+    pub fn bytes_per_sample(&self) -> usize {
+        (self.bits_per_sample as usize).div_ceil(8)
+    }
+}
+
+#[cfg(feature = "hound")]
+impl From<hound::WavSpec> for AudioInfo {
+    fn from(spec: hound::WavSpec) -> Self {
+        let pcm_format = match spec.sample_format {
+            hound::SampleFormat::Float => PcmFormat::Float,
+            hound::SampleFormat::Int => PcmFormat::Int,
+        };
+
+        AudioInfo {
+            channels: spec.channels,
+            sample_rate: spec.sample_rate,
+            bits_per_sample: spec.bits_per_sample,
+            pcm_format,
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use crate::models::audio::PcmFormat;
+use serde::{Deserialize, Serialize};
+
+/// The output format a client asks for in the `open` handshake.
+///
+/// Every field is optional; missing fields keep the value of the track.
+/// The server decides which formats it supports.
+/*
+    FORMAT: {"sample_rate": <hz>, "channels": <n>, "bits_per_sample": <bits>, "pcm_format": "int" | "float"}
+    e.g. {"sample_rate": 48000, "channels": 2, "bits_per_sample": 16, "pcm_format": "int"}
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormatRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bits_per_sample: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pcm_format: Option<PcmFormat>,
+}
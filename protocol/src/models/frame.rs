@@ -0,0 +1,171 @@
+use crate::{
+    errors::protocol::ProtocolError,
+    models::audio::{AudioInfo, PcmFormat},
+};
+
+/// The header in front of every binary PCM message.
+///
+/// It makes each chunk self-describing, so downstream can detect lost or reordered chunks
+/// and knows which position of the stream a chunk holds.
+/*
+    FORMAT: 32 bytes, little-endian, followed by `frames × channels × ceil(bits_per_sample / 8)` bytes of PCM
+        offset  size  field
+        0       1     header version (1)
+        1       1     format tag: bit 7 = float, bits 0-6 = bits_per_sample
+        2       2     channels
+        4       4     stream ID (unique per stream, shared by the listeners of a station)
+        8       4     sequence number (per stream, starts at 0, +1 per chunk)
+        12      4     frames in this chunk
+        16      8     first frame (position of the first frame in the track)
+        24      8     presentation timestamp (microseconds since the UNIX epoch)
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcmFrameHeader {
+    pub stream_id: u32,
+    pub sequence: u32,
+    pub frames: u32,
+    pub first_frame: u64,
+    pub timestamp_us: u64,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    pub pcm_format: PcmFormat,
+}
+
+impl PcmFrameHeader {
+    pub const SIZE: usize = 32;
+    pub const VERSION: u8 = 1;
+
+    pub fn new(audio_info: &AudioInfo, stream_id: u32, sequence: u32) -> Self {
+        PcmFrameHeader {
+            stream_id,
+            sequence,
+            frames: 0,
+            first_frame: 0,
+            timestamp_us: 0,
+            channels: audio_info.channels,
+            bits_per_sample: audio_info.bits_per_sample,
+            pcm_format: audio_info.pcm_format,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let format_tag = match self.pcm_format {
+            PcmFormat::Int => self.bits_per_sample as u8,
+            PcmFormat::Float => 0x80 | self.bits_per_sample as u8,
+        };
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[0] = Self::VERSION;
+        bytes[1] = format_tag;
+        bytes[2..4].copy_from_slice(&self.channels.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.stream_id.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.sequence.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.frames.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.first_frame.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.timestamp_us.to_le_bytes());
+        bytes
+    }
+
+    /// Reads the header of `frame` and checks that the payload has the announced size.
+    pub fn from_frame(frame: &[u8]) -> Result<Self, ProtocolError> {
+        let header = Self::from_prefix(frame)?;
+        let expected = Self::SIZE + header.payload_size();
+        if frame.len() != expected {
+            return Err(ProtocolError::InvalidFrameError(format!(
+                "{} bytes, the header announces {}",
+                frame.len(),
+                expected
+            )));
+        }
+        Ok(header)
+    }
+
+    /// Reads the header at the start of `bytes`, whatever follows it.
+    fn from_prefix(frame: &[u8]) -> Result<Self, ProtocolError> {
+        if frame.len() < Self::SIZE {
+            return Err(ProtocolError::InvalidFrameError(format!(
+                "{} bytes is shorter than the header",
+                frame.len()
+            )));
+        }
+        if frame[0] != Self::VERSION {
+            return Err(ProtocolError::InvalidFrameError(format!(
+                "unsupported header version {}",
+                frame[0]
+            )));
+        }
+
+        let u16_at = |offset: usize| u16::from_le_bytes([frame[offset], frame[offset + 1]]);
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes(frame[offset..offset + 4].try_into().unwrap_or_default())
+        };
+        let u64_at = |offset: usize| {
+            u64::from_le_bytes(frame[offset..offset + 8].try_into().unwrap_or_default())
+        };
+        let header = PcmFrameHeader {
+            pcm_format: if frame[1] & 0x80 != 0 {
+                PcmFormat::Float
+            } else {
+                PcmFormat::Int
+            },
+            bits_per_sample: (frame[1] & 0x7f) as u16,
+            channels: u16_at(2),
+            stream_id: u32_at(4),
+            sequence: u32_at(8),
+            frames: u32_at(12),
+            first_frame: u64_at(16),
+            timestamp_us: u64_at(24),
+        };
+        Ok(header)
+    }
+
+    pub fn payload_size(&self) -> usize {
+        self.frames as usize * self.channels as usize * (self.bits_per_sample as usize).div_ceil(8)
+    }
+
+    /// Decodes the PCM that follows this header into interleaved samples in [-1.0, 1.0].
+    ///
+    /// The inverse of the server's `pcm_encoder`: int samples are sign-extended from
+    /// `ceil(bits_per_sample / 8)` bytes and scaled by `2^(bits_per_sample - 1)`.
+    pub fn decode_samples(&self, payload: &[u8]) -> Vec<f32> {
+        match self.pcm_format {
+            PcmFormat::Float => payload
+                .chunks_exact(4)
+                .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect(),
+            PcmFormat::Int => {
+                let bytes_per_sample = (self.bits_per_sample as usize).div_ceil(8).clamp(1, 4);
+                let shift = 8 * (4 - bytes_per_sample) as u32;
+                let scale = (1u64 << self.bits_per_sample.clamp(1, 32).saturating_sub(1)) as f32;
+                payload
+                    .chunks_exact(bytes_per_sample)
+                    .map(|bytes| {
+                        // place the bytes at the top of an i32 and shift back to sign-extend
+                        let mut sample = [0u8; 4];
+                        sample[4 - bytes_per_sample..].copy_from_slice(bytes);
+                        (i32::from_le_bytes(sample) >> shift) as f32 / scale
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Splits a concatenation of frames (e.g. a window of the middle-server) into headers and payloads.
+pub fn frame_splitter(bytes: &[u8]) -> Result<Vec<(PcmFrameHeader, &[u8])>, ProtocolError> {
+    let mut frames = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let header = PcmFrameHeader::from_prefix(rest)?;
+        let end = PcmFrameHeader::SIZE + header.payload_size();
+        if rest.len() < end {
+            return Err(ProtocolError::InvalidFrameError(format!(
+                "{} bytes left, the header announces {}",
+                rest.len(),
+                end
+            )));
+        }
+        frames.push((header, &rest[PcmFrameHeader::SIZE..end]));
+        rest = &rest[end..];
+    }
+    Ok(frames)
+}
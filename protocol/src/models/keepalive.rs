@@ -0,0 +1,210 @@
+use crate::models::protocol::{CLOSE_HANDSHAKE_TIMEOUT, CLOSE_IDLE_TIMEOUT};
+use axum::{
+    body::Bytes,
+    extract::ws::{CloseFrame, Message, WebSocket},
+};
+use std::time::Duration;
+use tokio::time::Instant;
+
+// how long a close frame to an unresponsive peer may take
+static CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Timeouts of a WebSocket connection, the same for the server and both legs of the middle-server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepaliveConfig {
+    /// How often the peer is pinged.
+    pub ping_interval: Duration,
+    /// How long the peer may stay silent (no message, not even a pong) before it is considered dead.
+    pub idle_timeout: Duration,
+    /// How long the peer may take to say hello after connecting.
+    pub handshake_timeout: Duration,
+}
+
+impl Default for KeepaliveConfig {
+    fn default() -> Self {
+        KeepaliveConfig {
+            ping_interval: Duration::from_secs(15),
+            idle_timeout: Duration::from_secs(45),
+            handshake_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl KeepaliveConfig {
+    pub fn new(
+        ping_interval: Duration,
+        idle_timeout: Duration,
+        handshake_timeout: Duration,
+    ) -> Result<Self, String> {
+        if [ping_interval, idle_timeout, handshake_timeout]
+            .iter()
+            .any(Duration::is_zero)
+        {
+            return Err("the keepalive interval and timeouts must be positive".into());
+        }
+        // a live peer needs at least one ping to answer before it is declared dead
+        if idle_timeout <= ping_interval {
+            return Err(format!(
+                "the idle timeout ({idle_timeout:?}) must be longer than the keepalive interval ({ping_interval:?})"
+            ));
+        }
+        Ok(KeepaliveConfig {
+            ping_interval,
+            idle_timeout,
+            handshake_timeout,
+        })
+    }
+
+    /*
+        FORMAT: whole seconds, each variable is optional
+            KEEPALIVE_INTERVAL_SECS=15
+            IDLE_TIMEOUT_SECS=45
+            HANDSHAKE_TIMEOUT_SECS=10
+    */
+    pub fn from_env() -> Result<Self, String> {
+        let default = KeepaliveConfig::default();
+        KeepaliveConfig::new(
+            secs_from_env("KEEPALIVE_INTERVAL_SECS", default.ping_interval)?,
+            secs_from_env("IDLE_TIMEOUT_SECS", default.idle_timeout)?,
+            secs_from_env("HANDSHAKE_TIMEOUT_SECS", default.handshake_timeout)?,
+        )
+    }
+}
+
+fn secs_from_env(name: &str, default: Duration) -> Result<Duration, String> {
+    match std::env::var(name) {
+        Ok(secs) => match secs.trim().parse::<u64>() {
+            Ok(secs) if secs > 0 => Ok(Duration::from_secs(secs)),
+            _ => Err(format!("invalid {name}: {secs}")),
+        },
+        Err(_) => Ok(default),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeepaliveEvent {
+    /// Time to ping the peer.
+    Ping,
+    /// The peer has been silent for longer than the idle timeout.
+    IdleTimeout,
+    /// The peer did not say hello in time.
+    HandshakeTimeout,
+}
+
+impl KeepaliveEvent {
+    /// The close code and reason of a timeout, `None` for a ping.
+    pub fn close_reason(self) -> Option<(u16, &'static str)> {
+        match self {
+            KeepaliveEvent::Ping => None,
+            KeepaliveEvent::IdleTimeout => Some((CLOSE_IDLE_TIMEOUT, "idle timeout")),
+            KeepaliveEvent::HandshakeTimeout => {
+                Some((CLOSE_HANDSHAKE_TIMEOUT, "handshake timeout"))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liveness {
+    Alive,
+    /// The peer is gone and the connection should be dropped.
+    Dead,
+}
+
+/// Pings a peer periodically and notices when it goes silent.
+///
+/// Call `seen` for every message received from the peer, pongs included.
+/// `send` and `respond` serve a whole `WebSocket`, the split legs of the middle-server send on their own writers.
+pub struct Keepalive {
+    config: KeepaliveConfig,
+    last_seen: Instant,
+    next_ping: Instant,
+    handshake_deadline: Option<Instant>,
+}
+
+impl Keepalive {
+    pub fn new(config: KeepaliveConfig) -> Self {
+        let now = Instant::now();
+        Keepalive {
+            config,
+            last_seen: now,
+            next_ping: now + config.ping_interval,
+            handshake_deadline: Some(now + config.handshake_timeout),
+        }
+    }
+
+    pub fn seen(&mut self) {
+        self.last_seen = Instant::now();
+    }
+
+    pub fn handshake_done(&mut self) {
+        self.handshake_deadline = None;
+    }
+
+    /// Waits for the next keepalive event.
+    ///
+    /// NOTE: cancel safe, so it can be polled in `tokio::select!` next to `recv` or `next`.
+    pub async fn tick(&mut self) -> KeepaliveEvent {
+        let idle_deadline = self.last_seen + self.config.idle_timeout;
+        let deadline = [
+            Some(self.next_ping),
+            Some(idle_deadline),
+            self.handshake_deadline,
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(idle_deadline);
+        tokio::time::sleep_until(deadline).await;
+
+        let now = Instant::now();
+        if self
+            .handshake_deadline
+            .is_some_and(|deadline| now >= deadline)
+        {
+            return KeepaliveEvent::HandshakeTimeout;
+        }
+        if now >= idle_deadline {
+            return KeepaliveEvent::IdleTimeout;
+        }
+        self.next_ping = now + self.config.ping_interval;
+        KeepaliveEvent::Ping
+    }
+
+    /// Sends `message`, giving up on a peer that does not take it within the idle timeout.
+    ///
+    /// A dead peer stops reading, so without the limit the send would wait for the TCP timeout.
+    pub async fn send(
+        &self,
+        socket: &mut WebSocket,
+        message: Message,
+    ) -> Result<Liveness, axum::Error> {
+        match tokio::time::timeout(self.config.idle_timeout, socket.send(message)).await {
+            Ok(result) => result.map(|()| Liveness::Alive),
+            Err(_) => {
+                tracing::warn!("Peer stopped reading, giving up");
+                Ok(Liveness::Dead)
+            }
+        }
+    }
+
+    /// Pings the peer, or closes the connection of a peer that stopped answering.
+    pub async fn respond(
+        &self,
+        socket: &mut WebSocket,
+        event: KeepaliveEvent,
+    ) -> Result<Liveness, axum::Error> {
+        let Some((code, reason)) = event.close_reason() else {
+            // the pong is answered by axum itself
+            return self.send(socket, Message::Ping(Bytes::new())).await;
+        };
+        tracing::warn!("Closing the connection: {}", reason);
+        // the peer is probably gone, so the close frame is sent on a best-effort basis
+        let close = Message::Close(Some(CloseFrame {
+            code,
+            reason: reason.into(),
+        }));
+        let _ = tokio::time::timeout(CLOSE_TIMEOUT, socket.send(close)).await;
+        Ok(Liveness::Dead)
+    }
+}
@@ -0,0 +1,150 @@
+use crate::models::{
+    audio::AudioInfo,
+    format::FormatRequest,
+    transport::{StreamPosition, TransportCommand},
+};
+use serde::{Deserialize, Serialize};
+
+/// The version of the control protocol and the binary frame layout.
+/// Bump it on every incompatible change.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+// close codes of this application (4000-4999 are for private use, see RFC 6455 7.4.2)
+/// The peer sent nothing, not even a pong, within the idle timeout.
+pub const CLOSE_IDLE_TIMEOUT: u16 = 4000;
+/// The peer did not say hello within the handshake timeout.
+pub const CLOSE_HANDSHAKE_TIMEOUT: u16 = 4001;
+/// The server leg of the relay was lost (sent by the middle-server only).
+pub const CLOSE_UPSTREAM_LOST: u16 = 4002;
+/// A session that failed is closed with this code + the HTTP status of its error event
+/// (e.g. 4404 for an unknown track, 4500 for a decoder failure).
+pub const CLOSE_ERROR_BASE: u16 = 4000;
+
+/// A control message, sent as a JSON text frame. PCM is sent in binary frames (see `PcmFrameHeader`).
+/*
+    FORMAT: {"type": "<kebab-case variant>", ...fields}
+
+    client                         server
+      | -- hello {version} -------> |
+      | <------- hello {version} -- |
+      | -- open {track_id, format}> |
+      | <---------- audio-info ---- |
+      | -- accept ----------------> |
+      | <================= binary  |
+      | -- transport {command} ---> |
+      | <-------- end-of-stream --- |
+      | <---------------- error --- |  (on any failure, followed by a close frame)
+      | <------------- upstream --- |  (middle-server only, while the server leg is replaced)
+
+    e.g.
+        {"type": "hello", "version": 2}
+        {"type": "hello", "version": 2, "upstream": "main"}
+        {"type": "open", "track_id": "sample3", "format": {"sample_rate": 48000, "channels": 1}}
+        {"type": "open", "track_id": "sample3", "start": {"frame": 88064}}
+        {"type": "audio-info", "channels": 2, "sample_rate": 44100, "bits_per_sample": 16, "pcm_format": "int", "seekable": true}
+        {"type": "accept"}
+        {"type": "transport", "command": "seek", "position": {"millisecond": 1500}}
+        {"type": "end-of-stream", "reason": "finished"}
+        {"type": "error", "status": 404, "message": "UnknownTrackError: no track with ID sample9"}
+        {"type": "upstream", "status": "reconnecting", "attempt": 1, "retry_in_ms": 500}
+*/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ControlMessage {
+    /// The first message of both peers.
+    Hello {
+        version: u32,
+        /// The named upstream server a client asks the middle-server to relay to (ignored by the server).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        upstream: Option<String>,
+        /// The analyzers a client asks the middle-server to run on every window (ignored by the server).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        analyzers: Option<Vec<String>>,
+    },
+    /// Opens a track (the default track if `track_id` is omitted) or a station (`station:<name>`).
+    Open {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        track_id: Option<String>,
+        #[serde(default)]
+        format: FormatRequest,
+        /// Where the stream starts (only for seekable tracks), e.g. to resume after a reconnect.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        start: Option<StreamPosition>,
+    },
+    /// The format of the PCM that follows `accept`.
+    AudioInfo {
+        #[serde(flatten)]
+        info: AudioInfo,
+        /// Whether the stream accepts `seek` and `start` (false for stations and live inputs).
+        #[serde(default)]
+        seekable: bool,
+    },
+    /// Starts streaming the opened track.
+    Accept,
+    Transport(TransportCommand),
+    /// The stream ended, another track can be opened.
+    EndOfStream {
+        reason: EndOfStreamReason,
+    },
+    /// The request failed; `status` is an HTTP status code.
+    Error {
+        status: u16,
+        message: String,
+    },
+    /// The state of the server leg of the relay (sent by the middle-server only).
+    Upstream(UpstreamStatus),
+}
+
+/*
+    FORMAT (inside an "upstream" control message):
+        {"status": "reconnecting", "attempt": 1, "retry_in_ms": 500}
+        {"status": "reconnected", "resume_frame": 88064}
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum UpstreamStatus {
+    /// The server connection was lost, attempt `attempt` follows in `retry_in_ms`.
+    Reconnecting { attempt: u32, retry_in_ms: u64 },
+    /// The session was restored, at `resume_frame` if the stream was resumed by seeking.
+    Reconnected {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        resume_frame: Option<u64>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EndOfStreamReason {
+    /// The end of the track was reached.
+    Finished,
+    /// The client sent `stop`.
+    Stopped,
+}
+
+impl ControlMessage {
+    /// The `type` of the message, for logs and errors.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ControlMessage::Hello { .. } => "hello",
+            ControlMessage::Open { .. } => "open",
+            ControlMessage::AudioInfo { .. } => "audio-info",
+            ControlMessage::Accept => "accept",
+            ControlMessage::Transport(_) => "transport",
+            ControlMessage::EndOfStream { .. } => "end-of-stream",
+            ControlMessage::Error { .. } => "error",
+            ControlMessage::Upstream(_) => "upstream",
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("control messages always serialize")
+    }
+}
+
+impl TryFrom<&str> for ControlMessage {
+    type Error = serde_json::Error;
+
+    fn try_from(text: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{errors::protocol::ProtocolError, models::protocol::ControlMessage};
+use serde::{Deserialize, Serialize};
+
+/*
+    FORMAT: {"frame": <frame>} | {"millisecond": <millisecond>}
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamPosition {
+    /// An offset in frames (samples per channel).
+    Frame(u64),
+    /// An offset in milliseconds.
+    Millisecond(u64),
+}
+
+impl StreamPosition {
+    /// The offset in frames, clamped to `u64::MAX` for positions no stream reaches.
+    pub fn to_frame(self, sample_rate: u32) -> u64 {
+        match self {
+            StreamPosition::Frame(frame) => frame,
+            StreamPosition::Millisecond(millisecond) => {
+                let frame = millisecond as u128 * sample_rate as u128 / 1000;
+                u64::try_from(frame).unwrap_or(u64::MAX)
+            }
+        }
+    }
+}
+
+/*
+    FORMAT (inside a "transport" control message):
+        {"command": "pause"}
+        {"command": "resume"}
+        {"command": "seek", "position": <position>}
+        {"command": "loop", "start": <position>, "end": <position>}
+        {"command": "loop-off"}
+        {"command": "stop"}
+
+    Clients relayed the same stream by the middle-server share it: only the client that opened it
+    (or the longest connected one once it left) may move it, the others get a 403 error.
+    `stop` only ends the stream of the client that sent it.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum TransportCommand {
+    Pause,
+    Resume,
+    Seek {
+        position: StreamPosition,
+    },
+    /// Loop between two positions.
+    Loop {
+        start: StreamPosition,
+        end: StreamPosition,
+    },
+    LoopOff,
+    Stop,
+}
+
+impl TryFrom<&str> for TransportCommand {
+    type Error = ProtocolError;
+
+    /// Parses a control message received while streaming, which must be a `transport` message.
+    fn try_from(text: &str) -> Result<Self, Self::Error> {
+        match ControlMessage::try_from(text) {
+            Ok(ControlMessage::Transport(command)) => Ok(command),
+            Ok(message) => Err(ProtocolError::InvalidCommandError(format!(
+                "unexpected {} message while streaming",
+                message.kind()
+            ))),
+            Err(e) => Err(ProtocolError::InvalidCommandError(format!(
+                "invalid control message: {e}"
+            ))),
+        }
+    }
+}
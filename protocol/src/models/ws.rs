@@ -1,3 +1,5 @@
+// the two legs of the middle-server, split into reader and writer halves
+
 // client websocket
 pub type MutexWebSocketClientWriter = std::sync::Arc<
     tokio::sync::Mutex<
@@ -11,6 +13,7 @@ pub type WebSocketServerWriter = futures_util::stream::SplitSink<
     tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>,
     tokio_tungstenite::tungstenite::Message,
 >;
+pub type MutexWebSocketServerWriter = std::sync::Arc<tokio::sync::Mutex<WebSocketServerWriter>>;
 pub type WebSocketServerReader = futures_util::stream::SplitStream<
     tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>,
 >;
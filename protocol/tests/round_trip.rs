@@ -0,0 +1,430 @@
+use protocol::{
+    errors::protocol::ProtocolError,
+    models::{
+        audio::{AudioInfo, PcmFormat},
+        format::FormatRequest,
+        frame::{PcmFrameHeader, frame_splitter},
+        protocol::{ControlMessage, EndOfStreamReason, PROTOCOL_VERSION, UpstreamStatus},
+        transport::{StreamPosition, TransportCommand},
+    },
+};
+
+fn audio_info() -> AudioInfo {
+    AudioInfo {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 24,
+        pcm_format: PcmFormat::Int,
+    }
+}
+
+fn round_trip(message: &ControlMessage) -> ControlMessage {
+    ControlMessage::try_from(message.to_json().as_str()).expect("a serialized message parses")
+}
+
+#[test]
+fn control_messages_round_trip() {
+    let messages = [
+        ControlMessage::Hello {
+            version: PROTOCOL_VERSION,
+            upstream: None,
+            analyzers: None,
+        },
+        ControlMessage::Hello {
+            version: PROTOCOL_VERSION,
+            upstream: Some("main".into()),
+            analyzers: Some(vec!["native".into(), "mock".into()]),
+        },
+        ControlMessage::Open {
+            track_id: None,
+            format: FormatRequest::default(),
+            start: None,
+        },
+        ControlMessage::Open {
+            track_id: Some("station:main".into()),
+            format: FormatRequest {
+                sample_rate: Some(48000),
+                channels: Some(1),
+                bits_per_sample: Some(32),
+                pcm_format: Some(PcmFormat::Float),
+            },
+            start: None,
+        },
+        ControlMessage::Open {
+            track_id: Some("sample3".into()),
+            format: FormatRequest::default(),
+            start: Some(StreamPosition::Frame(88064)),
+        },
+        ControlMessage::AudioInfo {
+            info: audio_info(),
+            seekable: true,
+        },
+        ControlMessage::Accept,
+        ControlMessage::Transport(TransportCommand::Pause),
+        ControlMessage::Transport(TransportCommand::Resume),
+        ControlMessage::Transport(TransportCommand::Seek {
+            position: StreamPosition::Millisecond(1500),
+        }),
+        ControlMessage::Transport(TransportCommand::Loop {
+            start: StreamPosition::Frame(0),
+            end: StreamPosition::Frame(44100),
+        }),
+        ControlMessage::Transport(TransportCommand::LoopOff),
+        ControlMessage::Transport(TransportCommand::Stop),
+        ControlMessage::EndOfStream {
+            reason: EndOfStreamReason::Finished,
+        },
+        ControlMessage::EndOfStream {
+            reason: EndOfStreamReason::Stopped,
+        },
+        ControlMessage::Error {
+            status: 404,
+            message: "UnknownTrackError: no track with ID sample9".into(),
+        },
+        ControlMessage::Upstream(UpstreamStatus::Reconnecting {
+            attempt: 2,
+            retry_in_ms: 1000,
+        }),
+        ControlMessage::Upstream(UpstreamStatus::Reconnected { resume_frame: None }),
+        ControlMessage::Upstream(UpstreamStatus::Reconnected {
+            resume_frame: Some(88064),
+        }),
+    ];
+    for message in &messages {
+        assert_eq!(&round_trip(message), message);
+    }
+}
+
+#[test]
+fn control_messages_match_the_documented_format() {
+    let cases = [
+        (
+            r#"{"type":"hello","version":2}"#,
+            ControlMessage::Hello {
+                version: 2,
+                upstream: None,
+                analyzers: None,
+            },
+        ),
+        (
+            r#"{"type":"hello","version":2,"upstream":"main"}"#,
+            ControlMessage::Hello {
+                version: 2,
+                upstream: Some("main".into()),
+                analyzers: None,
+            },
+        ),
+        (
+            r#"{"type":"hello","version":2,"analyzers":["native","librosa"]}"#,
+            ControlMessage::Hello {
+                version: 2,
+                upstream: None,
+                analyzers: Some(vec!["native".into(), "librosa".into()]),
+            },
+        ),
+        (
+            r#"{"type":"open","track_id":"sample3","format":{"sample_rate":48000,"channels":1}}"#,
+            ControlMessage::Open {
+                track_id: Some("sample3".into()),
+                format: FormatRequest {
+                    sample_rate: Some(48000),
+                    channels: Some(1),
+                    ..Default::default()
+                },
+                start: None,
+            },
+        ),
+        (
+            r#"{"type":"open","track_id":"sample3","format":{},"start":{"frame":88064}}"#,
+            ControlMessage::Open {
+                track_id: Some("sample3".into()),
+                format: FormatRequest::default(),
+                start: Some(StreamPosition::Frame(88064)),
+            },
+        ),
+        (
+            r#"{"type":"audio-info","channels":2,"sample_rate":44100,"bits_per_sample":24,"pcm_format":"int","seekable":true}"#,
+            ControlMessage::AudioInfo {
+                info: audio_info(),
+                seekable: true,
+            },
+        ),
+        (r#"{"type":"accept"}"#, ControlMessage::Accept),
+        (
+            r#"{"type":"transport","command":"seek","position":{"millisecond":1500}}"#,
+            ControlMessage::Transport(TransportCommand::Seek {
+                position: StreamPosition::Millisecond(1500),
+            }),
+        ),
+        (
+            r#"{"type":"end-of-stream","reason":"finished"}"#,
+            ControlMessage::EndOfStream {
+                reason: EndOfStreamReason::Finished,
+            },
+        ),
+        (
+            r#"{"type":"upstream","status":"reconnecting","attempt":1,"retry_in_ms":500}"#,
+            ControlMessage::Upstream(UpstreamStatus::Reconnecting {
+                attempt: 1,
+                retry_in_ms: 500,
+            }),
+        ),
+    ];
+    for (json, message) in cases {
+        assert_eq!(message.to_json(), json);
+        assert_eq!(ControlMessage::try_from(json).unwrap(), message);
+    }
+}
+
+#[test]
+fn open_without_fields_opens_the_default_track() {
+    assert_eq!(
+        ControlMessage::try_from(r#"{"type":"open"}"#).unwrap(),
+        ControlMessage::Open {
+            track_id: None,
+            format: FormatRequest::default(),
+            start: None,
+        }
+    );
+}
+
+#[test]
+fn audio_info_without_seekable_is_not_seekable() {
+    assert_eq!(
+        ControlMessage::try_from(
+            r#"{"type":"audio-info","channels":2,"sample_rate":44100,"bits_per_sample":24,"pcm_format":"int"}"#
+        )
+        .unwrap(),
+        ControlMessage::AudioInfo {
+            info: audio_info(),
+            seekable: false,
+        }
+    );
+}
+
+#[test]
+fn invalid_control_messages_are_rejected() {
+    for text in [
+        "open",
+        r#"{"type":"shutdown"}"#,
+        r#"{"type":"hello"}"#,
+        r#"{"type":"open","format":{"sample_rate":48000,"bitrate":320}}"#,
+        r#"{"type":"transport","command":"rewind"}"#,
+    ] {
+        assert!(ControlMessage::try_from(text).is_err(), "{text}");
+    }
+}
+
+#[test]
+fn transport_commands_are_parsed_from_transport_messages_only() {
+    assert_eq!(
+        TransportCommand::try_from(r#"{"type":"transport","command":"loop-off"}"#).unwrap(),
+        TransportCommand::LoopOff
+    );
+    assert!(matches!(
+        TransportCommand::try_from(r#"{"type":"accept"}"#),
+        Err(ProtocolError::InvalidCommandError(_))
+    ));
+    assert!(matches!(
+        TransportCommand::try_from("pause"),
+        Err(ProtocolError::InvalidCommandError(_))
+    ));
+}
+
+#[test]
+fn stream_positions_convert_to_frames() {
+    assert_eq!(StreamPosition::Frame(123).to_frame(44100), 123);
+    assert_eq!(StreamPosition::Millisecond(1500).to_frame(48000), 72000);
+    // the product overflows a u64 although the frame does not
+    assert_eq!(
+        StreamPosition::Millisecond(100_000_000_000_000_000).to_frame(48000),
+        4_800_000_000_000_000_000
+    );
+    // frames past u64::MAX clamp
+    assert_eq!(
+        StreamPosition::Millisecond(u64::MAX).to_frame(48000),
+        u64::MAX
+    );
+    let position: StreamPosition =
+        serde_json::from_str(r#"{"millisecond": 100000000000000000}"#).unwrap();
+    assert_eq!(position.to_frame(48000), 4_800_000_000_000_000_000);
+}
+
+fn frame(header: &PcmFrameHeader) -> Vec<u8> {
+    let mut frame = header.to_bytes().to_vec();
+    frame.resize(PcmFrameHeader::SIZE + header.payload_size(), 0x5a);
+    frame
+}
+
+#[test]
+fn frame_headers_round_trip() {
+    for info in [
+        audio_info(),
+        AudioInfo {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 12,
+            pcm_format: PcmFormat::Int,
+        },
+        AudioInfo {
+            channels: 8,
+            sample_rate: 192000,
+            bits_per_sample: 32,
+            pcm_format: PcmFormat::Float,
+        },
+    ] {
+        let mut header = PcmFrameHeader::new(&info, u32::MAX, 7);
+        header.frames = 1024;
+        header.first_frame = u64::MAX - 1;
+        header.timestamp_us = 1_760_000_000_000_000;
+
+        let frame = frame(&header);
+        assert_eq!(
+            frame.len(),
+            PcmFrameHeader::SIZE + 1024 * info.channels as usize * info.bytes_per_sample()
+        );
+        assert_eq!(PcmFrameHeader::from_frame(&frame).unwrap(), header);
+    }
+}
+
+#[test]
+fn frame_header_layout_is_stable() {
+    let mut header = PcmFrameHeader::new(
+        &AudioInfo {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 32,
+            pcm_format: PcmFormat::Float,
+        },
+        0x0403_0201,
+        0x0807_0605,
+    );
+    header.frames = 1;
+    header.first_frame = 0x100;
+    header.timestamp_us = 0x200;
+    assert_eq!(
+        header.to_bytes(),
+        [
+            1, 0xa0, 2, 0, // version, float 32 bits, 2 channels
+            1, 2, 3, 4, // stream ID
+            5, 6, 7, 8, // sequence number
+            1, 0, 0, 0, // frames
+            0, 1, 0, 0, 0, 0, 0, 0, // first frame
+            0, 2, 0, 0, 0, 0, 0, 0, // timestamp
+        ]
+    );
+}
+
+#[test]
+fn malformed_frames_are_rejected() {
+    let mut header = PcmFrameHeader::new(&audio_info(), 1, 0);
+    header.frames = 16;
+    let valid = frame(&header);
+
+    let short = &valid[..PcmFrameHeader::SIZE - 1];
+    let truncated = &valid[..valid.len() - 1];
+    let mut unknown_version = valid.clone();
+    unknown_version[0] = PcmFrameHeader::VERSION + 1;
+    for frame in [short, truncated, &unknown_version] {
+        assert!(matches!(
+            PcmFrameHeader::from_frame(frame),
+            Err(ProtocolError::InvalidFrameError(_))
+        ));
+    }
+}
+
+#[cfg(feature = "hound")]
+#[test]
+fn audio_info_from_wav_spec() {
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 24,
+        sample_format: hound::SampleFormat::Int,
+    };
+    assert_eq!(AudioInfo::from(spec), audio_info());
+
+    let spec = hound::WavSpec {
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+        ..spec
+    };
+    assert_eq!(AudioInfo::from(spec).pcm_format, PcmFormat::Float);
+}
+
+fn encoded_frame(info: &AudioInfo, payload: &[u8]) -> Vec<u8> {
+    let mut header = PcmFrameHeader::new(info, 1, 0);
+    header.frames = (payload.len() / (info.channels as usize * info.bytes_per_sample())) as u32;
+    let mut frame = header.to_bytes().to_vec();
+    frame.extend_from_slice(payload);
+    frame
+}
+
+#[test]
+fn pcm_payloads_decode_to_normalized_samples() {
+    let cases: [(u16, PcmFormat, Vec<u8>, Vec<f32>); 4] = [
+        (
+            16,
+            PcmFormat::Int,
+            [i16::MIN, 0, 16384, -16384]
+                .iter()
+                .flat_map(|sample| sample.to_le_bytes())
+                .collect(),
+            vec![-1.0, 0.0, 0.5, -0.5],
+        ),
+        (
+            24,
+            PcmFormat::Int,
+            [-(1 << 23), 1 << 22, 0, -(1 << 22)]
+                .iter()
+                .flat_map(|sample: &i32| sample.to_le_bytes()[..3].to_vec())
+                .collect(),
+            vec![-1.0, 0.5, 0.0, -0.5],
+        ),
+        (
+            8,
+            PcmFormat::Int,
+            vec![0x80, 0x40, 0x00, 0xc0],
+            vec![-1.0, 0.5, 0.0, -0.5],
+        ),
+        (
+            32,
+            PcmFormat::Float,
+            [0.25f32, -0.75, 1.0, 0.0]
+                .iter()
+                .flat_map(|sample| sample.to_le_bytes())
+                .collect(),
+            vec![0.25, -0.75, 1.0, 0.0],
+        ),
+    ];
+    for (bits_per_sample, pcm_format, payload, samples) in cases {
+        let info = AudioInfo {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample,
+            pcm_format,
+        };
+        let frame = encoded_frame(&info, &payload);
+        let header = PcmFrameHeader::from_frame(&frame).unwrap();
+        assert_eq!(
+            header.decode_samples(&frame[PcmFrameHeader::SIZE..]),
+            samples
+        );
+    }
+}
+
+#[test]
+fn windows_split_into_frames() {
+    let info = audio_info();
+    let first = encoded_frame(&info, &[1; 12]);
+    let second = encoded_frame(&info, &[2; 6]);
+    let window = [first.as_slice(), second.as_slice()].concat();
+
+    let frames = frame_splitter(&window).unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!((frames[0].0.frames, frames[0].1), (2, &[1u8; 12][..]));
+    assert_eq!((frames[1].0.frames, frames[1].1), (1, &[2u8; 6][..]));
+
+    assert!(frame_splitter(&window[..window.len() - 1]).is_err());
+    assert!(frame_splitter(&[]).unwrap().is_empty());
+}
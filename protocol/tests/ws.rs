@@ -0,0 +1,85 @@
+#![cfg(feature = "ws")]
+
+use axum::http::StatusCode;
+use protocol::{
+    errors::app::AppError,
+    models::{
+        keepalive::{Keepalive, KeepaliveConfig, KeepaliveEvent},
+        protocol::{CLOSE_HANDSHAKE_TIMEOUT, CLOSE_IDLE_TIMEOUT, ControlMessage},
+    },
+};
+use std::time::Duration;
+
+fn secs(secs: u64) -> Duration {
+    Duration::from_secs(secs)
+}
+
+#[test]
+fn keepalive_configs_need_a_ping_within_the_idle_timeout() {
+    assert!(KeepaliveConfig::new(secs(15), secs(45), secs(10)).is_ok());
+    assert!(KeepaliveConfig::new(secs(15), secs(15), secs(10)).is_err());
+    assert!(KeepaliveConfig::new(Duration::ZERO, secs(45), secs(10)).is_err());
+    assert!(KeepaliveConfig::new(secs(15), secs(45), Duration::ZERO).is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn keepalive_pings_until_the_peer_goes_silent() {
+    let config = KeepaliveConfig::new(secs(1), secs(3), secs(2)).unwrap();
+
+    // a peer that never says hello
+    let mut keepalive = Keepalive::new(config);
+    assert_eq!(keepalive.tick().await, KeepaliveEvent::Ping);
+    assert_eq!(keepalive.tick().await, KeepaliveEvent::HandshakeTimeout);
+
+    // a peer that said hello and answers the first ping only (at 1s, so it is idle at 4s)
+    let mut keepalive = Keepalive::new(config);
+    keepalive.handshake_done();
+    let start = tokio::time::Instant::now();
+    assert_eq!(keepalive.tick().await, KeepaliveEvent::Ping);
+    keepalive.seen();
+    for _ in 0..2 {
+        assert_eq!(keepalive.tick().await, KeepaliveEvent::Ping);
+    }
+    assert_eq!(keepalive.tick().await, KeepaliveEvent::IdleTimeout);
+    assert_eq!(start.elapsed(), secs(4));
+}
+
+#[test]
+fn keepalive_timeouts_close_with_their_codes() {
+    assert_eq!(KeepaliveEvent::Ping.close_reason(), None);
+    assert_eq!(
+        KeepaliveEvent::IdleTimeout.close_reason(),
+        Some((CLOSE_IDLE_TIMEOUT, "idle timeout"))
+    );
+    assert_eq!(
+        KeepaliveEvent::HandshakeTimeout.close_reason(),
+        Some((CLOSE_HANDSHAKE_TIMEOUT, "handshake timeout"))
+    );
+}
+
+#[test]
+fn app_errors_become_an_error_event_and_a_close_frame() {
+    let error = AppError {
+        status_code: StatusCode::NOT_FOUND,
+        message: "UnknownTrackError: no track with ID sample9".into(),
+    };
+    assert_eq!(
+        error.to_control_message(),
+        ControlMessage::Error {
+            status: 404,
+            message: "UnknownTrackError: no track with ID sample9".into(),
+        }
+    );
+    let close = error.to_close_frame();
+    assert_eq!(close.code, 4404);
+    assert_eq!(close.reason.as_str(), error.message);
+
+    // the reason is cut to 123 bytes, on a character boundary
+    let error = AppError {
+        status_code: StatusCode::INTERNAL_SERVER_ERROR,
+        message: "é".repeat(100),
+    };
+    let close = error.to_close_frame();
+    assert_eq!(close.code, 4500);
+    assert_eq!(close.reason.as_str(), "é".repeat(61));
+}
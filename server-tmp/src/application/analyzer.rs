@@ -1,4 +1,5 @@
-use crate::{errors::analyzer::AnalyzerError, models::audio::AudioInfo};
+use crate::errors::analyzer::AnalyzerError;
+use protocol::models::audio::AudioInfo;
 
 pub fn wave_analyzer() -> Result<AudioInfo, AnalyzerError> {
     // read wav file
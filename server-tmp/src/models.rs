@@ -1,2 +1 @@
-pub mod audio;
 pub mod shared_state;
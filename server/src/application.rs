@@ -1,2 +1,10 @@
 pub mod analyzer;
+pub mod converter;
+pub mod decoder;
+pub mod encoder;
+pub mod generator;
+pub mod library;
+pub mod live;
+pub mod pacer;
+pub mod station;
 pub mod streamer;
@@ -1,20 +1,89 @@
-use crate::{errors::analyzer::AnalyzerError, models::audio::AudioInfo};
+use crate::{
+    application::{
+        converter::source_converter, decoder::source_opener, generator::generator_opener,
+        live::live_opener,
+    },
+    errors::analyzer::AnalyzerError,
+    models::library::{OpenedTrack, Track, TrackSource},
+};
+use protocol::models::{
+    audio::{AudioInfo, PcmFormat},
+    format::FormatRequest,
+};
 
-pub fn wave_analyzer() -> Result<AudioInfo, AnalyzerError> {
-    // read wav file
-    let reader = hound::WavReader::open("data/sample3.wav")?;
+pub fn wave_analyzer(
+    track: &Track,
+    format_request: &FormatRequest,
+) -> Result<OpenedTrack, AnalyzerError> {
+    // open audio file (any supported container and codec), live input (declared format)
+    // or generator (synthesized at the requested rate)
+    let source = match &track.source {
+        TrackSource::File(path) => source_opener(path)?,
+        TrackSource::Live(input) => live_opener(input)?,
+        TrackSource::Generator(spec) => generator_opener(spec, format_request)?,
+    };
 
     // get headers
-    let spec = reader.spec();
+    let audio_info = source.audio_info().clone();
     tracing::info!(
-        "WAV: {}Hz, {}ch, {}bits",
-        spec.sample_rate,
-        spec.channels,
-        spec.bits_per_sample
+        "Track [{}]: {}Hz, {}ch, {}bits, {}",
+        track.id,
+        audio_info.sample_rate,
+        audio_info.channels,
+        audio_info.bits_per_sample,
+        audio_info.pcm_format
     );
 
-    Ok(AudioInfo {
-        channel: (spec.channels as u32),
-        sample_rate: spec.sample_rate,
+    // negotiate the output format requested by the client
+    let audio_info = format_negotiator(format_request, &audio_info)?;
+    let source = source_converter(source, &audio_info)?;
+
+    Ok(OpenedTrack {
+        id: track.id.clone(),
+        audio_info,
+        source,
+        position: 0,
     })
 }
+
+/// Resolves the format requested by the client against the format of the track.
+pub fn format_negotiator(
+    format_request: &FormatRequest,
+    source: &AudioInfo,
+) -> Result<AudioInfo, AnalyzerError> {
+    // nothing requested: stream the track as is
+    if *format_request == FormatRequest::default() {
+        return Ok(source.clone());
+    }
+
+    let sample_rate = format_request.sample_rate.unwrap_or(source.sample_rate);
+    let channels = format_request.channels.unwrap_or(source.channels);
+    let pcm_format = format_request.pcm_format.unwrap_or(source.pcm_format);
+    let bits_per_sample = match (format_request.bits_per_sample, pcm_format) {
+        (Some(bits_per_sample), _) => bits_per_sample,
+        (None, PcmFormat::Float) => 32,
+        // float -> int without an explicit bit depth defaults to CD quality
+        (None, PcmFormat::Int) if source.pcm_format == PcmFormat::Float => 16,
+        (None, PcmFormat::Int) => source.bits_per_sample,
+    };
+
+    let negotiated = AudioInfo {
+        channels,
+        sample_rate,
+        bits_per_sample,
+        pcm_format,
+    };
+    let is_supported = (8_000..=192_000).contains(&sample_rate)
+        && (1..=8).contains(&channels)
+        && match pcm_format {
+            PcmFormat::Int => (1..=32).contains(&bits_per_sample),
+            PcmFormat::Float => bits_per_sample == 32,
+        };
+    if !is_supported {
+        return Err(AnalyzerError::UnsupportedOutputFormatError(format!(
+            "{}Hz, {}ch, {}bits, {}",
+            sample_rate, channels, bits_per_sample, pcm_format
+        )));
+    }
+    Ok(negotiated)
+}
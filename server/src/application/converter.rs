@@ -0,0 +1,393 @@
+use crate::{
+    errors::{analyzer::AnalyzerError, streamer::StreamerError},
+    models::source::{AudioSource, PcmSamples},
+};
+use protocol::models::audio::{AudioInfo, PcmFormat};
+use rubato::{
+    Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction,
+};
+use std::collections::VecDeque;
+
+// input frames per resampler call
+static RESAMPLER_CHUNK_SIZE: usize = 1024;
+
+/// Wraps `source` so that it produces `target`, or returns it untouched if the formats match.
+pub fn source_converter(
+    source: Box<dyn AudioSource>,
+    target: &AudioInfo,
+) -> Result<Box<dyn AudioSource>, AnalyzerError> {
+    if source.audio_info() == target {
+        return Ok(source);
+    }
+    tracing::info!(
+        "Converting {}Hz, {}ch, {}bits, {} -> {}Hz, {}ch, {}bits, {}",
+        source.audio_info().sample_rate,
+        source.audio_info().channels,
+        source.audio_info().bits_per_sample,
+        source.audio_info().pcm_format,
+        target.sample_rate,
+        target.channels,
+        target.bits_per_sample,
+        target.pcm_format
+    );
+    Ok(Box::new(ConvertedSource::new(source, target.clone())?))
+}
+
+/// Resamples (band-limited sinc interpolation), remixes channels and converts the sample format.
+pub struct ConvertedSource {
+    source: Box<dyn AudioSource>,
+    audio_info: AudioInfo,
+    resampler: Option<SincFixedIn<f32>>,
+    // planar input waiting for a full resampler chunk
+    input: Vec<Vec<f32>>,
+    // interleaved output that did not fit into the previous chunk
+    output: VecDeque<f32>,
+    // resampler output frames to discard (filter delay)
+    skip_frames: usize,
+    // frames read from the source and converted since the start or the last seek
+    source_frames: u64,
+    output_frames: u64,
+    source_finished: bool,
+}
+
+impl ConvertedSource {
+    pub fn new(source: Box<dyn AudioSource>, audio_info: AudioInfo) -> Result<Self, AnalyzerError> {
+        let source_rate = source.audio_info().sample_rate;
+        let resampler = if source_rate == audio_info.sample_rate {
+            None
+        } else {
+            let parameters = SincInterpolationParameters {
+                sinc_len: 128,
+                f_cutoff: 0.95,
+                oversampling_factor: 128,
+                interpolation: SincInterpolationType::Linear,
+                window: WindowFunction::BlackmanHarris2,
+            };
+            Some(SincFixedIn::<f32>::new(
+                audio_info.sample_rate as f64 / source_rate as f64,
+                1.0,
+                parameters,
+                RESAMPLER_CHUNK_SIZE,
+                audio_info.channels as usize,
+            )?)
+        };
+        let skip_frames = resampler
+            .as_ref()
+            .map(|resampler| resampler.output_delay())
+            .unwrap_or(0);
+
+        Ok(ConvertedSource {
+            input: vec![Vec::new(); audio_info.channels as usize],
+            source,
+            audio_info,
+            resampler,
+            output: VecDeque::new(),
+            skip_frames,
+            source_frames: 0,
+            output_frames: 0,
+            source_finished: false,
+        })
+    }
+
+    fn source_to_target_frame(&self, frame: u64) -> u64 {
+        rescale_frame(
+            frame,
+            self.source.audio_info().sample_rate,
+            self.audio_info.sample_rate,
+        )
+    }
+
+    fn target_to_source_frame(&self, frame: u64) -> u64 {
+        rescale_frame(
+            frame,
+            self.audio_info.sample_rate,
+            self.source.audio_info().sample_rate,
+        )
+    }
+
+    /// Reads one chunk from the source and pushes the converted frames to the output buffer.
+    fn convert_next_chunk(&mut self) -> Result<(), StreamerError> {
+        let samples = self.source.read_frames(RESAMPLER_CHUNK_SIZE)?;
+        if samples.is_empty() {
+            self.source_finished = true;
+        }
+        let source_channels = self.source.audio_info().channels as usize;
+        self.source_frames += (samples.len() / source_channels) as u64;
+        let queued = self.output.len();
+        let samples = normalize(&samples, self.source.audio_info());
+        remix(
+            &samples,
+            self.source.audio_info().channels as usize,
+            &mut self.input,
+        );
+
+        let Some(resampler) = self.resampler.as_mut() else {
+            let planar = std::mem::replace(
+                &mut self.input,
+                vec![Vec::new(); self.audio_info.channels as usize],
+            );
+            interleave(&planar, &mut self.output, 0);
+            return Ok(());
+        };
+
+        // feed full chunks, then flush the remainder and the filter tail at the end of the source
+        while self.input[0].len() >= resampler.input_frames_next() {
+            let frames = resampler.input_frames_next();
+            let chunk: Vec<Vec<f32>> = self
+                .input
+                .iter_mut()
+                .map(|channel| channel.drain(..frames).collect())
+                .collect();
+            let planar = resampler.process(&chunk, None)?;
+            self.skip_frames = interleave(&planar, &mut self.output, self.skip_frames);
+        }
+        if self.source_finished {
+            let remainder = std::mem::replace(
+                &mut self.input,
+                vec![Vec::new(); self.audio_info.channels as usize],
+            );
+            if !remainder[0].is_empty() {
+                let planar = resampler.process_partial(Some(&remainder), None)?;
+                self.skip_frames = interleave(&planar, &mut self.output, self.skip_frames);
+            }
+            let planar = resampler.process_partial::<Vec<f32>>(None, None)?;
+            self.skip_frames = interleave(&planar, &mut self.output, self.skip_frames);
+        }
+
+        let channels = self.audio_info.channels as usize;
+        self.output_frames += ((self.output.len() - queued) / channels) as u64;
+        if self.source_finished {
+            // the flush pads the last chunk, the output ends where the source ends
+            let expected = self.source_to_target_frame(self.source_frames);
+            let excess = self.output_frames.saturating_sub(expected) as usize;
+            let excess = excess.min(self.output.len() / channels);
+            self.output.truncate(self.output.len() - excess * channels);
+            self.output_frames -= excess as u64;
+        }
+        Ok(())
+    }
+}
+
+impl AudioSource for ConvertedSource {
+    fn audio_info(&self) -> &AudioInfo {
+        &self.audio_info
+    }
+
+    fn total_frames(&self) -> Option<u64> {
+        self.source
+            .total_frames()
+            .map(|frames| self.source_to_target_frame(frames))
+    }
+
+    fn is_live(&self) -> bool {
+        self.source.is_live()
+    }
+
+    fn read_frames(&mut self, frames: usize) -> Result<PcmSamples, StreamerError> {
+        let samples = frames * self.audio_info.channels as usize;
+        while self.output.len() < samples && !self.source_finished {
+            self.convert_next_chunk()?;
+        }
+
+        let samples = samples.min(self.output.len());
+        let output = self.output.drain(..samples);
+        Ok(match self.audio_info.pcm_format {
+            PcmFormat::Float => PcmSamples::Float(output.collect()),
+            PcmFormat::Int => {
+                let max = ((1i64 << (self.audio_info.bits_per_sample - 1)) - 1) as f32;
+                let min = -(1i64 << (self.audio_info.bits_per_sample - 1)) as f32;
+                PcmSamples::Int(
+                    output
+                        .map(|sample| (sample * (max + 1.0)).round().clamp(min, max) as i32)
+                        .collect(),
+                )
+            }
+        })
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), StreamerError> {
+        let source_frame = self.target_to_source_frame(frame);
+        self.source.seek(source_frame)?;
+        for channel in self.input.iter_mut() {
+            channel.clear();
+        }
+        self.output.clear();
+        self.source_frames = 0;
+        self.output_frames = 0;
+        if let Some(resampler) = self.resampler.as_mut() {
+            resampler.reset();
+            self.skip_frames = resampler.output_delay();
+        }
+        self.source_finished = false;
+        Ok(())
+    }
+}
+
+/// The frame at `to_rate` of `frame` at `from_rate`, clamped to `u64::MAX` for positions no stream reaches.
+fn rescale_frame(frame: u64, from_rate: u32, to_rate: u32) -> u64 {
+    let frame = frame as u128 * to_rate as u128 / from_rate as u128;
+    u64::try_from(frame).unwrap_or(u64::MAX)
+}
+
+/// Converts samples to interleaved `f32` in `[-1.0, 1.0)`.
+fn normalize(samples: &PcmSamples, audio_info: &AudioInfo) -> Vec<f32> {
+    match samples {
+        PcmSamples::Float(samples) => samples.clone(),
+        PcmSamples::Int(samples) => {
+            let scale = (1i64 << (audio_info.bits_per_sample - 1)) as f32;
+            samples
+                .iter()
+                .map(|&sample| sample as f32 / scale)
+                .collect()
+        }
+    }
+}
+
+/// Deinterleaves `samples` and maps the source channels onto `output.len()` channels.
+///
+/// - same layout: copied
+/// - down-mix: every output channel is the average of the source channels `c, c + n, c + 2n, ...`
+///   (e.g. stereo -> mono averages L and R)
+/// - up-mix: output channel `c` repeats source channel `c % source_channels`
+///   (e.g. mono -> stereo duplicates the signal)
+fn remix(samples: &[f32], source_channels: usize, output: &mut [Vec<f32>]) {
+    let target_channels = output.len();
+    for frame in samples.chunks_exact(source_channels) {
+        if target_channels >= source_channels {
+            for (channel, output) in output.iter_mut().enumerate() {
+                output.push(frame[channel % source_channels]);
+            }
+        } else {
+            for (channel, output) in output.iter_mut().enumerate() {
+                let sources = frame.iter().skip(channel).step_by(target_channels);
+                let count = sources.clone().count() as f32;
+                output.push(sources.sum::<f32>() / count);
+            }
+        }
+    }
+}
+
+/// Interleaves `planar` into `output`, dropping the first `skip_frames` frames.
+/// Returns the number of frames that are still to be skipped.
+fn interleave(planar: &[Vec<f32>], output: &mut VecDeque<f32>, skip_frames: usize) -> usize {
+    let frames = planar.first().map(Vec::len).unwrap_or(0);
+    let skipped = skip_frames.min(frames);
+    for frame in skipped..frames {
+        output.extend(planar.iter().map(|channel| channel[frame]));
+    }
+    skip_frames - skipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Interleaved 16-bit samples in memory.
+    struct MemorySource {
+        audio_info: AudioInfo,
+        samples: Vec<i32>,
+        position: usize,
+    }
+
+    impl MemorySource {
+        fn boxed(sample_rate: u32, channels: u16, samples: Vec<i32>) -> Box<dyn AudioSource> {
+            Box::new(MemorySource {
+                audio_info: AudioInfo {
+                    channels,
+                    sample_rate,
+                    bits_per_sample: 16,
+                    pcm_format: PcmFormat::Int,
+                },
+                samples,
+                position: 0,
+            })
+        }
+    }
+
+    impl AudioSource for MemorySource {
+        fn audio_info(&self) -> &AudioInfo {
+            &self.audio_info
+        }
+
+        fn total_frames(&self) -> Option<u64> {
+            Some((self.samples.len() / self.audio_info.channels as usize) as u64)
+        }
+
+        fn read_frames(&mut self, frames: usize) -> Result<PcmSamples, StreamerError> {
+            let end = (self.position + frames * self.audio_info.channels as usize)
+                .min(self.samples.len());
+            let samples = self.samples[self.position..end].to_vec();
+            self.position = end;
+            Ok(PcmSamples::Int(samples))
+        }
+
+        fn seek(&mut self, frame: u64) -> Result<(), StreamerError> {
+            let sample = frame.saturating_mul(self.audio_info.channels as u64);
+            self.position = sample.min(self.samples.len() as u64) as usize;
+            Ok(())
+        }
+    }
+
+    fn target(sample_rate: u32, channels: u16) -> AudioInfo {
+        AudioInfo {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            pcm_format: PcmFormat::Int,
+        }
+    }
+
+    fn read_all(source: &mut dyn AudioSource) -> Vec<i32> {
+        let mut samples = Vec::new();
+        loop {
+            match source.read_frames(1000).unwrap() {
+                PcmSamples::Int(chunk) if chunk.is_empty() => return samples,
+                PcmSamples::Int(chunk) => samples.extend(chunk),
+                PcmSamples::Float(_) => panic!("expected integer samples"),
+            }
+        }
+    }
+
+    #[test]
+    fn channels_are_remixed() {
+        // mono -> stereo duplicates the signal
+        let mono = MemorySource::boxed(8000, 1, vec![100, -200, 300]);
+        let mut stereo = source_converter(mono, &target(8000, 2)).unwrap();
+        assert_eq!(
+            read_all(stereo.as_mut()),
+            vec![100, 100, -200, -200, 300, 300]
+        );
+
+        // stereo -> mono averages L and R
+        let stereo = MemorySource::boxed(8000, 2, vec![100, 300, -200, 200, 1000, 0]);
+        let mut mono = source_converter(stereo, &target(8000, 1)).unwrap();
+        assert_eq!(read_all(mono.as_mut()), vec![200, 0, 500]);
+    }
+
+    #[test]
+    fn resampling_keeps_the_duration() {
+        let samples = (0..8000).map(|n| (n % 200 - 100) * 100).collect();
+        let source = MemorySource::boxed(8000, 1, samples);
+        let mut resampled = source_converter(source, &target(16000, 2)).unwrap();
+        assert_eq!(resampled.total_frames(), Some(16000));
+
+        // the filter delay is skipped and the padding of the last chunk dropped
+        assert_eq!(read_all(resampled.as_mut()).len(), 2 * 16000);
+
+        // again from the middle
+        resampled.seek(4000).unwrap();
+        assert_eq!(read_all(resampled.as_mut()).len(), 2 * 12000);
+    }
+
+    #[test]
+    fn seeks_are_rescaled_without_overflowing() {
+        assert_eq!(rescale_frame(48000, 48000, 44100), 44100);
+        assert_eq!(rescale_frame(u64::MAX, 44100, 48000), u64::MAX);
+        assert_eq!(rescale_frame(u64::MAX, 2, 1), u64::MAX / 2);
+
+        let source = MemorySource::boxed(8000, 1, vec![0; 8000]);
+        let mut resampled = source_converter(source, &target(16000, 1)).unwrap();
+        resampled.seek(u64::MAX).unwrap();
+        assert!(read_all(resampled.as_mut()).is_empty());
+    }
+}
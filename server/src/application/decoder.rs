@@ -0,0 +1,431 @@
+use crate::{
+    errors::{analyzer::AnalyzerError, streamer::StreamerError},
+    models::source::{AudioSource, PcmSamples},
+};
+use protocol::models::audio::{AudioInfo, PcmFormat};
+use std::{collections::VecDeque, fs::File, io::BufReader, path::Path};
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{
+        CODEC_TYPE_NULL, CODEC_TYPE_PCM_F32BE, CODEC_TYPE_PCM_F32LE, CODEC_TYPE_PCM_F64BE,
+        CODEC_TYPE_PCM_F64LE, CodecType, Decoder, DecoderOptions,
+    },
+    errors::Error as SymphoniaError,
+    formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+    sample::SampleFormat,
+    units::{Time, TimeBase},
+};
+
+// PCM codecs that report a bit depth but carry floating point samples
+static FLOAT_CODECS: [CodecType; 4] = [
+    CODEC_TYPE_PCM_F32LE,
+    CODEC_TYPE_PCM_F32BE,
+    CODEC_TYPE_PCM_F64LE,
+    CODEC_TYPE_PCM_F64BE,
+];
+
+pub fn source_opener(path: &Path) -> Result<Box<dyn AudioSource>, AnalyzerError> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+
+    // WAV is read by hound, everything else is decoded by symphonia
+    match extension.as_deref() {
+        Some("wav") => Ok(Box::new(WavSource::open(path)?)),
+        _ => Ok(Box::new(SymphoniaSource::open(path)?)),
+    }
+}
+
+//* WAV (hound) *//
+pub struct WavSource {
+    audio_info: AudioInfo,
+    reader: hound::WavReader<BufReader<File>>,
+}
+
+impl WavSource {
+    pub fn open(path: &Path) -> Result<Self, AnalyzerError> {
+        let reader = hound::WavReader::open(path)?;
+        Ok(WavSource {
+            audio_info: reader.spec().into(),
+            reader,
+        })
+    }
+}
+
+impl AudioSource for WavSource {
+    fn audio_info(&self) -> &AudioInfo {
+        &self.audio_info
+    }
+
+    fn total_frames(&self) -> Option<u64> {
+        Some(self.reader.duration() as u64)
+    }
+
+    fn read_frames(&mut self, frames: usize) -> Result<PcmSamples, StreamerError> {
+        let samples = frames * self.audio_info.channels as usize;
+        match (self.audio_info.pcm_format, self.audio_info.bits_per_sample) {
+            (PcmFormat::Int, 1..=32) => Ok(PcmSamples::Int(
+                self.reader
+                    .samples::<i32>()
+                    .take(samples)
+                    .collect::<Result<_, _>>()?,
+            )),
+            (PcmFormat::Float, 32) => Ok(PcmSamples::Float(
+                self.reader
+                    .samples::<f32>()
+                    .take(samples)
+                    .collect::<Result<_, _>>()?,
+            )),
+            (pcm_format, bits_per_sample) => Err(StreamerError::UnsupportedSampleFormatError(
+                pcm_format,
+                bits_per_sample,
+            )),
+        }
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), StreamerError> {
+        let frame = frame.min(self.reader.duration() as u64);
+        self.reader.seek(frame as u32)?;
+        Ok(())
+    }
+}
+
+//* FLAC, MP3, Ogg Vorbis, AAC, ... (symphonia) *//
+pub struct SymphoniaSource {
+    audio_info: AudioInfo,
+    total_frames: Option<u64>,
+    time_base: Option<TimeBase>,
+    track_id: u32,
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    // decoded samples that did not fit into the previous chunk
+    pending: VecDeque<i32>,
+    pending_float: VecDeque<f32>,
+    // frames to discard after an accurate seek landed before the requested position
+    skip_frames: u64,
+    finished: bool,
+}
+
+impl SymphoniaSource {
+    pub fn open(path: &Path) -> Result<Self, AnalyzerError> {
+        let file = File::open(path)?;
+        let media_source_stream = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+            hint.with_extension(extension);
+        }
+
+        let probed = symphonia::default::get_probe()
+            .format(
+                &hint,
+                media_source_stream,
+                &FormatOptions {
+                    enable_gapless: true,
+                    ..Default::default()
+                },
+                &MetadataOptions::default(),
+            )
+            .map_err(|e| match e {
+                SymphoniaError::Unsupported(format) => {
+                    AnalyzerError::UnsupportedCodecError(format!("unsupported container: {format}"))
+                }
+                e => AnalyzerError::SymphoniaError(e),
+            })?;
+        let format = probed.format;
+
+        // select the first audio track
+        let track = format
+            .tracks()
+            .iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or(AnalyzerError::UnsupportedCodecError(
+                "no audio track found".into(),
+            ))?;
+        let codec_params = track.codec_params.clone();
+        let track_id = track.id;
+
+        let decoder = symphonia::default::get_codecs()
+            .make(&codec_params, &DecoderOptions::default())
+            .map_err(|e| match e {
+                SymphoniaError::Unsupported(_) => AnalyzerError::UnsupportedCodecError(
+                    symphonia::default::get_codecs()
+                        .get_codec(codec_params.codec)
+                        .map(|codec| codec.short_name.to_string())
+                        .unwrap_or_else(|| format!("{:?}", codec_params.codec)),
+                ),
+                e => AnalyzerError::SymphoniaError(e),
+            })?;
+
+        let sample_rate = codec_params
+            .sample_rate
+            .ok_or(AnalyzerError::UnsupportedCodecError(
+                "unknown sample rate".into(),
+            ))?;
+        let channels = codec_params
+            .channels
+            .map(|channels| channels.count() as u16)
+            .ok_or(AnalyzerError::UnsupportedCodecError(
+                "unknown channel layout".into(),
+            ))?;
+        // integer codecs (FLAC, ALAC, PCM, ...) report their bit depth and are sent as integers,
+        // float PCM and lossy codecs (MP3, Vorbis, AAC, ...) are sent as 32-bit floats
+        let (pcm_format, bits_per_sample) =
+            match (codec_params.sample_format, codec_params.bits_per_sample) {
+                _ if FLOAT_CODECS.contains(&codec_params.codec) => (PcmFormat::Float, 32),
+                (Some(SampleFormat::F32 | SampleFormat::F64), _) => (PcmFormat::Float, 32),
+                (_, Some(bits_per_sample @ 1..=32)) => (PcmFormat::Int, bits_per_sample as u16),
+                _ => (PcmFormat::Float, 32),
+            };
+
+        Ok(SymphoniaSource {
+            audio_info: AudioInfo {
+                channels,
+                sample_rate,
+                bits_per_sample,
+                pcm_format,
+            },
+            total_frames: codec_params.n_frames,
+            time_base: codec_params.time_base,
+            track_id,
+            format,
+            decoder,
+            pending: VecDeque::new(),
+            pending_float: VecDeque::new(),
+            skip_frames: 0,
+            finished: false,
+        })
+    }
+
+    fn pending_samples(&self) -> usize {
+        match self.audio_info.pcm_format {
+            PcmFormat::Int => self.pending.len(),
+            PcmFormat::Float => self.pending_float.len(),
+        }
+    }
+
+    /// Decodes the next packet of the selected track into the pending buffer.
+    fn decode_next_packet(&mut self) -> Result<(), StreamerError> {
+        let packet = match self.format.next_packet() {
+            Ok(packet) => packet,
+            // end of stream
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                self.finished = true;
+                return Ok(());
+            }
+            Err(SymphoniaError::ResetRequired) => {
+                tracing::warn!("Decoder reset required, ending the stream");
+                self.finished = true;
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != self.track_id {
+            return Ok(());
+        }
+
+        let decoded = match self.decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // a corrupt packet is skipped, like every symphonia player does
+            Err(SymphoniaError::DecodeError(e)) => {
+                tracing::warn!("Skipping undecodable packet: {}", e);
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let channels = self.audio_info.channels as usize;
+        let spec = *decoded.spec();
+        let skip = (self.skip_frames.min(decoded.frames() as u64) as usize) * channels;
+        self.skip_frames -= (skip / channels) as u64;
+        match self.audio_info.pcm_format {
+            PcmFormat::Int => {
+                let mut buffer = SampleBuffer::<i32>::new(decoded.capacity() as u64, spec);
+                buffer.copy_interleaved_ref(decoded);
+                // symphonia scales integers to the full i32 range, shift back to the native range
+                let shift = 32 - self.audio_info.bits_per_sample as u32;
+                self.pending.extend(
+                    buffer.samples()[skip..]
+                        .iter()
+                        .map(|sample| sample >> shift),
+                );
+            }
+            PcmFormat::Float => {
+                let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+                buffer.copy_interleaved_ref(decoded);
+                self.pending_float.extend(&buffer.samples()[skip..]);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AudioSource for SymphoniaSource {
+    fn audio_info(&self) -> &AudioInfo {
+        &self.audio_info
+    }
+
+    fn total_frames(&self) -> Option<u64> {
+        self.total_frames
+    }
+
+    fn read_frames(&mut self, frames: usize) -> Result<PcmSamples, StreamerError> {
+        let samples = frames * self.audio_info.channels as usize;
+        while self.pending_samples() < samples && !self.finished {
+            self.decode_next_packet()?;
+        }
+
+        let samples = samples.min(self.pending_samples());
+        match self.audio_info.pcm_format {
+            PcmFormat::Int => Ok(PcmSamples::Int(self.pending.drain(..samples).collect())),
+            PcmFormat::Float => Ok(PcmSamples::Float(
+                self.pending_float.drain(..samples).collect(),
+            )),
+        }
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), StreamerError> {
+        let sample_rate = self.audio_info.sample_rate;
+        let seeked_to = match self.format.seek(
+            SeekMode::Accurate,
+            SeekTo::Time {
+                time: Time::from(frame as f64 / sample_rate as f64),
+                track_id: Some(self.track_id),
+            },
+        ) {
+            Ok(seeked_to) => seeked_to,
+            // seeking past the end finishes the stream
+            Err(SymphoniaError::SeekError(e)) => {
+                tracing::warn!("Seek to frame {} failed: {:?}", frame, e);
+                self.pending.clear();
+                self.pending_float.clear();
+                self.finished = true;
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        // the demuxer lands on a packet boundary at or before the requested position
+        let skip_ts = seeked_to.required_ts.saturating_sub(seeked_to.actual_ts);
+        self.skip_frames = match self.time_base {
+            Some(time_base) => {
+                let time = time_base.calc_time(skip_ts);
+                ((time.seconds as f64 + time.frac) * sample_rate as f64).round() as u64
+            }
+            None => skip_ts,
+        };
+        self.decoder.reset();
+        self.pending.clear();
+        self.pending_float.clear();
+        self.finished = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static FIXTURE_SAMPLE_RATE: u32 = 8000;
+    static FIXTURE_BLOCK_FRAMES: usize = 4096;
+    static FIXTURE_FRAMES: usize = 4 * FIXTURE_BLOCK_FRAMES - 1000;
+
+    /// A mono 16-bit FLAC whose sample `n` is `n`, with uncompressed (verbatim) blocks.
+    fn flac_fixture() -> Vec<u8> {
+        fn crc8(bytes: &[u8]) -> u8 {
+            bytes.iter().fold(0, |crc, byte| {
+                (0..8).fold(crc ^ byte, |crc, _| {
+                    if crc & 0x80 != 0 {
+                        (crc << 1) ^ 0x07
+                    } else {
+                        crc << 1
+                    }
+                })
+            })
+        }
+        fn crc16(bytes: &[u8]) -> u16 {
+            bytes.iter().fold(0, |crc, &byte| {
+                (0..8).fold(crc ^ (byte as u16) << 8, |crc, _| {
+                    if crc & 0x8000 != 0 {
+                        (crc << 1) ^ 0x8005
+                    } else {
+                        crc << 1
+                    }
+                })
+            })
+        }
+
+        let mut flac = b"fLaC".to_vec();
+        // the last metadata block (STREAMINFO), 34 bytes
+        flac.extend([0x80, 0, 0, 34]);
+        flac.extend((FIXTURE_BLOCK_FRAMES as u16).to_be_bytes());
+        flac.extend((FIXTURE_BLOCK_FRAMES as u16).to_be_bytes());
+        flac.extend([0; 6]);
+        // sample rate (20 bits), channels - 1 (3 bits), bits per sample - 1 (5 bits), frames (36 bits)
+        let info = (FIXTURE_SAMPLE_RATE as u64) << 44 | 15 << 36 | FIXTURE_FRAMES as u64;
+        flac.extend(info.to_be_bytes());
+        // no MD5
+        flac.extend([0; 16]);
+
+        let samples: Vec<i16> = (0..FIXTURE_FRAMES as i16).collect();
+        for (number, block) in samples.chunks(FIXTURE_BLOCK_FRAMES).enumerate() {
+            // fixed block size, explicit 16-bit block size, rate of STREAMINFO, mono, 16 bits
+            let mut frame = vec![0xFF, 0xF8, 0x70, 0x08, number as u8];
+            frame.extend((block.len() as u16 - 1).to_be_bytes());
+            frame.push(crc8(&frame));
+            // a verbatim subframe
+            frame.push(0x02);
+            frame.extend(block.iter().flat_map(|sample| sample.to_be_bytes()));
+            frame.extend(crc16(&frame).to_be_bytes());
+            flac.extend(frame);
+        }
+        flac
+    }
+
+    fn read_ints(source: &mut SymphoniaSource, frames: usize) -> Vec<i32> {
+        match source.read_frames(frames).unwrap() {
+            PcmSamples::Int(samples) => samples,
+            PcmSamples::Float(_) => panic!("expected integer samples"),
+        }
+    }
+
+    #[test]
+    fn symphonia_sources_decode_and_seek_to_the_exact_frame() {
+        let path = std::env::temp_dir().join(format!("decoder-{}.flac", std::process::id()));
+        std::fs::write(&path, flac_fixture()).unwrap();
+        let mut source = SymphoniaSource::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            source.audio_info(),
+            &AudioInfo {
+                channels: 1,
+                sample_rate: FIXTURE_SAMPLE_RATE,
+                bits_per_sample: 16,
+                pcm_format: PcmFormat::Int,
+            }
+        );
+        assert_eq!(source.total_frames(), Some(FIXTURE_FRAMES as u64));
+        // chunks do not follow the blocks of the file
+        assert_eq!(read_ints(&mut source, 5000), (0..5000).collect::<Vec<_>>());
+        assert_eq!(read_ints(&mut source, 3), vec![5000, 5001, 5002]);
+
+        // the seek lands on the start of a block, the frames before the position are skipped
+        source.seek(10000).unwrap();
+        assert!(source.skip_frames > 0);
+        assert_eq!(read_ints(&mut source, 4), vec![10000, 10001, 10002, 10003]);
+        source.seek(4096).unwrap();
+        assert_eq!(read_ints(&mut source, 2), vec![4096, 4097]);
+        source.seek(1).unwrap();
+        assert_eq!(read_ints(&mut source, 2), vec![1, 2]);
+
+        // the last frames, then the end of the stream
+        source.seek(FIXTURE_FRAMES as u64 - 2).unwrap();
+        let last = FIXTURE_FRAMES as i32;
+        assert_eq!(read_ints(&mut source, 10), vec![last - 2, last - 1]);
+        assert!(read_ints(&mut source, 10).is_empty());
+    }
+}
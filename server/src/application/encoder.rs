@@ -0,0 +1,166 @@
+use crate::{errors::streamer::StreamerError, models::source::PcmSamples};
+use protocol::models::{
+    audio::{AudioInfo, PcmFormat},
+    frame::PcmFrameHeader,
+};
+
+/// Encodes samples as little-endian PCM in the format advertised by `audio_info`.
+///
+/// - int: signed two's complement, `ceil(bits_per_sample / 8)` bytes per sample
+///   (8-bit WAV samples are unsigned on disk and are converted to signed by hound)
+/// - float: IEEE 754 single precision, 4 bytes per sample
+pub fn pcm_encoder(samples: &PcmSamples, audio_info: &AudioInfo) -> Result<Vec<u8>, StreamerError> {
+    let bytes_per_sample = audio_info.bytes_per_sample();
+    let mut buf = Vec::with_capacity(samples.len() * bytes_per_sample);
+
+    match (samples, audio_info.pcm_format, audio_info.bits_per_sample) {
+        (PcmSamples::Int(samples), PcmFormat::Int, 1..=32) => {
+            for sample in samples {
+                buf.extend_from_slice(&sample.to_le_bytes()[..bytes_per_sample]);
+            }
+        }
+        (PcmSamples::Float(samples), PcmFormat::Float, 32) => {
+            for sample in samples {
+                buf.extend_from_slice(&sample.to_le_bytes());
+            }
+        }
+        (_, pcm_format, bits_per_sample) => {
+            return Err(StreamerError::UnsupportedSampleFormatError(
+                pcm_format,
+                bits_per_sample,
+            ));
+        }
+    }
+
+    Ok(buf)
+}
+
+/// Encodes one binary message: the frame header followed by the PCM of `samples`.
+///
+/// `header.frames` is filled in from `samples`.
+pub fn frame_encoder(
+    header: &mut PcmFrameHeader,
+    samples: &PcmSamples,
+    audio_info: &AudioInfo,
+) -> Result<Vec<u8>, StreamerError> {
+    header.frames = (samples.len() / audio_info.channels as usize) as u32;
+    let payload = pcm_encoder(samples, audio_info)?;
+    let mut buf = Vec::with_capacity(PcmFrameHeader::SIZE + payload.len());
+    buf.extend_from_slice(&header.to_bytes());
+    buf.extend(payload);
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::decoder::source_opener;
+
+    /// Writes a stereo 8 kHz WAV of `samples` and reads it back through the source.
+    fn wav_roundtrip<S: hound::Sample + Copy>(
+        bits_per_sample: u16,
+        sample_format: hound::SampleFormat,
+        samples: &[S],
+    ) -> (AudioInfo, Vec<u8>) {
+        let path = std::env::temp_dir().join(format!(
+            "encoder-{}-{bits_per_sample}-{sample_format:?}.wav",
+            std::process::id()
+        ));
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 8000,
+            bits_per_sample,
+            sample_format,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for sample in samples {
+            writer.write_sample(*sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        let mut source = source_opener(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let audio_info = source.audio_info().clone();
+        let frames = samples.len() / 2;
+        assert_eq!(source.total_frames(), Some(frames as u64));
+        let samples = source.read_frames(1024).unwrap();
+        assert!(source.read_frames(1024).unwrap().is_empty());
+
+        let mut header = PcmFrameHeader::new(&audio_info, 1, 0);
+        let frame = frame_encoder(&mut header, &samples, &audio_info).unwrap();
+        assert_eq!(header.frames as usize, frames);
+        assert_eq!(frame[..PcmFrameHeader::SIZE], header.to_bytes());
+        (audio_info, frame[PcmFrameHeader::SIZE..].to_vec())
+    }
+
+    fn audio_info(bits_per_sample: u16, pcm_format: PcmFormat) -> AudioInfo {
+        AudioInfo {
+            channels: 2,
+            sample_rate: 8000,
+            bits_per_sample,
+            pcm_format,
+        }
+    }
+
+    #[test]
+    fn unsigned_8_bit_wavs_are_sent_signed() {
+        let (info, payload) = wav_roundtrip(8, hound::SampleFormat::Int, &[-128i8, -1, 0, 127]);
+        assert_eq!(info, audio_info(8, PcmFormat::Int));
+        assert_eq!(payload, [0x80, 0xFF, 0x00, 0x7F]);
+    }
+
+    #[test]
+    fn int_wavs_keep_their_sample_width() {
+        let (info, payload) =
+            wav_roundtrip(16, hound::SampleFormat::Int, &[i16::MIN, -1, 1, i16::MAX]);
+        assert_eq!(info, audio_info(16, PcmFormat::Int));
+        assert_eq!(payload, [0x00, 0x80, 0xFF, 0xFF, 0x01, 0x00, 0xFF, 0x7F]);
+
+        let samples = [-0x80_0000, -1, 0x12_3456, 0x7F_FFFF];
+        let (info, payload) = wav_roundtrip(24, hound::SampleFormat::Int, &samples);
+        assert_eq!(info, audio_info(24, PcmFormat::Int));
+        assert_eq!(
+            payload,
+            [
+                0x00, 0x00, 0x80, 0xFF, 0xFF, 0xFF, 0x56, 0x34, 0x12, 0xFF, 0xFF, 0x7F
+            ]
+        );
+
+        let samples = [i32::MIN, -1, 0x1234_5678, i32::MAX];
+        let (info, payload) = wav_roundtrip(32, hound::SampleFormat::Int, &samples);
+        assert_eq!(info, audio_info(32, PcmFormat::Int));
+        assert_eq!(
+            payload,
+            [
+                0x00, 0x00, 0x00, 0x80, 0xFF, 0xFF, 0xFF, 0xFF, 0x78, 0x56, 0x34, 0x12, 0xFF, 0xFF,
+                0xFF, 0x7F
+            ]
+        );
+    }
+
+    #[test]
+    fn float_wavs_are_sent_as_ieee_754() {
+        let samples = [-1.0f32, 0.5, 0.0, 1.0];
+        let (info, payload) = wav_roundtrip(32, hound::SampleFormat::Float, &samples);
+        assert_eq!(info, audio_info(32, PcmFormat::Float));
+        assert_eq!(
+            payload,
+            [
+                0x00, 0x00, 0x80, 0xBF, 0x00, 0x00, 0x00, 0x3F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x80, 0x3F
+            ]
+        );
+    }
+
+    #[test]
+    fn samples_in_another_format_are_rejected() {
+        let samples = PcmSamples::Float(vec![0.0; 2]);
+        assert!(matches!(
+            pcm_encoder(&samples, &audio_info(16, PcmFormat::Int)),
+            Err(StreamerError::UnsupportedSampleFormatError(
+                PcmFormat::Int,
+                16
+            ))
+        ));
+    }
+}
@@ -0,0 +1,258 @@
+use crate::{
+    application::analyzer::format_negotiator,
+    errors::{analyzer::AnalyzerError, streamer::StreamerError},
+    models::{
+        generator::{GeneratorSpec, Signal},
+        source::{AudioSource, PcmSamples},
+    },
+};
+use protocol::models::{
+    audio::{AudioInfo, PcmFormat},
+    format::FormatRequest,
+};
+use std::f64::consts::PI;
+
+// format of a generator unless the client requests another rate or channel count
+static GENERATOR_SAMPLE_RATE: u32 = 44100;
+static GENERATOR_CHANNELS: u16 = 2;
+// click: a decaying sine burst, higher and louder on the downbeat
+static CLICK_LENGTH_SECS: f64 = 0.02;
+static CLICK_DECAY_SECS: f64 = 0.004;
+static CLICK_HZ: f64 = 1000.0;
+static ACCENT_HZ: f64 = 1500.0;
+static CLICK_AMPLITUDE: f64 = 0.6;
+static ACCENT_AMPLITUDE: f64 = 0.9;
+// sweep and noise stay well below full scale
+static SIGNAL_AMPLITUDE: f64 = 0.5;
+
+pub fn generator_opener(
+    spec: &GeneratorSpec,
+    format_request: &FormatRequest,
+) -> Result<Box<dyn AudioSource>, AnalyzerError> {
+    // synthesize at the requested rate and channel count, so only the sample format is converted
+    let audio_info = format_negotiator(
+        format_request,
+        &AudioInfo {
+            channels: GENERATOR_CHANNELS,
+            sample_rate: GENERATOR_SAMPLE_RATE,
+            bits_per_sample: 32,
+            pcm_format: PcmFormat::Float,
+        },
+    )?;
+    Ok(Box::new(GeneratorSource {
+        spec: *spec,
+        audio_info: AudioInfo {
+            bits_per_sample: 32,
+            pcm_format: PcmFormat::Float,
+            ..audio_info
+        },
+        total_frames: (spec.duration_secs * audio_info.sample_rate as f64).round() as u64,
+        position: 0,
+        pink: PinkFilter::default(),
+    }))
+}
+
+/// Synthesizes a test signal. Every sample is a function of its frame index
+/// (except for the pink noise filter state), so seeking is exact.
+pub struct GeneratorSource {
+    spec: GeneratorSpec,
+    audio_info: AudioInfo,
+    total_frames: u64,
+    position: u64,
+    pink: PinkFilter,
+}
+
+impl GeneratorSource {
+    fn sample(&mut self, frame: u64) -> f32 {
+        let sample_rate = self.audio_info.sample_rate as f64;
+        let time = frame as f64 / sample_rate;
+        let sample = match self.spec.signal {
+            Signal::Click {
+                bpm, beats_per_bar, ..
+            } => {
+                let beat_secs = 60.0 / bpm;
+                let beat = (time / beat_secs).floor();
+                let offset = time - beat * beat_secs;
+                let (hz, amplitude) = if (beat as u64).is_multiple_of(beats_per_bar as u64) {
+                    (ACCENT_HZ, ACCENT_AMPLITUDE)
+                } else {
+                    (CLICK_HZ, CLICK_AMPLITUDE)
+                };
+                if offset < CLICK_LENGTH_SECS {
+                    amplitude * (2.0 * PI * hz * offset).sin() * (-offset / CLICK_DECAY_SECS).exp()
+                } else {
+                    0.0
+                }
+            }
+            Signal::Sweep { start_hz, end_hz } => {
+                let duration = self.spec.duration_secs;
+                let phase = if start_hz == end_hz {
+                    2.0 * PI * start_hz * time
+                } else {
+                    // integral of f(t) = start_hz * (end_hz / start_hz)^(t / duration)
+                    let rate = (end_hz / start_hz).ln();
+                    2.0 * PI * start_hz * duration / rate * ((time / duration * rate).exp() - 1.0)
+                };
+                SIGNAL_AMPLITUDE * phase.sin()
+            }
+            Signal::WhiteNoise => SIGNAL_AMPLITUDE * white_noise(frame),
+            Signal::PinkNoise => SIGNAL_AMPLITUDE * self.pink.next(white_noise(frame)),
+            Signal::Silence => 0.0,
+        };
+        sample as f32
+    }
+}
+
+impl AudioSource for GeneratorSource {
+    fn audio_info(&self) -> &AudioInfo {
+        &self.audio_info
+    }
+
+    fn total_frames(&self) -> Option<u64> {
+        Some(self.total_frames)
+    }
+
+    fn read_frames(&mut self, frames: usize) -> Result<PcmSamples, StreamerError> {
+        let end = self
+            .position
+            .saturating_add(frames as u64)
+            .min(self.total_frames);
+        let channels = self.audio_info.channels as usize;
+        let mut samples = Vec::with_capacity((end - self.position) as usize * channels);
+        for frame in self.position..end {
+            // every channel carries the same signal
+            let sample = self.sample(frame);
+            samples.extend(std::iter::repeat_n(sample, channels));
+        }
+        self.position = end;
+        Ok(PcmSamples::Float(samples))
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), StreamerError> {
+        self.position = frame.min(self.total_frames);
+        self.pink = PinkFilter::default();
+        Ok(())
+    }
+}
+
+/// Uniform noise in `[-1.0, 1.0)` derived from the frame index (SplitMix64).
+fn white_noise(frame: u64) -> f64 {
+    let mut z = frame.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+}
+
+/// Paul Kellett's economy pink noise filter (-3 dB/octave above ~40 Hz).
+#[derive(Debug, Default)]
+struct PinkFilter {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+}
+
+impl PinkFilter {
+    fn next(&mut self, white: f64) -> f64 {
+        self.b0 = 0.99765 * self.b0 + white * 0.0990460;
+        self.b1 = 0.96300 * self.b1 + white * 0.2965164;
+        self.b2 = 0.57000 * self.b2 + white * 1.0526913;
+        // the filter has a gain of about 4.5
+        (self.b0 + self.b1 + self.b2 + white * 0.1848) / 4.5
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(id: &str) -> Box<dyn AudioSource> {
+        let spec = GeneratorSpec::try_from(id).unwrap();
+        generator_opener(&spec, &FormatRequest::default()).unwrap()
+    }
+
+    /// The first channel of the whole track.
+    fn read_all(source: &mut dyn AudioSource) -> Vec<f32> {
+        let channels = source.audio_info().channels as usize;
+        let mut mono = Vec::new();
+        loop {
+            let PcmSamples::Float(samples) = source.read_frames(4096).unwrap() else {
+                panic!("generators produce float samples");
+            };
+            if samples.is_empty() {
+                return mono;
+            }
+            mono.extend(samples.iter().step_by(channels));
+        }
+    }
+
+    /// The frames where the signal crosses zero upwards.
+    fn upward_crossings(samples: &[f32]) -> Vec<usize> {
+        (1..samples.len())
+            .filter(|&n| samples[n - 1] < 0.0 && samples[n] >= 0.0)
+            .collect()
+    }
+
+    #[test]
+    fn tracks_have_the_frames_of_their_duration() {
+        let mut source = open("gen:white@2.5");
+        assert_eq!(source.total_frames(), Some(110250));
+        assert_eq!(read_all(source.as_mut()).len(), 110250);
+
+        // seeking past the end (e.g. to a clamped position) ends the track without overflowing
+        source.seek(u64::MAX).unwrap();
+        assert!(source.read_frames(usize::MAX).unwrap().is_empty());
+    }
+
+    #[test]
+    fn clicks_start_on_every_beat_with_an_accent_per_bar() {
+        let sample_rate = GENERATOR_SAMPLE_RATE as f64;
+        let click_frames = (CLICK_LENGTH_SECS * sample_rate) as usize;
+        for (bpm, beats_per_bar) in [(120.0, 4), (90.0, 3), (128.0, 4)] {
+            let id = format!("gen:click:{bpm}:{beats_per_bar}/4@8");
+            let samples = read_all(open(&id).as_mut());
+            let peak = |frames: &[f32]| frames.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+
+            // beat k starts at k * 60 / bpm seconds, the signal is silent between two clicks
+            let beat_frames = 60.0 / bpm * sample_rate;
+            let beats = (samples.len() as f64 / beat_frames).ceil() as usize;
+            for beat in 0..beats {
+                let start = (beat as f64 * beat_frames).ceil() as usize;
+                let next = ((beat + 1) as f64 * beat_frames).ceil() as usize;
+                let click = &samples[start..start + click_frames];
+                let gap = &samples[start + click_frames + 1..next.min(samples.len())];
+                assert!(peak(click) > 0.1, "{id}: no click on beat {beat}");
+                assert!(peak(gap) == 0.0, "{id}: sound after beat {beat}");
+                // the downbeats are louder
+                let accented = peak(click) > CLICK_AMPLITUDE as f32;
+                assert_eq!(accented, beat % beats_per_bar == 0, "{id}: beat {beat}");
+            }
+        }
+    }
+
+    #[test]
+    fn silence_is_all_zeros() {
+        let samples = read_all(open("gen:silence@1").as_mut());
+        assert_eq!(samples.len(), GENERATOR_SAMPLE_RATE as usize);
+        assert!(samples.iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn sweeps_run_from_their_start_to_their_end_frequency() {
+        let samples = read_all(open("gen:sweep:100:1000@2").as_mut());
+        let sample_rate = GENERATOR_SAMPLE_RATE as f64;
+        let crossings = upward_crossings(&samples);
+        // the period of the first and the last cycle
+        let start_hz = sample_rate / (crossings[1] - crossings[0]) as f64;
+        let end_hz =
+            sample_rate / (crossings[crossings.len() - 1] - crossings[crossings.len() - 2]) as f64;
+        assert!(
+            (start_hz - 100.0).abs() / 100.0 < 0.02,
+            "starts at {start_hz} Hz"
+        );
+        assert!(
+            (end_hz - 1000.0).abs() / 1000.0 < 0.03,
+            "ends at {end_hz} Hz"
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use crate::models::{
+    library::{Track, TrackLibrary, TrackSource},
+    live::LiveInput,
+};
+use std::path::Path;
+
+// supported audio file extensions
+static SUPPORTED_EXTENSIONS: [&str; 8] = ["wav", "flac", "mp3", "ogg", "oga", "m4a", "mp4", "aac"];
+
+pub fn library_scanner(
+    library_dir: &Path,
+    live_inputs: Vec<LiveInput>,
+) -> Result<TrackLibrary, std::io::Error> {
+    let mut library = TrackLibrary::default();
+    scan_directory(library_dir, library_dir, &mut library)?;
+    for input in live_inputs {
+        let id = input.track_id();
+        library.tracks.insert(
+            id.clone(),
+            Track {
+                id,
+                source: TrackSource::Live(input),
+            },
+        );
+    }
+
+    tracing::info!(
+        "Library: {} track(s) found in {}",
+        library.tracks.len(),
+        library_dir.display()
+    );
+    for track in library.tracks.values() {
+        match &track.source {
+            TrackSource::File(path) => tracing::info!("Track: {} -> {}", track.id, path.display()),
+            TrackSource::Live(input) => tracing::info!("Track: {} -> {:?}", track.id, input.kind),
+            TrackSource::Generator(spec) => tracing::info!("Track: {} -> {:?}", track.id, spec),
+        }
+    }
+
+    Ok(library)
+}
+
+fn scan_directory(
+    root: &Path,
+    directory: &Path,
+    library: &mut TrackLibrary,
+) -> Result<(), std::io::Error> {
+    // sorted, so the same file wins a duplicate track ID on every scan
+    let mut paths = std::fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            scan_directory(root, &path, library)?;
+            continue;
+        }
+
+        let is_supported = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                SUPPORTED_EXTENSIONS
+                    .iter()
+                    .any(|supported| extension.eq_ignore_ascii_case(supported))
+            });
+        if !is_supported {
+            continue;
+        }
+
+        // track id: relative path without extension (e.g. data/live/set1.wav -> live/set1)
+        let Ok(relative_path) = path
+            .with_extension("")
+            .strip_prefix(root)
+            .map(Path::to_path_buf)
+        else {
+            continue;
+        };
+        let id = relative_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        if let Some(Track {
+            source: TrackSource::File(duplicate),
+            ..
+        }) = library.tracks.get(&id)
+        {
+            tracing::warn!(
+                "Track ID {} is already used by {}, skipping {}",
+                id,
+                duplicate.display(),
+                path.display()
+            );
+            continue;
+        }
+        library.tracks.insert(
+            id.clone(),
+            Track {
+                id,
+                source: TrackSource::File(path),
+            },
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A library directory holding `files` (relative paths), removed when dropped.
+    struct LibraryDir(PathBuf);
+
+    impl LibraryDir {
+        fn new(name: &str, files: &[&str]) -> Self {
+            let root = std::env::temp_dir().join(format!("library-{name}-{}", std::process::id()));
+            for file in files {
+                let path = root.join(file);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, []).unwrap();
+            }
+            LibraryDir(root)
+        }
+    }
+
+    impl Drop for LibraryDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn file_tracks(library: &TrackLibrary) -> Vec<(&str, PathBuf)> {
+        library
+            .tracks
+            .values()
+            .filter_map(|track| match &track.source {
+                TrackSource::File(path) => Some((track.id.as_str(), path.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn track_ids_are_the_relative_paths_of_the_audio_files() {
+        let dir = LibraryDir::new(
+            "ids",
+            &[
+                "sample3.wav",
+                "Loud.FLAC",
+                "live/set1.mp3",
+                "live/b-sides/intro.ogg",
+                "cover.jpg",
+                "notes.txt",
+                "live/README",
+            ],
+        );
+        let library = library_scanner(&dir.0, Vec::new()).unwrap();
+        assert_eq!(
+            file_tracks(&library),
+            vec![
+                ("Loud", dir.0.join("Loud.FLAC")),
+                ("live/b-sides/intro", dir.0.join("live/b-sides/intro.ogg")),
+                ("live/set1", dir.0.join("live/set1.mp3")),
+                ("sample3", dir.0.join("sample3.wav")),
+            ]
+        );
+        for (id, track) in &library.tracks {
+            assert_eq!(&track.id, id);
+        }
+
+        // the IDs do not depend on the scan
+        let again = library_scanner(&dir.0, Vec::new()).unwrap();
+        assert_eq!(file_tracks(&again), file_tracks(&library));
+    }
+
+    #[test]
+    fn the_first_file_in_path_order_keeps_a_duplicate_id() {
+        let dir = LibraryDir::new(
+            "duplicates",
+            &["song.wav", "song.flac", "set/a.mp3", "set/a.m4a"],
+        );
+        let library = library_scanner(&dir.0, Vec::new()).unwrap();
+        assert_eq!(
+            file_tracks(&library),
+            vec![
+                ("set/a", dir.0.join("set/a.m4a")),
+                ("song", dir.0.join("song.flac")),
+            ]
+        );
+    }
+
+    #[test]
+    fn live_inputs_are_listed_next_to_the_files() {
+        let dir = LibraryDir::new("live", &["live/deck.wav"]);
+        let inputs = LiveInput::parse_list("deck=stdin@48000/2/16/int").unwrap();
+        let library = library_scanner(&dir.0, inputs).unwrap();
+        let ids: Vec<&str> = library.tracks.keys().map(String::as_str).collect();
+        assert_eq!(ids, vec!["live/deck", "live:deck"]);
+        assert!(matches!(
+            library.tracks["live:deck"].source,
+            TrackSource::Live(_)
+        ));
+    }
+
+    #[test]
+    fn a_missing_directory_is_an_error() {
+        let dir = LibraryDir::new("missing", &[]);
+        assert!(library_scanner(&dir.0, Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+use crate::{
+    errors::{analyzer::AnalyzerError, streamer::StreamerError},
+    models::{
+        live::{LiveInput, LiveInputKind, LiveSender, LiveTap},
+        source::{AudioSource, PcmSamples},
+    },
+};
+use protocol::models::audio::{AudioInfo, PcmFormat};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    sync::{
+        Arc, MutexGuard,
+        atomic::Ordering,
+        mpsc::{Receiver, RecvTimeoutError},
+    },
+    time::{Duration, Instant},
+};
+
+// frames read from the input at once
+static LIVE_READ_FRAMES: usize = 1024;
+// chunks buffered between the reader thread and the stream, a full buffer blocks the producer
+static LIVE_BUFFER_CHUNKS: usize = 64;
+// no data for this long ends the stream
+static LIVE_STALL_TIMEOUT: Duration = Duration::from_secs(5);
+// how often a tailed file is checked for new data
+static TAIL_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub fn live_opener(input: &LiveInput) -> Result<Box<dyn AudioSource>, AnalyzerError> {
+    let (sender, receiver) = std::sync::mpsc::sync_channel(LIVE_BUFFER_CHUNKS);
+    let session_id = input.tap.sessions.fetch_add(1, Ordering::Relaxed);
+    let start_reader = {
+        // a pipe delivers every byte to one reader only, so an input serves one session at a time
+        let mut session = lock_session(&input.tap);
+        if session.is_some() {
+            return Err(AnalyzerError::LiveInputBusyError(input.track_id()));
+        }
+        *session = Some((session_id, sender));
+        // under the lock, so the session never attaches to a reader that is stopping
+        !input.tap.reading.swap(true, Ordering::AcqRel)
+    };
+
+    // the reader thread is started by the first session, or again once the input ended
+    if start_reader && let Err(e) = live_reader_launcher(input) {
+        input.tap.reading.store(false, Ordering::Release);
+        detach(&input.tap, session_id);
+        return Err(e.into());
+    }
+
+    Ok(Box::new(LiveSource {
+        track_id: input.track_id(),
+        audio_info: input.audio_info.clone(),
+        receiver,
+        tap: Arc::clone(&input.tap),
+        session_id,
+        pending: VecDeque::new(),
+        finished: false,
+    }))
+}
+
+fn live_reader_launcher(input: &LiveInput) -> std::io::Result<()> {
+    let frame_bytes = input.audio_info.bytes_per_sample() * input.audio_info.channels as usize;
+    // a tailed file is opened right away so that a wrong path fails the handshake,
+    // a FIFO blocks on open until a writer connects and is opened by the reader thread
+    let tail = match &input.kind {
+        LiveInputKind::Tail(path) => Some(open_tail(path, frame_bytes)?),
+        _ => None,
+    };
+
+    let kind = input.kind.clone();
+    let tap = Arc::clone(&input.tap);
+    std::thread::spawn(move || {
+        let result = match (kind, tail) {
+            (_, Some(file)) => live_reader(TailReader { file }, frame_bytes, &tap),
+            // a FIFO ends with each writer, the reader then waits for the next one
+            (LiveInputKind::Fifo(path), None) => loop {
+                match File::open(&path) {
+                    Ok(file) => match live_reader(file, frame_bytes, &tap) {
+                        Ok(()) => continue,
+                        Err(e) => break Err(e),
+                    },
+                    Err(e) => break Err(e),
+                }
+            },
+            _ => live_reader(std::io::stdin(), frame_bytes, &tap),
+        };
+        // the session attached now gets the end of the input, a later one starts a new reader
+        let session = {
+            let mut session = lock_session(&tap);
+            tap.reading.store(false, Ordering::Release);
+            session.take()
+        };
+        if let (Err(e), Some((_, sender))) = (result, session) {
+            let _ = sender.send(Err(e));
+        }
+    });
+    Ok(())
+}
+
+/// Opens `path` and moves to its current end, aligned to a whole frame.
+fn open_tail(path: &std::path::Path, frame_bytes: usize) -> std::io::Result<File> {
+    let mut file = File::open(path)?;
+    let end = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(end - end % frame_bytes as u64))?;
+    Ok(file)
+}
+
+/// Reads the input on a dedicated thread, since stdin and pipes only offer blocking reads.
+///
+/// Only whole frames are delivered, so that what is read while no session is attached
+/// can be dropped without misaligning the next session. Returns at the end of the input.
+fn live_reader(mut reader: impl Read, frame_bytes: usize, tap: &LiveTap) -> std::io::Result<()> {
+    let mut buf = vec![0u8; LIVE_READ_FRAMES * frame_bytes];
+    // bytes of a frame that is not complete yet
+    let mut partial = Vec::new();
+    loop {
+        let read = match reader.read(&mut buf) {
+            // end of input (the writer closed the pipe), which ends the stream of the session
+            Ok(0) => {
+                lock_session(tap).take();
+                return Ok(());
+            }
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        partial.extend_from_slice(&buf[..read]);
+        let whole = partial.len() - partial.len() % frame_bytes;
+        if whole == 0 {
+            continue;
+        }
+        let chunk: Vec<u8> = partial.drain(..whole).collect();
+
+        // sent outside the lock, a full buffer blocks the reader but not the session detaching
+        let sender = lock_session(tap).as_ref().map(|(_, sender)| sender.clone());
+        if let Some(sender) = sender {
+            // fails when the session is gone, the chunk is then dropped
+            let _ = sender.send(Ok(chunk));
+        }
+    }
+}
+
+fn lock_session(tap: &LiveTap) -> MutexGuard<'_, Option<(u64, LiveSender)>> {
+    tap.session.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Frees the input for the next session, unless `session_id` was already detached.
+fn detach(tap: &LiveTap, session_id: u64) {
+    let mut session = lock_session(tap);
+    if session.as_ref().is_some_and(|(id, _)| *id == session_id) {
+        *session = None;
+    }
+}
+
+/// Follows a file that is still being written, like `tail -f`.
+struct TailReader {
+    file: File,
+}
+
+impl Read for TailReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            let read = self.file.read(buf)?;
+            if read > 0 {
+                return Ok(read);
+            }
+            std::thread::sleep(TAIL_POLL_INTERVAL);
+        }
+    }
+}
+
+/// Raw interleaved little-endian PCM from a live input, in the declared format.
+///
+/// The input runs in real time, so `read_frames` waits for the requested frames and
+/// fails with `LiveInputStalledError` if nothing arrives for `LIVE_STALL_TIMEOUT`.
+pub struct LiveSource {
+    track_id: String,
+    audio_info: AudioInfo,
+    receiver: Receiver<std::io::Result<Vec<u8>>>,
+    tap: Arc<LiveTap>,
+    session_id: u64,
+    // received bytes that do not fill the requested frames yet
+    pending: VecDeque<u8>,
+    finished: bool,
+}
+
+impl LiveSource {
+    fn frame_bytes(&self) -> usize {
+        self.audio_info.bytes_per_sample() * self.audio_info.channels as usize
+    }
+}
+
+impl AudioSource for LiveSource {
+    fn audio_info(&self) -> &AudioInfo {
+        &self.audio_info
+    }
+
+    fn total_frames(&self) -> Option<u64> {
+        None
+    }
+
+    fn is_live(&self) -> bool {
+        true
+    }
+
+    fn read_frames(&mut self, frames: usize) -> Result<PcmSamples, StreamerError> {
+        let bytes = frames * self.frame_bytes();
+        let mut last_data = Instant::now();
+        while self.pending.len() < bytes && !self.finished {
+            let timeout = LIVE_STALL_TIMEOUT.saturating_sub(last_data.elapsed());
+            match self.receiver.recv_timeout(timeout) {
+                Ok(chunk) => {
+                    self.pending.extend(chunk?);
+                    last_data = Instant::now();
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(StreamerError::LiveInputStalledError(self.track_id.clone()));
+                }
+                Err(RecvTimeoutError::Disconnected) => self.finished = true,
+            }
+        }
+
+        // a trailing partial frame at the end of the input is dropped
+        let bytes = bytes.min(self.pending.len() / self.frame_bytes() * self.frame_bytes());
+        let buf: Vec<u8> = self.pending.drain(..bytes).collect();
+        Ok(pcm_decoder(&buf, &self.audio_info))
+    }
+
+    fn seek(&mut self, _frame: u64) -> Result<(), StreamerError> {
+        Err(StreamerError::InvalidCommandError(format!(
+            "live input {} cannot seek",
+            self.track_id
+        )))
+    }
+}
+
+impl Drop for LiveSource {
+    fn drop(&mut self) {
+        // the reader thread may be blocked in a read, the input is freed without waiting for it
+        detach(&self.tap, self.session_id);
+    }
+}
+
+/// The inverse of `pcm_encoder`: little-endian bytes to samples in their native range.
+fn pcm_decoder(buf: &[u8], audio_info: &AudioInfo) -> PcmSamples {
+    match audio_info.pcm_format {
+        PcmFormat::Float => PcmSamples::Float(
+            buf.chunks_exact(4)
+                .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect(),
+        ),
+        PcmFormat::Int => {
+            let bytes_per_sample = audio_info.bytes_per_sample();
+            let shift = 8 * (4 - bytes_per_sample) as u32;
+            PcmSamples::Int(
+                buf.chunks_exact(bytes_per_sample)
+                    .map(|bytes| {
+                        // place the bytes at the top of an i32 and shift back to sign-extend
+                        let mut sample = [0u8; 4];
+                        sample[4 - bytes_per_sample..].copy_from_slice(bytes);
+                        i32::from_le_bytes(sample) >> shift
+                    })
+                    .collect(),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn a_live_input_is_opened_again_after_its_session_ends() {
+        let path = std::env::temp_dir().join(format!("live-reopen-{}.pcm", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let status = std::process::Command::new("mkfifo").arg(&path).status();
+        assert!(status.is_ok_and(|status| status.success()));
+        let inputs =
+            LiveInput::parse_list(&format!("deck=fifo:{}@8000/1/16/int", path.display())).unwrap();
+        let input = &inputs[0];
+
+        // no writer connects, the reader thread stays blocked in the open of the FIFO
+        let first = live_opener(input).unwrap();
+        assert!(matches!(
+            live_opener(input),
+            Err(AnalyzerError::LiveInputBusyError(_))
+        ));
+        drop(first);
+
+        let mut second = live_opener(input).unwrap();
+        let writer_path = path.clone();
+        let writer = std::thread::spawn(move || {
+            let mut fifo = std::fs::OpenOptions::new()
+                .write(true)
+                .open(writer_path)
+                .unwrap();
+            // three frames and a half
+            fifo.write_all(&[1, 0, 2, 0, 3, 0, 4]).unwrap();
+        });
+        writer.join().unwrap();
+
+        // the reader thread of the first session delivers to the second one
+        match second.read_frames(4).unwrap() {
+            PcmSamples::Int(samples) => assert_eq!(samples, vec![1, 2, 3]),
+            PcmSamples::Float(_) => panic!("expected integer samples"),
+        }
+        drop(second);
+        assert!(live_opener(input).is_ok());
+        let _ = std::fs::remove_file(&path);
+    }
+}
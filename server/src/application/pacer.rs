@@ -0,0 +1,150 @@
+use tokio::time::{Duration, Instant};
+
+// log the drift statistic every N seconds of streamed audio
+static DRIFT_LOG_INTERVAL_SECS: u64 = 10;
+// if a chunk is later than this, re-anchor instead of bursting to catch up
+static MAX_CATCH_UP: Duration = Duration::from_secs(1);
+
+/// Real-time pacing anchored to the instant the stream (re)started.
+///
+/// Chunk `n` is due at `anchor + frames_sent / sample_rate`, so the time spent in
+/// `socket.send` and scheduler jitter do not accumulate over a long track.
+/// This is the `MissedTickBehavior::Burst` policy of `tokio::time::interval`, except that
+/// a stall longer than `MAX_CATCH_UP` re-anchors the clock (like `MissedTickBehavior::Delay`)
+/// so a multi-second hiccup does not flood the middle-server with queued chunks.
+pub struct Pacer {
+    sample_rate: u32,
+    anchor: Instant,
+    frames_since_anchor: u64,
+    frames_sent: u64,
+    next_log_frame: u64,
+    drift: DriftStats,
+}
+
+/// Lateness of each chunk relative to its deadline (positive = sent late).
+#[derive(Debug, Default)]
+pub struct DriftStats {
+    pub chunks: u64,
+    pub last: Duration,
+    pub max: Duration,
+    pub total: Duration,
+    pub reanchors: u64,
+}
+
+impl DriftStats {
+    pub fn mean(&self) -> Duration {
+        if self.chunks == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(self.total.as_secs_f64() / self.chunks as f64)
+    }
+}
+
+impl Pacer {
+    pub fn new(sample_rate: u32) -> Self {
+        Pacer {
+            sample_rate,
+            anchor: Instant::now(),
+            frames_since_anchor: 0,
+            frames_sent: 0,
+            next_log_frame: DRIFT_LOG_INTERVAL_SECS * sample_rate as u64,
+            drift: DriftStats::default(),
+        }
+    }
+
+    /// Re-anchor the clock to now, e.g. when resuming after a pause.
+    pub fn restart(&mut self) {
+        self.anchor = Instant::now();
+        self.frames_since_anchor = 0;
+    }
+
+    /// The instant the next chunk is due.
+    pub fn deadline(&self) -> Instant {
+        self.anchor
+            + Duration::from_secs_f64(self.frames_since_anchor as f64 / self.sample_rate as f64)
+    }
+
+    /// Record that a chunk of `frames` frames, due at `deadline`, has been sent.
+    pub fn record(&mut self, deadline: Instant, frames: u64) {
+        let lateness = Instant::now().saturating_duration_since(deadline);
+        self.drift.chunks += 1;
+        self.drift.last = lateness;
+        self.drift.max = self.drift.max.max(lateness);
+        self.drift.total += lateness;
+
+        if lateness > MAX_CATCH_UP {
+            tracing::warn!(
+                "Stream fell {:?} behind real time, re-anchoring the clock",
+                lateness
+            );
+            self.drift.reanchors += 1;
+            self.restart();
+        }
+        self.frames_since_anchor += frames;
+        self.frames_sent += frames;
+
+        if self.frames_sent >= self.next_log_frame {
+            self.next_log_frame += DRIFT_LOG_INTERVAL_SECS * self.sample_rate as u64;
+            self.log_drift();
+        }
+    }
+
+    pub fn log_drift(&self) {
+        tracing::info!(
+            "Drift: {:.1}s streamed, {} chunks, last {:?}, mean {:?}, max {:?}, {} re-anchor(s)",
+            self.frames_sent as f64 / self.sample_rate as f64,
+            self.drift.chunks,
+            self.drift.last,
+            self.drift.mean(),
+            self.drift.max,
+            self.drift.reanchors
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn deadlines_advance_with_the_frames_sent() {
+        let start = Instant::now();
+        let mut pacer = Pacer::new(48000);
+        assert_eq!(pacer.deadline(), start);
+
+        for chunk in 1..=4 {
+            let deadline = pacer.deadline();
+            tokio::time::sleep_until(deadline).await;
+            pacer.record(deadline, 12000);
+            assert_eq!(pacer.deadline(), start + Duration::from_millis(250 * chunk));
+        }
+        // a chunk sent on time is not late
+        assert_eq!(pacer.drift.max, Duration::ZERO);
+
+        // a late chunk is caught up with, the deadlines keep their anchor
+        let deadline = pacer.deadline();
+        tokio::time::sleep_until(deadline + Duration::from_millis(500)).await;
+        pacer.record(deadline, 12000);
+        assert_eq!(pacer.drift.last, Duration::from_millis(500));
+        assert_eq!(pacer.drift.reanchors, 0);
+        assert_eq!(pacer.deadline(), start + Duration::from_millis(1250));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_stall_past_the_catch_up_limit_re_anchors_the_clock() {
+        let mut pacer = Pacer::new(48000);
+        let deadline = pacer.deadline();
+        pacer.record(deadline, 48000);
+
+        let deadline = pacer.deadline();
+        tokio::time::advance(Duration::from_secs(1) + MAX_CATCH_UP * 3).await;
+        let now = Instant::now();
+        pacer.record(deadline, 24000);
+
+        assert_eq!(pacer.drift.reanchors, 1);
+        assert_eq!(pacer.drift.max, MAX_CATCH_UP * 3);
+        // the late chunk is the first one of the new anchor
+        assert_eq!(pacer.deadline(), now + Duration::from_millis(500));
+        assert_eq!(pacer.frames_sent, 72000);
+    }
+}
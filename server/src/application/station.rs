@@ -0,0 +1,416 @@
+use crate::{
+    application::{
+        analyzer::wave_analyzer,
+        encoder::frame_encoder,
+        pacer::Pacer,
+        streamer::{chunk_feeder, live_chunk, next_stream_id, presentation_timestamp, send_close},
+    },
+    errors::{root::RootError, streamer::StreamerError},
+    models::{
+        library::{OpenedTrack, TrackLibrary},
+        station::{LagPolicy, Station, StationConfig, Stations},
+        transport::StreamEnd,
+    },
+};
+use axum::{
+    body::Bytes,
+    extract::ws::{Message, WebSocket, close_code},
+};
+use protocol::models::{
+    format::FormatRequest,
+    frame::PcmFrameHeader,
+    keepalive::{Keepalive, Liveness},
+    transport::TransportCommand,
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+// chunks kept for listeners that fall behind (~1.5 s at 44.1 kHz with 1024-frame chunks)
+static STATION_BUFFER_CHUNKS: usize = 64;
+
+/// Opens the track of every station and puts it on air.
+pub fn station_launcher(
+    configs: Vec<StationConfig>,
+    library: &TrackLibrary,
+    lag_policy: LagPolicy,
+) -> Result<Stations, RootError> {
+    let mut stations = Stations::new();
+    for config in configs {
+        let track = library.get(&config.track_id).ok_or_else(|| {
+            RootError::InvalidStationError(format!(
+                "station {}: no track with ID {}",
+                config.name, config.track_id
+            ))
+        })?;
+        // stations stream the track as is, listeners cannot request another format
+        let track = wave_analyzer(&track, &FormatRequest::default())?;
+        let (sender, _) = broadcast::channel(STATION_BUFFER_CHUNKS);
+        stations.insert(
+            config.name.clone(),
+            Station {
+                name: config.name.clone(),
+                track_id: config.track_id,
+                audio_info: track.audio_info.clone(),
+                sender: sender.downgrade(),
+                lag_policy,
+            },
+        );
+
+        let name = config.name;
+        tokio::spawn(async move {
+            if let Err(error) = station_broadcaster(name.clone(), track, sender).await {
+                tracing::error!("Station [{}] error: {:?}", name, error);
+            }
+        });
+    }
+    Ok(stations)
+}
+
+/// Plays `track` in real time on repeat and broadcasts every chunk, with or without listeners.
+///
+/// A live input ends the station when the input ends; closing the channel tells the listeners.
+pub async fn station_broadcaster(
+    name: String,
+    track: OpenedTrack,
+    sender: broadcast::Sender<Bytes>,
+) -> Result<(), StreamerError> {
+    let OpenedTrack {
+        id,
+        audio_info,
+        source,
+        ..
+    } = track;
+    tracing::info!("Station [{}] on air: {}", name, id);
+    let frames_per_chunk: usize = 1024;
+    let (mut source, mut live_chunks) = chunk_feeder(source, frames_per_chunk);
+    let mut pacer = Pacer::new(audio_info.sample_rate);
+
+    // all listeners see the same stream ID and sequence numbers, so a lagging one sees the gap
+    let mut header = PcmFrameHeader::new(&audio_info, next_stream_id(), 0);
+    let mut position: u64 = 0;
+
+    loop {
+        tokio::time::sleep_until(pacer.deadline()).await;
+        let deadline = pacer.deadline();
+        let samples = match source.as_mut() {
+            Some(source) => source.read_frames(frames_per_chunk)?,
+            None => live_chunk(&mut live_chunks).await?,
+        };
+        if samples.is_empty() {
+            let Some(source) = source.as_mut() else {
+                break;
+            };
+            tracing::info!("Station [{}] restarts {}", name, id);
+            source.seek(0)?;
+            position = 0;
+            continue;
+        }
+        let frames_read = (samples.len() / audio_info.channels as usize) as u64;
+        header.first_frame = position;
+        header.timestamp_us = presentation_timestamp(deadline);
+        position += frames_read;
+        let buf = frame_encoder(&mut header, &samples, &audio_info)?;
+        header.sequence = header.sequence.wrapping_add(1);
+        // an error only means that nobody is listening right now
+        let _ = sender.send(buf.into());
+        pacer.record(deadline, frames_read);
+    }
+
+    pacer.log_drift();
+    tracing::info!("Station [{}] off air", name);
+    Ok(())
+}
+
+/// Forwards the chunks of `station` to one listener, starting at the live position.
+///
+/// Seek and loop are not available since all listeners share the same position;
+/// pause stops receiving and resume joins the station again at the live position.
+pub async fn station_streamer(
+    socket: &mut WebSocket,
+    station: &Station,
+    keepalive: &mut Keepalive,
+) -> Result<StreamEnd, StreamerError> {
+    tracing::info!("Listening to station: {}", station.name);
+    let Some(receiver) = station.subscribe() else {
+        send_close(socket, close_code::NORMAL, "station ended").await?;
+        return Ok(StreamEnd::Closed);
+    };
+    let mut receiver = Some(receiver);
+
+    let stream_end = loop {
+        tokio::select! {
+            message = socket.recv() => {
+                let Some(message) = message else {
+                    break StreamEnd::Closed;
+                };
+                let message = message.map_err(StreamerError::AxumError)?;
+                keepalive.seen();
+                match message {
+                    Message::Text(text) => {
+                        let command = TransportCommand::try_from(text.as_str())?;
+                        tracing::info!("Transport command [station:{}]: {:?}", station.name, command);
+                        match command {
+                            TransportCommand::Pause => receiver = None,
+                            TransportCommand::Resume if receiver.is_none() => {
+                                receiver = station.subscribe();
+                                if receiver.is_none() {
+                                    send_close(socket, close_code::NORMAL, "station ended").await?;
+                                    break StreamEnd::Closed;
+                                }
+                            }
+                            TransportCommand::Resume => {}
+                            TransportCommand::Stop => break StreamEnd::Stopped,
+                            TransportCommand::Seek { .. }
+                            | TransportCommand::Loop { .. }
+                            | TransportCommand::LoopOff => {
+                                return Err(StreamerError::InvalidCommandError(format!(
+                                    "station {} cannot seek or loop",
+                                    station.name
+                                )));
+                            }
+                        }
+                    }
+                    Message::Close(close) => {
+                        tracing::info!("Client disconnected while streaming: {:?}", close);
+                        break StreamEnd::Closed;
+                    }
+                    Message::Ping(_) | Message::Pong(_) => {}
+                    _ => {
+                        tracing::error!("Received unsupported message type while streaming");
+                        return Err(StreamerError::UnexpectedMessageTypeError);
+                    }
+                }
+            }
+            chunk = recv_chunk(&mut receiver) => {
+                match chunk {
+                    Ok(buf) => {
+                        if keepalive.send(socket, Message::Binary(buf)).await? == Liveness::Dead {
+                            break StreamEnd::Closed;
+                        }
+                    }
+                    Err(RecvError::Lagged(chunks)) => {
+                        tracing::warn!(
+                            "Listener of station {} lagged by {} chunk(s), policy: {:?}",
+                            station.name,
+                            chunks,
+                            station.lag_policy
+                        );
+                        if lag_resolver(station.lag_policy, &mut receiver) == Liveness::Dead {
+                            send_close(socket, close_code::AGAIN, "listener too slow").await?;
+                            break StreamEnd::Closed;
+                        }
+                    }
+                    Err(RecvError::Closed) => {
+                        send_close(socket, close_code::NORMAL, "station ended").await?;
+                        break StreamEnd::Closed;
+                    }
+                }
+            }
+            event = keepalive.tick() => {
+                if keepalive.respond(socket, event).await? == Liveness::Dead {
+                    break StreamEnd::Closed;
+                }
+            }
+        }
+    };
+
+    Ok(stream_end)
+}
+
+/// Applies the lag policy to a listener that missed chunks, `Dead` if it is disconnected.
+fn lag_resolver(
+    lag_policy: LagPolicy,
+    receiver: &mut Option<broadcast::Receiver<Bytes>>,
+) -> Liveness {
+    match lag_policy {
+        // the receiver already moved to the oldest chunk still buffered
+        LagPolicy::Skip => Liveness::Alive,
+        LagPolicy::Resync => {
+            *receiver = receiver.as_ref().map(|receiver| receiver.resubscribe());
+            Liveness::Alive
+        }
+        LagPolicy::Disconnect => Liveness::Dead,
+    }
+}
+
+/// Receives the next chunk, or waits forever while paused.
+async fn recv_chunk(receiver: &mut Option<broadcast::Receiver<Bytes>>) -> Result<Bytes, RecvError> {
+    match receiver {
+        Some(receiver) => receiver.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        errors::streamer::StreamerError,
+        models::source::{AudioSource, PcmSamples},
+    };
+    use axum::{Router, extract::WebSocketUpgrade, routing::get};
+    use futures_util::StreamExt;
+    use protocol::models::{
+        audio::{AudioInfo, PcmFormat},
+        keepalive::KeepaliveConfig,
+    };
+    use tokio_tungstenite::tungstenite;
+
+    /// A listener that missed the first 6 of 10 chunks of a station buffering 4.
+    fn lagged_listener() -> (broadcast::Sender<Bytes>, Option<broadcast::Receiver<Bytes>>) {
+        let (sender, receiver) = broadcast::channel(4);
+        for chunk in 0..10u8 {
+            sender.send(Bytes::from(vec![chunk])).unwrap();
+        }
+        (sender, Some(receiver))
+    }
+
+    async fn lag(receiver: &mut Option<broadcast::Receiver<Bytes>>) -> u64 {
+        match recv_chunk(receiver).await {
+            Err(RecvError::Lagged(chunks)) => chunks,
+            chunk => panic!("expected a lag, got {chunk:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn skipping_listeners_continue_with_the_oldest_buffered_chunk() {
+        let (_sender, mut receiver) = lagged_listener();
+        assert_eq!(lag(&mut receiver).await, 6);
+        assert_eq!(
+            lag_resolver(LagPolicy::Skip, &mut receiver),
+            Liveness::Alive
+        );
+        for chunk in 6..10u8 {
+            assert_eq!(recv_chunk(&mut receiver).await.unwrap(), vec![chunk]);
+        }
+    }
+
+    #[tokio::test]
+    async fn resyncing_listeners_continue_at_the_live_position() {
+        let (sender, mut receiver) = lagged_listener();
+        assert_eq!(lag(&mut receiver).await, 6);
+        assert_eq!(
+            lag_resolver(LagPolicy::Resync, &mut receiver),
+            Liveness::Alive
+        );
+        sender.send(Bytes::from(vec![10])).unwrap();
+        assert_eq!(recv_chunk(&mut receiver).await.unwrap(), vec![10]);
+        assert_eq!(receiver.unwrap().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn lagging_listeners_are_disconnected() {
+        let (_sender, mut receiver) = lagged_listener();
+        assert_eq!(lag(&mut receiver).await, 6);
+        assert_eq!(
+            lag_resolver(LagPolicy::Disconnect, &mut receiver),
+            Liveness::Dead
+        );
+    }
+
+    /// A live input that delivers `chunks` chunks of silence, then ends.
+    struct EndingInput {
+        audio_info: AudioInfo,
+        chunks: usize,
+    }
+
+    impl AudioSource for EndingInput {
+        fn audio_info(&self) -> &AudioInfo {
+            &self.audio_info
+        }
+
+        fn total_frames(&self) -> Option<u64> {
+            None
+        }
+
+        fn read_frames(&mut self, frames: usize) -> Result<PcmSamples, StreamerError> {
+            if self.chunks == 0 {
+                return Ok(PcmSamples::Int(Vec::new()));
+            }
+            self.chunks -= 1;
+            Ok(PcmSamples::Int(vec![
+                0;
+                frames
+                    * self.audio_info.channels as usize
+            ]))
+        }
+
+        fn seek(&mut self, _frame: u64) -> Result<(), StreamerError> {
+            Ok(())
+        }
+
+        fn is_live(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn listeners_are_closed_when_the_station_ends() {
+        let audio_info = AudioInfo {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            pcm_format: PcmFormat::Int,
+        };
+        let (sender, _) = broadcast::channel(STATION_BUFFER_CHUNKS);
+        let station = Station {
+            name: "test".into(),
+            track_id: "live:test".into(),
+            audio_info: audio_info.clone(),
+            sender: sender.downgrade(),
+            lag_policy: LagPolicy::Skip,
+        };
+
+        // one listener, whose stream end is reported to the test
+        let (ended, mut stream_end) = tokio::sync::mpsc::unbounded_channel();
+        let app = Router::new().route(
+            "/",
+            get(move |upgrade: WebSocketUpgrade| {
+                let (station, ended) = (station.clone(), ended.clone());
+                async move {
+                    upgrade.on_upgrade(move |mut socket| async move {
+                        let mut keepalive = Keepalive::new(KeepaliveConfig::default());
+                        let end = station_streamer(&mut socket, &station, &mut keepalive).await;
+                        let _ = ended.send(end.map_err(|e| e.to_string()));
+                    })
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{address}/"))
+            .await
+            .unwrap();
+        while sender.receiver_count() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        // the broadcaster owns the only sender, which it drops when the input ends
+        let track = OpenedTrack {
+            id: "live:test".into(),
+            audio_info: audio_info.clone(),
+            source: Box::new(EndingInput {
+                audio_info,
+                chunks: 2,
+            }),
+            position: 0,
+        };
+        station_broadcaster("test".into(), track, sender)
+            .await
+            .unwrap();
+
+        assert_eq!(stream_end.recv().await, Some(Ok(StreamEnd::Closed)));
+        let mut chunks = 0;
+        while let Some(Ok(message)) = client.next().await {
+            match message {
+                tungstenite::Message::Binary(_) => chunks += 1,
+                tungstenite::Message::Close(Some(close)) => {
+                    assert_eq!(close.reason, "station ended");
+                    break;
+                }
+                message => panic!("unexpected message {message:?}"),
+            }
+        }
+        assert_eq!(chunks, 2);
+    }
+}
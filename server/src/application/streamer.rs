@@ -1,59 +1,363 @@
-use crate::errors::streamer::StreamerError;
-use axum::extract::ws::WebSocket;
-
-pub async fn wave_streamer(socket: &mut WebSocket) -> Result<(), StreamerError> {
-    // read wav file
-    let mut reader = hound::WavReader::open("data/sample3.wav")?;
-    // get headers
-    let spec = reader.spec();
-    println!(
-        "WAV: {}Hz, {}, {}bits",
-        spec.sample_rate,
-        if spec.channels == 1 {
-            "monoral"
-        } else {
-            "stereo"
-        },
-        spec.bits_per_sample
-    );
-    // get body (PCM samples)
-    let mut samples = reader.samples::<i16>();
-    let frames_per_chunk = 1024;
-    let samples_per_chunk = frames_per_chunk * spec.channels as usize;
-    // define interval
-    let interval =
-        tokio::time::Duration::from_secs_f64(frames_per_chunk as f64 / spec.sample_rate as f64);
-
-    // send PCM data to middle-server
-    loop {
-        let mut buf = Vec::with_capacity(samples_per_chunk);
-
-        // send chunks of a sample
-        for _ in 0..samples_per_chunk {
-            if let Some(Ok(sample)) = samples.next() {
-                buf.extend_from_slice(&sample.to_le_bytes());
+use crate::{
+    application::{encoder::frame_encoder, pacer::Pacer},
+    errors::streamer::StreamerError,
+    models::{
+        library::OpenedTrack,
+        source::{AudioSource, PcmSamples},
+        transport::StreamEnd,
+    },
+};
+use axum::extract::ws::{CloseFrame, Message, WebSocket, close_code};
+use protocol::models::{
+    frame::PcmFrameHeader,
+    keepalive::{Keepalive, Liveness},
+    transport::TransportCommand,
+};
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::SystemTime,
+};
+use tokio::{sync::mpsc, time::Instant};
+
+/// The chunks of a live source, read on a blocking thread.
+pub type LiveChunks = mpsc::Receiver<Result<PcmSamples, StreamerError>>;
+
+// stream IDs of the frame headers, unique within the server process
+static NEXT_STREAM_ID: AtomicU32 = AtomicU32::new(1);
+
+pub fn next_stream_id() -> u32 {
+    NEXT_STREAM_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The wall-clock time of `deadline` in microseconds since the UNIX epoch.
+pub fn presentation_timestamp(deadline: Instant) -> u64 {
+    let now = SystemTime::now();
+    let elapsed = Instant::now().saturating_duration_since(deadline);
+    (now - elapsed)
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|timestamp| timestamp.as_micros() as u64)
+        .unwrap_or_default()
+}
+
+/// Splits the reads of `source` off the async workers if it is live.
+///
+/// A live source waits for its producer, so it moves to a blocking thread that reads ahead
+/// by one chunk of `frames` frames; other sources are returned to be read in place.
+pub fn chunk_feeder(
+    mut source: Box<dyn AudioSource>,
+    frames: usize,
+) -> (Option<Box<dyn AudioSource>>, Option<LiveChunks>) {
+    if !source.is_live() {
+        return (Some(source), None);
+    }
+    let (sender, receiver) = mpsc::channel(1);
+    tokio::task::spawn_blocking(move || {
+        loop {
+            let samples = source.read_frames(frames);
+            let end = !matches!(&samples, Ok(samples) if !samples.is_empty());
+            // the source is dropped (and detached) once the stream is gone
+            if sender.blocking_send(samples).is_err() || end {
+                break;
             }
         }
+    });
+    (None, Some(receiver))
+}
+
+/// The next chunk of a live source, empty once the source has ended.
+pub async fn live_chunk(live_chunks: &mut Option<LiveChunks>) -> Result<PcmSamples, StreamerError> {
+    match live_chunks {
+        Some(chunks) => chunks
+            .recv()
+            .await
+            .unwrap_or(Ok(PcmSamples::Int(Vec::new()))),
+        None => std::future::pending().await,
+    }
+}
+
+fn seeker(
+    source: &mut Option<Box<dyn AudioSource>>,
+    frame: u64,
+    id: &str,
+) -> Result<(), StreamerError> {
+    match source {
+        Some(source) => source.seek(frame),
+        None => Err(StreamerError::InvalidCommandError(format!(
+            "live input {id} cannot seek"
+        ))),
+    }
+}
+
+pub async fn wave_streamer(
+    socket: &mut WebSocket,
+    track: OpenedTrack,
+    keepalive: &mut Keepalive,
+) -> Result<StreamEnd, StreamerError> {
+    // reuse the source opened by the analyzer
+    let OpenedTrack {
+        id,
+        audio_info,
+        source,
+        position,
+    } = track;
+    tracing::info!("Streaming track: {}", id);
+    let total_frames = source.total_frames().unwrap_or(u64::MAX);
+    let is_live = source.is_live();
+    let frames_per_chunk: u64 = 1024;
+    // a live source is read on a blocking thread, so commands and keepalive go on while it waits
+    let (mut source, mut live_chunks) = chunk_feeder(source, frames_per_chunk as usize);
+    let mut awaiting_chunk = false;
+    // deadline-based pacing anchored to the stream start
+    let mut pacer = Pacer::new(audio_info.sample_rate);
+    // frame header, the sequence number counts chunks and is not affected by seek or loop
+    let mut header = PcmFrameHeader::new(&audio_info, next_stream_id(), 0);
+
+    // transport state
+    let mut position = position;
+    let mut paused = false;
+    let mut loop_region: Option<(u64, u64)> = None;
+    let next_chunk = tokio::time::sleep_until(pacer.deadline());
+    tokio::pin!(next_chunk);
+
+    // send PCM data to middle-server while listening for transport commands
+    let stream_end = loop {
+        let samples = tokio::select! {
+            message = socket.recv() => {
+                let Some(message) = message else {
+                    break StreamEnd::Closed;
+                };
+                let message = message.map_err(StreamerError::AxumError)?;
+                keepalive.seen();
+                match message {
+                    Message::Text(text) => {
+                        let command = TransportCommand::try_from(text.as_str())?;
+                        tracing::info!("Transport command [{}]: {:?}", id, command);
+                        match command {
+                            TransportCommand::Pause => paused = true,
+                            TransportCommand::Resume => {
+                                paused = false;
+                                pacer.restart();
+                                next_chunk.as_mut().reset(pacer.deadline());
+                            }
+                            TransportCommand::Seek { position: target } => {
+                                position = target.to_frame(audio_info.sample_rate).min(total_frames);
+                                seeker(&mut source, position, &id)?;
+                            }
+                            TransportCommand::LoopOff => loop_region = None,
+                            TransportCommand::Loop { start, end } => {
+                                let start = start.to_frame(audio_info.sample_rate);
+                                let end = end.to_frame(audio_info.sample_rate).min(total_frames);
+                                if start >= end {
+                                    return Err(StreamerError::InvalidCommandError(format!(
+                                        "empty loop region: {start}..{end}"
+                                    )));
+                                }
+                                if position < start || position >= end {
+                                    position = start;
+                                    seeker(&mut source, position, &id)?;
+                                }
+                                loop_region = Some((start, end));
+                            }
+                            TransportCommand::Stop => break StreamEnd::Stopped,
+                        }
+                    }
+                    Message::Close(close) => {
+                        tracing::info!("Client disconnected while streaming: {:?}", close);
+                        break StreamEnd::Closed;
+                    }
+                    Message::Ping(_) | Message::Pong(_) => {}
+                    _ => {
+                        tracing::error!("Received unsupported message type while streaming");
+                        return Err(StreamerError::UnexpectedMessageTypeError);
+                    }
+                }
+                continue;
+            }
+            () = &mut next_chunk, if !paused && !awaiting_chunk => {
+                let Some(source) = source.as_mut() else {
+                    // the chunk of a live source is awaited in its own branch
+                    awaiting_chunk = true;
+                    continue;
+                };
+                // jump back to the start of the loop region
+                let mut frames = frames_per_chunk;
+                if let Some((start, end)) = loop_region {
+                    if position >= end {
+                        position = start;
+                        source.seek(position)?;
+                    }
+                    frames = frames.min(end - position);
+                }
+                source.read_frames(frames as usize)
+            }
+            samples = live_chunk(&mut live_chunks), if !paused && awaiting_chunk => {
+                awaiting_chunk = false;
+                samples
+            }
+            event = keepalive.tick() => {
+                if keepalive.respond(socket, event).await? == Liveness::Dead {
+                    break StreamEnd::Closed;
+                }
+                continue;
+            }
+        };
+
+        // get body (PCM samples)
+        let samples = match samples {
+            Ok(samples) => samples,
+            Err(StreamerError::LiveInputStalledError(input)) => {
+                tracing::warn!("Live input stalled: {}", input);
+                send_close(socket, close_code::AWAY, "live input stalled").await?;
+                break StreamEnd::Closed;
+            }
+            Err(e) => return Err(e),
+        };
 
         // break point
-        if buf.is_empty() {
-            break;
+        if samples.is_empty() {
+            // the end of a live input is final, so close the connection
+            if is_live {
+                tracing::info!("Live input ended: {}", id);
+                send_close(socket, close_code::NORMAL, "live input ended").await?;
+                break StreamEnd::Closed;
+            }
+            break StreamEnd::Finished;
         }
+        let frames_read = (samples.len() / audio_info.channels as usize) as u64;
+        header.first_frame = position;
+        header.timestamp_us = presentation_timestamp(next_chunk.deadline());
+        position += frames_read;
+        let buf = frame_encoder(&mut header, &samples, &audio_info)?;
+        header.sequence = header.sequence.wrapping_add(1);
 
         // send PCM data
         /*
-            binary size = frames_per_chunk × spec.channels × (spec.bits_per_sample / 8)
-            NOTE: (spec.bits_per_sample / 8) -> bit size to byte size conversion
-            e.g. 1024 frames × 2 channels × (16 bits / 8) = 4096 bytes
-            e.g. 1024 frames × 1 channel × (16 bits / 8) = 2048 bytes
+            binary size = 32 (header, see PcmFrameHeader) + frames_per_chunk × channels × ceil(bits_per_sample / 8)
+            NOTE: ceil(bits_per_sample / 8) -> bit size to byte size conversion
+            e.g. 32 + 1024 frames × 2 channels × (16 bits / 8) = 4128 bytes
+            e.g. 32 + 1024 frames × 1 channel × (24 bits / 8) = 3104 bytes
+            e.g. 32 + 1024 frames × 2 channels × (32 bits float / 8) = 8224 bytes
         */
-        socket
-            .send(axum::extract::ws::Message::Binary(buf.into()))
-            .await
-            .map_err(StreamerError::AxumError)?;
+        if keepalive.send(socket, Message::Binary(buf.into())).await? == Liveness::Dead {
+            break StreamEnd::Closed;
+        }
+
+        // schedule the next chunk relative to the anchor, not to the end of this send
+        pacer.record(next_chunk.deadline(), frames_read);
+        next_chunk.as_mut().reset(pacer.deadline());
+    };
 
-        tokio::time::sleep(interval).await;
+    pacer.log_drift();
+    Ok(stream_end)
+}
+
+pub async fn send_close(
+    socket: &mut WebSocket,
+    code: u16,
+    reason: &str,
+) -> Result<(), StreamerError> {
+    socket
+        .send(Message::Close(Some(CloseFrame {
+            code,
+            reason: reason.into(),
+        })))
+        .await
+        .map_err(StreamerError::AxumError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, extract::WebSocketUpgrade, routing::get};
+    use futures_util::SinkExt;
+    use protocol::models::{
+        audio::{AudioInfo, PcmFormat},
+        keepalive::KeepaliveConfig,
+    };
+    use std::{sync::mpsc as std_mpsc, time::Duration};
+    use tokio_tungstenite::tungstenite;
+
+    /// A live input whose producer sends nothing until `release` is dropped.
+    struct SilentInput {
+        audio_info: AudioInfo,
+        release: std_mpsc::Receiver<()>,
     }
 
-    Ok(())
+    impl AudioSource for SilentInput {
+        fn audio_info(&self) -> &AudioInfo {
+            &self.audio_info
+        }
+
+        fn total_frames(&self) -> Option<u64> {
+            None
+        }
+
+        fn read_frames(&mut self, _frames: usize) -> Result<PcmSamples, StreamerError> {
+            let _ = self.release.recv();
+            Ok(PcmSamples::Int(Vec::new()))
+        }
+
+        fn seek(&mut self, _frame: u64) -> Result<(), StreamerError> {
+            Ok(())
+        }
+
+        fn is_live(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn commands_are_handled_while_a_live_input_waits() {
+        let (release, waiting) = std_mpsc::channel();
+        let audio_info = AudioInfo {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            pcm_format: PcmFormat::Int,
+        };
+        let track = std::sync::Mutex::new(Some(OpenedTrack {
+            id: "live:test".into(),
+            audio_info: audio_info.clone(),
+            source: Box::new(SilentInput {
+                audio_info,
+                release: waiting,
+            }),
+            position: 0,
+        }));
+        let track = std::sync::Arc::new(track);
+
+        // one session, whose stream end is reported to the test
+        let (ended, mut stream_end) = mpsc::unbounded_channel();
+        let app = Router::new().route(
+            "/",
+            get(move |upgrade: WebSocketUpgrade| {
+                let (track, ended) = (track.clone(), ended.clone());
+                async move {
+                    upgrade.on_upgrade(move |mut socket| async move {
+                        let track = track.lock().unwrap().take().unwrap();
+                        let mut keepalive = Keepalive::new(KeepaliveConfig::default());
+                        let end = wave_streamer(&mut socket, track, &mut keepalive).await;
+                        let _ = ended.send(end.map_err(|e| e.to_string()));
+                    })
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{address}/"))
+            .await
+            .unwrap();
+
+        // the input sends nothing, yet the stop is handled at once
+        client
+            .send(tungstenite::Message::text(
+                r#"{"type":"transport","command":"stop"}"#,
+            ))
+            .await
+            .unwrap();
+        let end = tokio::time::timeout(Duration::from_secs(1), stream_end.recv()).await;
+        assert_eq!(end, Ok(Some(Ok(StreamEnd::Stopped))));
+        drop(release);
+    }
 }
@@ -1,10 +1,22 @@
-use super::app::AppError;
 use axum::http::StatusCode;
+use protocol::errors::app::AppError;
 
 #[derive(Debug, thiserror::Error)]
 pub enum AnalyzerError {
     #[error(transparent)]
     HoundError(#[from] hound::Error),
+    #[error(transparent)]
+    SymphoniaError(#[from] symphonia::core::errors::Error),
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error("UnsupportedCodecError: {0}")]
+    UnsupportedCodecError(String),
+    #[error("UnsupportedOutputFormatError: {0}")]
+    UnsupportedOutputFormatError(String),
+    #[error("LiveInputBusyError: {0} is already streaming to another session")]
+    LiveInputBusyError(String),
+    #[error(transparent)]
+    ResamplerConstructionError(#[from] rubato::ResamplerConstructionError),
 }
 
 impl From<AnalyzerError> for AppError {
@@ -14,6 +26,30 @@ impl From<AnalyzerError> for AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
                 message: format!("HoundError: {e}"),
             },
+            AnalyzerError::SymphoniaError(e) => AppError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: format!("SymphoniaError: {e}"),
+            },
+            AnalyzerError::IoError(e) => AppError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: format!("IoError: {e}"),
+            },
+            AnalyzerError::UnsupportedCodecError(e) => AppError {
+                status_code: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                message: format!("UnsupportedCodecError: {e}"),
+            },
+            AnalyzerError::UnsupportedOutputFormatError(e) => AppError {
+                status_code: StatusCode::BAD_REQUEST,
+                message: format!("UnsupportedOutputFormatError: {e}"),
+            },
+            AnalyzerError::LiveInputBusyError(e) => AppError {
+                status_code: StatusCode::CONFLICT,
+                message: format!("LiveInputBusyError: {e} is already streaming to another session"),
+            },
+            AnalyzerError::ResamplerConstructionError(e) => AppError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: format!("ResamplerConstructionError: {e}"),
+            },
         }
     }
 }
@@ -1,5 +1,6 @@
-use super::app::AppError;
 use axum::http::StatusCode;
+use protocol::errors::app::AppError;
+use protocol::models::protocol::PROTOCOL_VERSION;
 
 #[derive(Debug, thiserror::Error)]
 pub enum HandlerError {
@@ -7,12 +8,30 @@ pub enum HandlerError {
     UnexpectedMessageTypeError,
     #[error("UnexpectedMessageError: {0}")]
     UnexpectedMessageError(String),
+    #[error("InvalidControlMessageError: {0}")]
+    InvalidControlMessageError(String),
+    #[error(
+        "ProtocolVersionError: peer speaks version {0}, this server speaks version {PROTOCOL_VERSION}"
+    )]
+    ProtocolVersionError(u32),
+    #[error("UnknownTrackError: no track with ID {0}")]
+    UnknownTrackError(String),
+    #[error("UnknownStationError: no station named {0}")]
+    UnknownStationError(String),
+    #[error("EmptyLibraryError: the track library is empty")]
+    EmptyLibraryError,
+    #[error("TrackNotOpenedError: accept received before a track was opened")]
+    TrackNotOpenedError,
+    #[error("InvalidFormatRequestError: {0}")]
+    InvalidFormatRequestError(String),
     #[error(transparent)]
     SetGlobalDefaultError(#[from] tracing::subscriber::SetGlobalDefaultError),
     #[error(transparent)]
     IoError(#[from] std::io::Error),
     #[error(transparent)]
     AxumError(#[from] axum::Error),
+    #[error(transparent)]
+    JoinError(#[from] tokio::task::JoinError),
 }
 
 impl From<HandlerError> for AppError {
@@ -26,6 +45,36 @@ impl From<HandlerError> for AppError {
                 status_code: StatusCode::BAD_REQUEST,
                 message: format!("UnexpectedMessageError: {e}"),
             },
+            HandlerError::InvalidControlMessageError(e) => AppError {
+                status_code: StatusCode::BAD_REQUEST,
+                message: format!("InvalidControlMessageError: {e}"),
+            },
+            HandlerError::ProtocolVersionError(version) => AppError {
+                status_code: StatusCode::UPGRADE_REQUIRED,
+                message: format!(
+                    "ProtocolVersionError: peer speaks version {version}, this server speaks version {PROTOCOL_VERSION}"
+                ),
+            },
+            HandlerError::UnknownTrackError(e) => AppError {
+                status_code: StatusCode::NOT_FOUND,
+                message: format!("UnknownTrackError: no track with ID {e}"),
+            },
+            HandlerError::UnknownStationError(e) => AppError {
+                status_code: StatusCode::NOT_FOUND,
+                message: format!("UnknownStationError: no station named {e}"),
+            },
+            HandlerError::EmptyLibraryError => AppError {
+                status_code: StatusCode::NOT_FOUND,
+                message: "EmptyLibraryError: the track library is empty".into(),
+            },
+            HandlerError::TrackNotOpenedError => AppError {
+                status_code: StatusCode::BAD_REQUEST,
+                message: "TrackNotOpenedError: accept received before a track was opened".into(),
+            },
+            HandlerError::InvalidFormatRequestError(e) => AppError {
+                status_code: StatusCode::BAD_REQUEST,
+                message: format!("InvalidFormatRequestError: {e}"),
+            },
             HandlerError::SetGlobalDefaultError(e) => AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
                 message: format!("SetGlobalDefaultError: {e}"),
@@ -38,6 +87,10 @@ impl From<HandlerError> for AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
                 message: format!("AxumError: {e}"),
             },
+            HandlerError::JoinError(e) => AppError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: format!("JoinError: {e}"),
+            },
         }
     }
 }
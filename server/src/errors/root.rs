@@ -4,4 +4,12 @@ pub enum RootError {
     SetGlobalDefaultError(#[from] tracing::subscriber::SetGlobalDefaultError),
     #[error(transparent)]
     IoError(#[from] std::io::Error),
+    #[error("InvalidLiveInputError: {0}")]
+    InvalidLiveInputError(String),
+    #[error("InvalidStationError: {0}")]
+    InvalidStationError(String),
+    #[error("InvalidKeepaliveError: {0}")]
+    InvalidKeepaliveError(String),
+    #[error(transparent)]
+    AnalyzerError(#[from] crate::errors::analyzer::AnalyzerError),
 }
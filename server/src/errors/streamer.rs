@@ -1,12 +1,29 @@
-use super::app::AppError;
 use axum::http::StatusCode;
+use protocol::errors::app::AppError;
+use protocol::{errors::protocol::ProtocolError, models::audio::PcmFormat};
 
 #[derive(Debug, thiserror::Error)]
 pub enum StreamerError {
     #[error(transparent)]
     HoundError(#[from] hound::Error),
     #[error(transparent)]
+    SymphoniaError(#[from] symphonia::core::errors::Error),
+    #[error(transparent)]
+    ResampleError(#[from] rubato::ResampleError),
+    #[error(transparent)]
     AxumError(#[from] axum::Error),
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error("UnsupportedSampleFormatError: {0} samples with {1} bits are not supported")]
+    UnsupportedSampleFormatError(PcmFormat, u16),
+    #[error("LiveInputStalledError: no data from {0}")]
+    LiveInputStalledError(String),
+    #[error("InvalidCommandError: {0}")]
+    InvalidCommandError(String),
+    #[error("UnexpectedMessageTypeError: unsupported message type received")]
+    UnexpectedMessageTypeError,
+    #[error(transparent)]
+    ProtocolError(#[from] ProtocolError),
 }
 
 impl From<StreamerError> for AppError {
@@ -16,10 +33,45 @@ impl From<StreamerError> for AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
                 message: format!("HoundError: {e}"),
             },
+            StreamerError::SymphoniaError(e) => AppError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: format!("SymphoniaError: {e}"),
+            },
+            StreamerError::ResampleError(e) => AppError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: format!("ResampleError: {e}"),
+            },
             StreamerError::AxumError(e) => AppError {
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
                 message: format!("AxumError: {e}"),
             },
+            StreamerError::IoError(e) => AppError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: format!("IoError: {e}"),
+            },
+            StreamerError::UnsupportedSampleFormatError(pcm_format, bits_per_sample) => AppError {
+                status_code: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                message: format!(
+                    "UnsupportedSampleFormatError: {pcm_format} samples with {bits_per_sample} bits are not supported"
+                ),
+            },
+            StreamerError::LiveInputStalledError(e) => AppError {
+                status_code: StatusCode::GATEWAY_TIMEOUT,
+                message: format!("LiveInputStalledError: no data from {e}"),
+            },
+            StreamerError::InvalidCommandError(e) => AppError {
+                status_code: StatusCode::BAD_REQUEST,
+                message: format!("InvalidCommandError: {e}"),
+            },
+            StreamerError::UnexpectedMessageTypeError => AppError {
+                status_code: StatusCode::BAD_REQUEST,
+                message: "UnexpectedMessageTypeError: unsupported message type received".into(),
+            },
+            // the message of a protocol error already starts with its variant name
+            StreamerError::ProtocolError(e) => AppError {
+                status_code: StatusCode::BAD_REQUEST,
+                message: e.to_string(),
+            },
         }
     }
 }
@@ -1,13 +1,28 @@
 use crate::{
-    application::streamer::wave_streamer,
-    errors::{app::AppError, handler::HandlerError},
-    models::shared_state::RwLockSharedState,
+    application::{analyzer::wave_analyzer, station::station_streamer, streamer::wave_streamer},
+    errors::{handler::HandlerError, streamer::StreamerError},
+    models::{
+        library::{OpenedTrack, Track, TrackLibrary, TrackSource},
+        shared_state::RwLockSharedState,
+        station::{OpenedStream, Stations},
+        transport::StreamEnd,
+    },
 };
 use axum::extract::ws::{Message, WebSocket};
 use axum::{
     extract::{State, WebSocketUpgrade},
     response::IntoResponse,
 };
+use protocol::{
+    errors::app::AppError,
+    models::{
+        format::FormatRequest,
+        keepalive::{Keepalive, KeepaliveConfig, Liveness},
+        protocol::{ControlMessage, EndOfStreamReason, PROTOCOL_VERSION},
+        transport::StreamPosition,
+    },
+};
+use std::sync::Arc;
 
 // handler
 pub async fn websocket_handler(
@@ -15,8 +30,11 @@ pub async fn websocket_handler(
     web_socket: WebSocketUpgrade,
 ) -> Result<impl IntoResponse, AppError> {
     let shared_state = shared_state.read().await;
-    let response = web_socket.on_upgrade(|socket| async move {
-        if let Err(error) = websocket_processing(socket).await {
+    let library = Arc::clone(&shared_state.library);
+    let stations = Arc::clone(&shared_state.stations);
+    let keepalive = shared_state.keepalive;
+    let response = web_socket.on_upgrade(move |socket| async move {
+        if let Err(error) = websocket_processing(socket, library, stations, keepalive).await {
             tracing::error!("WebSocket error: {:?}", error);
         }
     });
@@ -25,44 +43,217 @@ pub async fn websocket_handler(
 }
 
 //websocket
-pub async fn websocket_processing(mut socket: WebSocket) -> Result<(), AppError> {
-    while let Some(message) = socket.recv().await {
+pub async fn websocket_processing(
+    mut socket: WebSocket,
+    library: Arc<TrackLibrary>,
+    stations: Arc<Stations>,
+    keepalive: KeepaliveConfig,
+) -> Result<(), AppError> {
+    let mut keepalive = Keepalive::new(keepalive);
+    let result = control_session(&mut socket, library, stations, &mut keepalive).await;
+
+    // report the failure to the peer and close the connection with the matching close code
+    if let Err(error) = &result {
+        let message = error.to_control_message();
+        if keepalive
+            .send(&mut socket, Message::Text(message.to_json().into()))
+            .await
+            .is_ok_and(|liveness| liveness == Liveness::Alive)
+        {
+            let _ = keepalive
+                .send(&mut socket, Message::Close(Some(error.to_close_frame())))
+                .await;
+        }
+    }
+    result
+}
+
+async fn control_session(
+    socket: &mut WebSocket,
+    library: Arc<TrackLibrary>,
+    stations: Arc<Stations>,
+    keepalive: &mut Keepalive,
+) -> Result<(), AppError> {
+    // the protocol version is checked before anything else
+    let mut hello_received = false;
+    // the track or station opened by "open", consumed by "accept"
+    let mut opened_stream: Option<OpenedStream> = None;
+
+    loop {
+        let message = tokio::select! {
+            message = socket.recv() => message,
+            event = keepalive.tick() => {
+                match keepalive
+                    .respond(socket, event)
+                    .await
+                    .map_err(HandlerError::AxumError)?
+                {
+                    Liveness::Alive => continue,
+                    Liveness::Dead => return Ok(()),
+                }
+            }
+        };
+        let Some(message) = message else {
+            return Ok(());
+        };
         // Receive a message from the client
         match message {
             Ok(message) => {
+                keepalive.seen();
                 match message {
                     Message::Text(text) => {
-                        // receive connection request from client
-                        let msg = text.to_string();
-                        if msg != "open" && msg != "accept" {
-                            tracing::info!("Received unexpected text: {:?}", msg);
-                            return Err(HandlerError::UnexpectedMessageError(msg).into());
-                        }
-                        tracing::info!("Received text: {:?}", msg);
+                        // receive control message from client (see ControlMessage for the format)
+                        let message = ControlMessage::try_from(text.as_str()).map_err(|e| {
+                            tracing::info!("Received unexpected text: {:?}", text);
+                            HandlerError::InvalidControlMessageError(format!(
+                                "expected a JSON control message of protocol version {PROTOCOL_VERSION} ({e})"
+                            ))
+                        })?;
+                        tracing::info!("Received control message: {:?}", message);
 
-                        // step1: analyze audio file and send audio info to middle-server
-                        if msg == "open" {
-                            // analyze audio file
-                            let audio_info = crate::application::analyzer::wave_analyzer()?;
-                            // send audio info to middle-server
-                            socket
-                                .send(Message::Text(
-                                    format!("{} {}", audio_info.channel, audio_info.sample_rate)
-                                        .into(),
+                        match message {
+                            //step0: negotiate the protocol version
+                            ControlMessage::Hello { version, .. } => {
+                                if version != PROTOCOL_VERSION {
+                                    return Err(HandlerError::ProtocolVersionError(version).into());
+                                }
+                                hello_received = true;
+                                keepalive.handshake_done();
+                                send_control_message(
+                                    socket,
+                                    &ControlMessage::Hello {
+                                        version: PROTOCOL_VERSION,
+                                        upstream: None,
+                                        analyzers: None,
+                                    },
+                                )
+                                .await?;
+                            }
+                            message if !hello_received => {
+                                return Err(HandlerError::UnexpectedMessageError(format!(
+                                    "{} before hello",
+                                    message.kind()
                                 ))
-                                .await
-                                .map_err(HandlerError::AxumError)?;
-                        }
+                                .into());
+                            }
+
+                            // step1: analyze audio file and send audio info to middle-server
+                            ControlMessage::Open {
+                                track_id,
+                                format,
+                                start,
+                            } => {
+                                let station_name = track_id
+                                    .as_deref()
+                                    .and_then(|track_id| track_id.strip_prefix("station:"));
+                                // select station
+                                if let Some(name) = station_name {
+                                    let station = stations.get(name).ok_or(
+                                        HandlerError::UnknownStationError(name.to_string()),
+                                    )?;
+                                    // the station is decoded once for all listeners
+                                    if format != Default::default() {
+                                        return Err(HandlerError::InvalidFormatRequestError(
+                                            format!("station {name} streams in its own format"),
+                                        )
+                                        .into());
+                                    }
+                                    // listeners join the broadcast where it is
+                                    if start.is_some() {
+                                        return Err(StreamerError::InvalidCommandError(format!(
+                                            "station {name} cannot start at a position"
+                                        ))
+                                        .into());
+                                    }
+                                    let audio_info = station.audio_info.clone();
+                                    opened_stream = Some(OpenedStream::Station(station.clone()));
+                                    send_control_message(
+                                        socket,
+                                        &ControlMessage::AudioInfo {
+                                            info: audio_info,
+                                            seekable: false,
+                                        },
+                                    )
+                                    .await?;
+                                    continue;
+                                }
+
+                                // select track
+                                let track = match track_id {
+                                    Some(track_id) => library
+                                        .get(&track_id)
+                                        .ok_or(HandlerError::UnknownTrackError(track_id))?,
+                                    None => library
+                                        .default_track()
+                                        .ok_or(HandlerError::EmptyLibraryError)?,
+                                };
+                                // analyze audio file and negotiate the output format,
+                                // opening and probing a file blocks, so off the async workers
+                                let track = match track.source {
+                                    TrackSource::File(_) => {
+                                        tokio::task::spawn_blocking(move || {
+                                            track_opener(&track, &format, start)
+                                        })
+                                        .await
+                                        .map_err(HandlerError::JoinError)??
+                                    }
+                                    _ => track_opener(&track, &format, start)?,
+                                };
+                                let audio_info = track.audio_info.clone();
+                                let seekable = !track.source.is_live();
+                                opened_stream = Some(OpenedStream::Track(track));
+                                // send audio info to middle-server
+                                send_control_message(
+                                    socket,
+                                    &ControlMessage::AudioInfo {
+                                        info: audio_info,
+                                        seekable,
+                                    },
+                                )
+                                .await?;
+                            }
 
-                        //step2: receive connection acceptance from middle-server and send PCM data to middle-server
-                        if msg == "accept" {
-                            wave_streamer(&mut socket).await?;
+                            //step2: receive connection acceptance from middle-server and send PCM data to middle-server
+                            ControlMessage::Accept => {
+                                let stream_end = match opened_stream
+                                    .take()
+                                    .ok_or(HandlerError::TrackNotOpenedError)?
+                                {
+                                    OpenedStream::Track(track) => {
+                                        wave_streamer(socket, track, keepalive).await?
+                                    }
+                                    OpenedStream::Station(station) => {
+                                        station_streamer(socket, &station, keepalive).await?
+                                    }
+                                };
+                                let reason = match stream_end {
+                                    StreamEnd::Finished => EndOfStreamReason::Finished,
+                                    StreamEnd::Stopped => EndOfStreamReason::Stopped,
+                                    StreamEnd::Closed => return Ok(()),
+                                };
+                                tracing::info!("End of stream: {:?}", reason);
+                                send_control_message(
+                                    socket,
+                                    &ControlMessage::EndOfStream { reason },
+                                )
+                                .await?;
+                            }
+
+                            message => {
+                                return Err(HandlerError::UnexpectedMessageError(format!(
+                                    "{} is not accepted outside of a stream",
+                                    message.kind()
+                                ))
+                                .into());
+                            }
                         }
                     }
                     Message::Close(close) => {
                         tracing::info!("Client disconnected: {:?}", close);
                         return Ok(());
                     }
+                    // pings are answered by axum, pongs only prove that the peer is alive
+                    Message::Ping(_) | Message::Pong(_) => {}
                     _ => {
                         tracing::error!("Received unsupported message type from server");
                         return Err(HandlerError::UnexpectedMessageTypeError.into());
@@ -75,5 +266,32 @@ pub async fn websocket_processing(mut socket: WebSocket) -> Result<(), AppError>
             }
         }
     }
-    Ok(())
+}
+
+/// Opens `track` in the requested format, at `start` after a reconnect.
+fn track_opener(
+    track: &Track,
+    format: &FormatRequest,
+    start: Option<StreamPosition>,
+) -> Result<OpenedTrack, AppError> {
+    let mut track = wave_analyzer(track, format)?;
+    // start where the middle-server left off after a reconnect
+    if let Some(start) = start {
+        let total_frames = track.source.total_frames().unwrap_or(u64::MAX);
+        track.position = start
+            .to_frame(track.audio_info.sample_rate)
+            .min(total_frames);
+        track.source.seek(track.position)?;
+    }
+    Ok(track)
+}
+
+async fn send_control_message(
+    socket: &mut WebSocket,
+    message: &ControlMessage,
+) -> Result<(), HandlerError> {
+    socket
+        .send(Message::Text(message.to_json().into()))
+        .await
+        .map_err(HandlerError::AxumError)
 }
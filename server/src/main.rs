@@ -1,6 +1,16 @@
-use crate::{errors::root::RootError, handlers::ws::websocket_handler};
+use crate::{
+    application::{library::library_scanner, station::station_launcher},
+    errors::root::RootError,
+    handlers::ws::websocket_handler,
+    models::{
+        live::LiveInput,
+        shared_state::SharedState,
+        station::{LagPolicy, StationConfig},
+    },
+};
 use axum::{Router, extract::DefaultBodyLimit, routing::get};
-use std::sync::Arc;
+use protocol::models::keepalive::KeepaliveConfig;
+use std::{path::PathBuf, sync::Arc};
 use tokio::sync::RwLock;
 use tower_http::cors::CorsLayer;
 
@@ -12,16 +22,51 @@ pub mod models;
 // Domain
 const IP_ADDRESS: &str = "localhost";
 const PORT: u16 = 5001;
+// Library
+const DEFAULT_LIBRARY_DIR: &str = "data";
 
 #[tokio::main]
 async fn main() -> Result<(), RootError> {
-    // shared object
-    let shared_state = Arc::new(RwLock::new(0));
     // tracing
     let subscriber = tracing_subscriber::FmtSubscriber::builder()
         .with_max_level(tracing::Level::DEBUG)
         .finish();
     tracing::subscriber::set_global_default(subscriber)?;
+    // track library (LIBRARY_DIR overrides the default directory)
+    let library_dir = std::env::var("LIBRARY_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_LIBRARY_DIR));
+    // live inputs (LIVE_INPUTS, see LiveInput::parse_list for the format)
+    let live_inputs = match std::env::var("LIVE_INPUTS") {
+        Ok(live_inputs) => {
+            LiveInput::parse_list(&live_inputs).map_err(RootError::InvalidLiveInputError)?
+        }
+        Err(_) => Vec::new(),
+    };
+    let library = library_scanner(&library_dir, live_inputs)?;
+    // broadcast stations (STATIONS, see StationConfig::parse_list for the format)
+    let station_configs = match std::env::var("STATIONS") {
+        Ok(stations) => {
+            StationConfig::parse_list(&stations).map_err(RootError::InvalidStationError)?
+        }
+        Err(_) => Vec::new(),
+    };
+    // what a listener that falls behind does (STATION_LAG_POLICY: skip | resync | disconnect)
+    let lag_policy = match std::env::var("STATION_LAG_POLICY") {
+        Ok(lag_policy) => {
+            LagPolicy::try_from(lag_policy.as_str()).map_err(RootError::InvalidStationError)?
+        }
+        Err(_) => LagPolicy::default(),
+    };
+    let stations = station_launcher(station_configs, &library, lag_policy)?;
+    // ping interval, idle and handshake timeouts (see KeepaliveConfig::from_env for the variables)
+    let keepalive = KeepaliveConfig::from_env().map_err(RootError::InvalidKeepaliveError)?;
+    // shared object
+    let shared_state = Arc::new(RwLock::new(SharedState {
+        library: Arc::new(library),
+        stations: Arc::new(stations),
+        keepalive,
+    }));
     // cors
     let cors = CorsLayer::new().allow_origin(tower_http::cors::Any);
 
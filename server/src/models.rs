@@ -1,2 +1,7 @@
-pub mod audio;
+pub mod generator;
+pub mod library;
+pub mod live;
 pub mod shared_state;
+pub mod source;
+pub mod station;
+pub mod transport;
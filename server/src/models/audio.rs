@@ -1,4 +0,0 @@
-pub struct AudioInfo {
-    pub channel: u32,
-    pub sample_rate: u32,
-}
@@ -0,0 +1,148 @@
+// length of a generated track unless `@<seconds>` is given
+static DEFAULT_DURATION_SECS: f64 = 60.0;
+// a day, which keeps the frame count of any sample rate far from u64::MAX
+static MAX_DURATION_SECS: f64 = 86400.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Signal {
+    /// A click on every beat, with an accented click on the first beat of each bar.
+    ///
+    /// `bpm` counts `beat_unit` notes, so the ground truth tempo is `bpm` for any time signature
+    /// (e.g. 6/8 at 180 clicks on every eighth note and accents every sixth click).
+    Click {
+        bpm: f64,
+        beats_per_bar: u32,
+        beat_unit: u32,
+    },
+    /// An exponential sine sweep from `start_hz` to `end_hz` over the whole track.
+    Sweep {
+        start_hz: f64,
+        end_hz: f64,
+    },
+    WhiteNoise,
+    PinkNoise,
+    Silence,
+}
+
+/// A synthetic test signal, selected with a `gen:` track ID.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeneratorSpec {
+    pub signal: Signal,
+    pub duration_secs: f64,
+}
+
+impl TryFrom<&str> for GeneratorSpec {
+    type Error = String;
+
+    /*
+        FORMAT:
+            gen:click:<bpm>[:<beats_per_bar>/<beat_unit>][@<seconds>]
+            gen:sweep:<start_hz>:<end_hz>[@<seconds>]
+            gen:white[@<seconds>]
+            gen:pink[@<seconds>]
+            gen:silence[@<seconds>]
+        e.g. gen:click:128, gen:click:90:3/4@30, gen:sweep:20:20000@10
+    */
+    fn try_from(id: &str) -> Result<Self, Self::Error> {
+        let invalid = || format!("invalid generator: {id}");
+        let signal = id.strip_prefix("gen:").ok_or_else(invalid)?;
+        let (signal, duration_secs) = match signal.split_once('@') {
+            Some((signal, seconds)) => (signal, seconds.parse().map_err(|_| invalid())?),
+            None => (signal, DEFAULT_DURATION_SECS),
+        };
+        let parse = |value: &str| value.parse::<f64>().map_err(|_| invalid());
+
+        let arguments: Vec<&str> = signal.split(':').collect();
+        let signal = match arguments[..] {
+            ["click", bpm] => Signal::Click {
+                bpm: parse(bpm)?,
+                beats_per_bar: 4,
+                beat_unit: 4,
+            },
+            ["click", bpm, time_signature] => {
+                let (beats_per_bar, beat_unit) =
+                    time_signature.split_once('/').ok_or_else(invalid)?;
+                Signal::Click {
+                    bpm: parse(bpm)?,
+                    beats_per_bar: beats_per_bar.parse().map_err(|_| invalid())?,
+                    beat_unit: beat_unit.parse().map_err(|_| invalid())?,
+                }
+            }
+            ["sweep", start_hz, end_hz] => Signal::Sweep {
+                start_hz: parse(start_hz)?,
+                end_hz: parse(end_hz)?,
+            },
+            ["white"] => Signal::WhiteNoise,
+            ["pink"] => Signal::PinkNoise,
+            ["silence"] => Signal::Silence,
+            _ => return Err(invalid()),
+        };
+
+        let is_valid = duration_secs > 0.0
+            && duration_secs <= MAX_DURATION_SECS
+            && match signal {
+                Signal::Click {
+                    bpm,
+                    beats_per_bar,
+                    beat_unit,
+                } => (1.0..=1000.0).contains(&bpm) && beats_per_bar > 0 && beat_unit > 0,
+                Signal::Sweep { start_hz, end_hz } => [start_hz, end_hz]
+                    .iter()
+                    .all(|hz| hz.is_finite() && *hz > 0.0),
+                _ => true,
+            };
+        if !is_valid {
+            return Err(invalid());
+        }
+        Ok(GeneratorSpec {
+            signal,
+            duration_secs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generator_ids_are_parsed() {
+        assert_eq!(
+            GeneratorSpec::try_from("gen:click:90:3/4@30"),
+            Ok(GeneratorSpec {
+                signal: Signal::Click {
+                    bpm: 90.0,
+                    beats_per_bar: 3,
+                    beat_unit: 4,
+                },
+                duration_secs: 30.0,
+            })
+        );
+        assert_eq!(
+            GeneratorSpec::try_from("gen:silence").map(|spec| spec.duration_secs),
+            Ok(DEFAULT_DURATION_SECS)
+        );
+    }
+
+    #[test]
+    fn unbounded_or_invalid_generators_are_rejected() {
+        for id in [
+            "gen:silence@inf",
+            "gen:silence@NaN",
+            "gen:silence@1e300",
+            "gen:silence@86401",
+            "gen:silence@0",
+            "gen:click:0",
+            "gen:click:inf",
+            "gen:click:120:4/0",
+            "gen:sweep:0:1000",
+            "gen:sweep:inf:1000",
+            "gen:sweep:20:NaN",
+            "gen:square",
+            "click:120",
+        ] {
+            assert!(GeneratorSpec::try_from(id).is_err(), "{id}");
+        }
+        assert!(GeneratorSpec::try_from("gen:silence@86400").is_ok());
+    }
+}
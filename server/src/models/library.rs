@@ -0,0 +1,64 @@
+use crate::models::{generator::GeneratorSpec, live::LiveInput, source::AudioSource};
+use protocol::models::audio::AudioInfo;
+use std::{collections::BTreeMap, path::PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct Track {
+    /// Stable identifier derived from the path relative to the library directory,
+    /// without extension and with `/` separators (e.g. `sample3`, `live/set1`).
+    /// Live inputs use `live:<name>` and generators `gen:<signal>` (see `GeneratorSpec`).
+    pub id: String,
+
+    /// Where the samples come from.
+    pub source: TrackSource,
+}
+
+#[derive(Debug, Clone)]
+pub enum TrackSource {
+    /// An audio file in the library directory.
+    File(PathBuf),
+    /// Raw PCM from stdin, a named pipe or a growing file.
+    Live(LiveInput),
+    /// A synthetic test signal.
+    Generator(GeneratorSpec),
+}
+
+#[derive(Debug, Default)]
+pub struct TrackLibrary {
+    /// Tracks keyed by their ID. A `BTreeMap` keeps the listing order stable.
+    pub tracks: BTreeMap<String, Track>,
+}
+
+impl TrackLibrary {
+    /// Looks up a track. Generator tracks are parameterized by their ID and are created on demand.
+    pub fn get(&self, id: &str) -> Option<Track> {
+        if let Ok(spec) = GeneratorSpec::try_from(id) {
+            return Some(Track {
+                id: id.to_string(),
+                source: TrackSource::Generator(spec),
+            });
+        }
+        self.tracks.get(id).cloned()
+    }
+
+    /// The track selected when a client sends `open` without a track ID.
+    ///
+    /// Live inputs are never selected implicitly.
+    pub fn default_track(&self) -> Option<Track> {
+        self.tracks
+            .values()
+            .find(|track| matches!(track.source, TrackSource::File(_)))
+            .cloned()
+    }
+}
+
+/// A track opened for a single session.
+///
+/// The analyzer opens the file once and the streamer keeps reading from the same source.
+pub struct OpenedTrack {
+    pub id: String,
+    pub audio_info: AudioInfo,
+    pub source: Box<dyn AudioSource>,
+    /// The frame the stream starts at (the source is already positioned there).
+    pub position: u64,
+}
@@ -0,0 +1,113 @@
+use protocol::models::audio::{AudioInfo, PcmFormat};
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64},
+        mpsc::SyncSender,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LiveInputKind {
+    /// Raw PCM piped into the server process.
+    Stdin,
+    /// A named pipe (FIFO). Opening it waits until a writer connects.
+    Fifo(PathBuf),
+    /// A raw PCM file that is still being written, read from its current end.
+    Tail(PathBuf),
+}
+
+/// A live input of raw interleaved little-endian PCM.
+///
+/// Raw PCM has no header, so the format is declared in the configuration.
+#[derive(Debug, Clone)]
+pub struct LiveInput {
+    /// The name in the configuration, without the `live:` prefix of the track ID.
+    pub name: String,
+    pub kind: LiveInputKind,
+    pub audio_info: AudioInfo,
+    pub tap: Arc<LiveTap>,
+}
+
+/// Where the reader thread of a live input delivers what it reads.
+///
+/// The thread outlives the sessions: a pipe cannot be shared between readers,
+/// so sessions attach to the one reader instead of opening the input themselves.
+#[derive(Debug, Default)]
+pub struct LiveTap {
+    /// The number and channel of the session reading the input, one session at a time.
+    pub session: Mutex<Option<(u64, LiveSender)>>,
+    /// Numbers the sessions, so that a session only detaches itself.
+    pub sessions: AtomicU64,
+    /// Set while the reader thread runs.
+    pub reading: AtomicBool,
+}
+
+pub type LiveSender = SyncSender<std::io::Result<Vec<u8>>>;
+
+impl LiveInput {
+    pub fn track_id(&self) -> String {
+        format!("live:{}", self.name)
+    }
+
+    /// Parses the `LIVE_INPUTS` environment variable.
+    /*
+        FORMAT: <name>=<stdin|fifo:<path>|tail:<path>>@<sample_rate>/<channels>/<bits_per_sample>/<pcm_format>[,...]
+        e.g. deck=fifo:/tmp/deck.pcm@48000/2/16/int,mic=stdin@44100/1/32/float
+    */
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        text.split(',')
+            .map(str::trim)
+            .filter(|input| !input.is_empty())
+            .map(LiveInput::parse)
+            .collect()
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid live input: {text}");
+        let (name, rest) = text.split_once('=').ok_or_else(invalid)?;
+        let (kind, format) = rest.rsplit_once('@').ok_or_else(invalid)?;
+        if name.is_empty() {
+            return Err(invalid());
+        }
+
+        let kind = match kind.split_once(':') {
+            None if kind == "stdin" => LiveInputKind::Stdin,
+            Some(("fifo", path)) if !path.is_empty() => LiveInputKind::Fifo(PathBuf::from(path)),
+            Some(("tail", path)) if !path.is_empty() => LiveInputKind::Tail(PathBuf::from(path)),
+            _ => return Err(invalid()),
+        };
+
+        let fields: Vec<&str> = format.split('/').collect();
+        let [sample_rate, channels, bits_per_sample, pcm_format] = fields[..] else {
+            return Err(invalid());
+        };
+        let audio_info = AudioInfo {
+            sample_rate: sample_rate.parse().map_err(|_| invalid())?,
+            channels: channels.parse().map_err(|_| invalid())?,
+            bits_per_sample: bits_per_sample.parse().map_err(|_| invalid())?,
+            pcm_format: match pcm_format {
+                "int" => PcmFormat::Int,
+                "float" => PcmFormat::Float,
+                _ => return Err(invalid()),
+            },
+        };
+        let is_supported = audio_info.sample_rate > 0
+            && audio_info.channels > 0
+            && match audio_info.pcm_format {
+                PcmFormat::Int => (1..=32).contains(&audio_info.bits_per_sample),
+                PcmFormat::Float => audio_info.bits_per_sample == 32,
+            };
+        if !is_supported {
+            return Err(invalid());
+        }
+
+        Ok(LiveInput {
+            name: name.to_string(),
+            kind,
+            audio_info,
+            tap: Arc::default(),
+        })
+    }
+}
@@ -1,4 +1,12 @@
+use crate::models::{library::TrackLibrary, station::Stations};
+use protocol::models::keepalive::KeepaliveConfig;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-pub type RwLockSharedState = Arc<RwLock<i32>>;
+pub struct SharedState {
+    pub library: Arc<TrackLibrary>,
+    pub stations: Arc<Stations>,
+    pub keepalive: KeepaliveConfig,
+}
+
+pub type RwLockSharedState = Arc<RwLock<SharedState>>;
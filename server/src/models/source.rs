@@ -0,0 +1,44 @@
+use crate::errors::streamer::StreamerError;
+use protocol::models::audio::AudioInfo;
+
+/// Interleaved samples in the native representation of a source.
+///
+/// Integer samples keep their native range (e.g. ±2^23 for 24-bit), so they can be
+/// re-encoded without loss in the format advertised by `AudioInfo`.
+#[derive(Debug, Clone)]
+pub enum PcmSamples {
+    Int(Vec<i32>),
+    Float(Vec<f32>),
+}
+
+impl PcmSamples {
+    pub fn len(&self) -> usize {
+        match self {
+            PcmSamples::Int(samples) => samples.len(),
+            PcmSamples::Float(samples) => samples.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A decoded audio stream, independent of container and codec.
+pub trait AudioSource: Send {
+    fn audio_info(&self) -> &AudioInfo;
+
+    /// The length of the stream in frames, if known.
+    fn total_frames(&self) -> Option<u64>;
+
+    /// Reads up to `frames` frames. An empty result means the end of the stream.
+    fn read_frames(&mut self, frames: usize) -> Result<PcmSamples, StreamerError>;
+
+    /// Moves the read position to `frame`.
+    fn seek(&mut self, frame: u64) -> Result<(), StreamerError>;
+
+    /// Whether the stream is produced in real time (e.g. a pipe) rather than read from a file.
+    fn is_live(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,88 @@
+use crate::models::library::OpenedTrack;
+use axum::body::Bytes;
+use protocol::models::audio::AudioInfo;
+use std::collections::BTreeMap;
+use tokio::sync::broadcast;
+
+/// What a listener does when it falls more than the broadcast buffer behind the station.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LagPolicy {
+    /// Continue with the oldest chunk still buffered (keeps as much audio as possible).
+    Skip,
+    /// Jump to the live position, so the listener is back in sync with the others.
+    #[default]
+    Resync,
+    /// Close the connection.
+    Disconnect,
+}
+
+impl TryFrom<&str> for LagPolicy {
+    type Error = String;
+
+    /// FORMAT: skip | resync | disconnect
+    fn try_from(text: &str) -> Result<Self, Self::Error> {
+        match text {
+            "skip" => Ok(LagPolicy::Skip),
+            "resync" => Ok(LagPolicy::Resync),
+            "disconnect" => Ok(LagPolicy::Disconnect),
+            _ => Err(format!("invalid lag policy: {text}")),
+        }
+    }
+}
+
+/// A station from the `STATIONS` environment variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StationConfig {
+    pub name: String,
+    pub track_id: String,
+}
+
+impl StationConfig {
+    /*
+        FORMAT: <name>=<track_id>[,...]
+        e.g. main=sample3,clicks=gen:click:128,deck=live:deck
+    */
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        text.split(',')
+            .map(str::trim)
+            .filter(|station| !station.is_empty())
+            .map(|station| match station.split_once('=') {
+                Some((name, track_id)) if !name.is_empty() && !track_id.is_empty() => {
+                    Ok(StationConfig {
+                        name: name.to_string(),
+                        track_id: track_id.to_string(),
+                    })
+                }
+                _ => Err(format!("invalid station: {station}")),
+            })
+            .collect()
+    }
+}
+
+/// A track that is decoded and paced once and broadcast to every listener.
+///
+/// Listeners subscribe to `sender` and receive encoded PCM chunks from the live position on.
+#[derive(Debug, Clone)]
+pub struct Station {
+    pub name: String,
+    pub track_id: String,
+    pub audio_info: AudioInfo,
+    /// The broadcaster holds the only `Sender`, so the channel closes when the station goes off air.
+    pub sender: broadcast::WeakSender<Bytes>,
+    pub lag_policy: LagPolicy,
+}
+
+impl Station {
+    /// A receiver from the live position on, `None` once the station is off air.
+    pub fn subscribe(&self) -> Option<broadcast::Receiver<Bytes>> {
+        self.sender.upgrade().map(|sender| sender.subscribe())
+    }
+}
+
+pub type Stations = BTreeMap<String, Station>;
+
+/// What `open` prepared for `accept`.
+pub enum OpenedStream {
+    Track(OpenedTrack),
+    Station(Station),
+}
@@ -0,0 +1,10 @@
+/// Why `wave_streamer` returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamEnd {
+    /// The end of the track was reached.
+    Finished,
+    /// The peer sent `stop`; the session can open another track.
+    Stopped,
+    /// The peer closed the connection.
+    Closed,
+}